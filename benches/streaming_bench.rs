@@ -16,7 +16,7 @@ fn create_runtime() -> Runtime {
 
 /// Benchmark stream event serialization
 fn bench_event_serialization(c: &mut Criterion) {
-    let events = vec![
+    let events = [
         StreamEvent::McpServerStarted {
             server_id: "srv-12345678".to_string(),
             name: "Test Server".to_string(),
@@ -47,7 +47,7 @@ fn bench_event_serialization(c: &mut Criterion) {
 
     let mut group = c.benchmark_group("event_serialization");
 
-    for (i, event) in events.iter().enumerate() {
+    for event in events.iter() {
         let name = match event {
             StreamEvent::McpServerStarted { .. } => "server_started",
             StreamEvent::McpServerStopped { .. } => "server_stopped",
@@ -72,7 +72,7 @@ fn bench_event_filter_evaluation(c: &mut Criterion) {
         name: "Test Server".to_string(),
     };
 
-    let filters = vec![
+    let filters = [
         ("no_filter", EventFilters::default()),
         ("type_filter", EventFilters {
             event_types: Some(vec!["mcp_server_started".to_string()]),
@@ -241,7 +241,7 @@ fn bench_stream_manager_handle_mcp_event(c: &mut Criterion) {
 
 /// Benchmark stream event deserialization
 fn bench_event_deserialization(c: &mut Criterion) {
-    let json_events = vec![
+    let json_events = [
         (
             "server_started",
             r#"{"type":"mcp_server_started","server_id":"srv-1","name":"Test"}"#,
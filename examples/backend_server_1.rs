@@ -37,6 +37,7 @@ const SERVER_VERSION: &str = "1.0.0";
 /// JSON-RPC Request
 #[derive(Debug, Deserialize)]
 struct JsonRpcRequest {
+    #[allow(dead_code)]
     jsonrpc: String,
     id: Value,
     method: String,
@@ -99,6 +100,7 @@ struct Tool {
 
 /// Content item for tool results
 #[derive(Debug, Serialize)]
+#[allow(dead_code)]
 struct TextContent {
     #[serde(rename = "type")]
     content_type: String,
@@ -350,11 +352,9 @@ fn parse_args() -> u16 {
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
-            "--port" | "-p" => {
-                if i + 1 < args.len() {
-                    port = args[i + 1].parse().unwrap_or(3001);
-                    i += 1;
-                }
+            "--port" | "-p" if i + 1 < args.len() => {
+                port = args[i + 1].parse().unwrap_or(3001);
+                i += 1;
             }
             "--help" | "-h" => {
                 println!("Backend MCP Server #1 - Simple Tools Server");
@@ -37,6 +37,7 @@ const SERVER_VERSION: &str = "1.0.0";
 /// JSON-RPC Request
 #[derive(Debug, Deserialize)]
 struct JsonRpcRequest {
+    #[allow(dead_code)]
     jsonrpc: String,
     id: Value,
     method: String,
@@ -541,8 +542,7 @@ async fn read_resource(state: &Arc<ServerState>, uri: &str) -> Result<Value, Str
         }));
     }
 
-    if uri.starts_with("file://") {
-        let path = &uri[7..];
+    if let Some(path) = uri.strip_prefix("file://") {
         let files = state.virtual_files.read().await;
 
         match files.get(path) {
@@ -836,11 +836,9 @@ fn parse_args() -> u16 {
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
-            "--port" | "-p" => {
-                if i + 1 < args.len() {
-                    port = args[i + 1].parse().unwrap_or(3002);
-                    i += 1;
-                }
+            "--port" | "-p" if i + 1 < args.len() => {
+                port = args[i + 1].parse().unwrap_or(3002);
+                i += 1;
             }
             "--help" | "-h" => {
                 println!("Backend MCP Server #2 - Advanced Server");
@@ -38,6 +38,7 @@ struct McpServerInfo {
     id: String,
     name: String,
     url: String,
+    #[allow(dead_code)]
     protocol: String,
     is_active: bool,
 }
@@ -192,6 +193,7 @@ impl TestClient {
     }
 
     /// Get an MCP server by ID
+    #[allow(dead_code)]
     async fn get_server(&self, server_id: &str) -> Result<McpServerInfo, Box<dyn std::error::Error>> {
         let url = format!("{}/api/v1/mcp/servers/{}", self.base_url, server_id);
         let response = self
@@ -270,17 +272,13 @@ fn parse_args() -> (String, String) {
     let mut i = 1;
     while i < args.len() {
         match args[i].as_str() {
-            "--api-key" | "-k" => {
-                if i + 1 < args.len() {
-                    api_key = Some(args[i + 1].clone());
-                    i += 1;
-                }
+            "--api-key" | "-k" if i + 1 < args.len() => {
+                api_key = Some(args[i + 1].clone());
+                i += 1;
             }
-            "--url" | "-u" => {
-                if i + 1 < args.len() {
-                    server_url = args[i + 1].clone();
-                    i += 1;
-                }
+            "--url" | "-u" if i + 1 < args.len() => {
+                server_url = args[i + 1].clone();
+                i += 1;
             }
             "--help" | "-h" => {
                 println!("MetaMCP Test Client");
@@ -0,0 +1,33 @@
+-- Namespaces and tool renames/aliases for MCP servers
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS namespace VARCHAR(32);
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS tool_renames JSONB;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS tool_aliases JSONB;
+
+-- Backfill existing servers with a slug of their name. Later servers with the
+-- same slug get a prefix of their id appended rather than a counter, which
+-- could repeat the slug of another server (e.g. "a", "a" and "a 2").
+-- 24 + 1 + 7 characters fit the 32 character limit.
+UPDATE mcp_servers AS s
+SET namespace = numbered.base || CASE WHEN numbered.n > 1 THEN '-' || LEFT(numbered.id::text, 7) ELSE '' END
+FROM (
+    SELECT id, base, ROW_NUMBER() OVER (PARTITION BY base ORDER BY created_at, id) AS n
+    FROM (
+        SELECT
+            id,
+            created_at,
+            COALESCE(
+                NULLIF(TRIM(BOTH '-' FROM LEFT(LOWER(REGEXP_REPLACE(name, '[^a-zA-Z0-9]+', '-', 'g')), 24)), ''),
+                'server'
+            ) AS base
+        FROM mcp_servers
+    ) AS slugs
+) AS numbered
+WHERE s.id = numbered.id AND s.namespace IS NULL;
+
+ALTER TABLE mcp_servers ALTER COLUMN namespace SET NOT NULL;
+
+ALTER TABLE mcp_servers DROP CONSTRAINT IF EXISTS mcp_servers_namespace_check;
+ALTER TABLE mcp_servers ADD CONSTRAINT mcp_servers_namespace_check
+    CHECK (namespace ~ '^[a-z0-9]([a-z0-9-]*[a-z0-9])?$');
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mcp_servers_namespace ON mcp_servers(namespace);
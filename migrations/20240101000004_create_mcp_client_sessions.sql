@@ -0,0 +1,16 @@
+-- Sessions of MCP clients connected to the /mcp gateway
+-- Only written when MCP_SESSION_PERSIST is enabled, so sessions survive restarts
+CREATE TABLE IF NOT EXISTS mcp_client_sessions (
+    id UUID PRIMARY KEY,
+    -- API key that opened the session; only it may use or end the session
+    owner_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    protocol_version VARCHAR(32) NOT NULL,
+    capabilities JSONB NOT NULL DEFAULT '{}',
+    client_info JSONB,
+    initialized BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Index for expiring idle sessions
+CREATE INDEX IF NOT EXISTS idx_mcp_client_sessions_last_seen ON mcp_client_sessions(last_seen_at);
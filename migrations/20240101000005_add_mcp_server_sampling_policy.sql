@@ -0,0 +1,2 @@
+-- Whether a server's sampling/createMessage requests are relayed to clients
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS allow_sampling BOOLEAN NOT NULL DEFAULT true;
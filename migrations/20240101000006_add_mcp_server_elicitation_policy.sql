@@ -0,0 +1,2 @@
+-- Whether a server's elicitation/create requests are relayed to clients
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS allow_elicitation BOOLEAN NOT NULL DEFAULT true;
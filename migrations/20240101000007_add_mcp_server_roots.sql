@@ -0,0 +1,4 @@
+-- Roots a server sees: fixed roots answered by the gateway, or a scope that
+-- narrows the roots of connected clients
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS roots JSONB;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS roots_scope TEXT;
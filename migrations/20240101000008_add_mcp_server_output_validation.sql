@@ -0,0 +1,3 @@
+-- How a server's structuredContent is checked against its tools' outputSchema:
+-- enforce (refuse mismatches), warn (log them) or off
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS output_validation VARCHAR(16) NOT NULL DEFAULT 'warn';
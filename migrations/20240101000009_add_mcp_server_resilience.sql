@@ -0,0 +1,9 @@
+-- Per-server resilience settings: connect and request timeouts, retries of
+-- idempotent requests with jittered exponential backoff, and a circuit
+-- breaker opening after breaker_threshold consecutive failures (0 disables it)
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS connect_timeout_ms INTEGER NOT NULL DEFAULT 10000;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS request_timeout_ms INTEGER NOT NULL DEFAULT 30000;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS max_retries INTEGER NOT NULL DEFAULT 2;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS retry_backoff_ms INTEGER NOT NULL DEFAULT 200;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS breaker_threshold INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE mcp_servers ADD COLUMN IF NOT EXISTS breaker_cooldown_secs INTEGER NOT NULL DEFAULT 30;
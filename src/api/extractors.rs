@@ -0,0 +1,183 @@
+//! Request extractors for API handlers
+
+use crate::mcp::protocol::{
+    error_codes, JsonRpcError, JsonRpcMessage, JsonRpcResponse, RequestId, JSONRPC_VERSION,
+};
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+/// A message of a JSON-RPC body, or the error response it earned
+pub type JsonRpcItem = Result<JsonRpcMessage, Value>;
+
+/// JSON-RPC body of a POST to the MCP gateway: one message or a batch
+///
+/// A body that is not JSON is rejected with -32700, one that is not a
+/// JSON-RPC message with -32600 (-32602 if only its params are malformed),
+/// both as a JSON-RPC error with HTTP 400. Invalid elements of a batch are
+/// kept as their error response so the rest of the batch can still be served.
+pub enum JsonRpcBody {
+    Single(JsonRpcMessage),
+    Batch(Vec<JsonRpcItem>),
+}
+
+impl<S> FromRequest<S> for JsonRpcBody
+where
+    S: Send + Sync,
+{
+    type Rejection = JsonRpcRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| JsonRpcRejection {
+                status: e.status(),
+                error: json_rpc_error(None, parse_error(e.body_text())),
+            })?;
+
+        let value: Value = serde_json::from_slice(&bytes).map_err(|e| JsonRpcRejection {
+            status: StatusCode::BAD_REQUEST,
+            error: json_rpc_error(None, parse_error(e.to_string())),
+        })?;
+
+        match value {
+            Value::Array(batch) if batch.is_empty() => Err(JsonRpcRejection {
+                status: StatusCode::BAD_REQUEST,
+                error: json_rpc_error(None, invalid_request("empty batch")),
+            }),
+            Value::Array(batch) => Ok(Self::Batch(batch.into_iter().map(parse_message).collect())),
+            value => parse_message(value)
+                .map(Self::Single)
+                .map_err(|error| JsonRpcRejection {
+                    status: StatusCode::BAD_REQUEST,
+                    error,
+                }),
+        }
+    }
+}
+
+/// A body rejected by [`JsonRpcBody`]
+#[derive(Debug)]
+pub struct JsonRpcRejection {
+    pub status: StatusCode,
+    /// The JSON-RPC error response
+    pub error: Value,
+}
+
+impl IntoResponse for JsonRpcRejection {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.error)).into_response()
+    }
+}
+
+/// Error response to a message, under its id if it has a usable one
+pub fn json_rpc_error(id: Option<RequestId>, error: JsonRpcError) -> Value {
+    match id {
+        Some(id) => json!(JsonRpcResponse::error(
+            id,
+            error.code,
+            &error.message,
+            error.data
+        )),
+        None => json!({
+            "jsonrpc": JSONRPC_VERSION,
+            "id": null,
+            "error": error,
+        }),
+    }
+}
+
+/// -32600 Invalid Request with the given reason
+pub fn invalid_request(reason: &str) -> JsonRpcError {
+    JsonRpcError {
+        code: error_codes::INVALID_REQUEST,
+        message: format!("Invalid Request: {}", reason),
+        data: None,
+    }
+}
+
+fn parse_error(reason: String) -> JsonRpcError {
+    JsonRpcError {
+        code: error_codes::PARSE_ERROR,
+        message: format!("Parse error: {}", reason),
+        data: None,
+    }
+}
+
+/// Check a value is a well-formed JSON-RPC 2.0 message and classify it
+fn parse_message(value: Value) -> JsonRpcItem {
+    let Some(object) = value.as_object() else {
+        return Err(json_rpc_error(None, invalid_request("expected an object")));
+    };
+
+    let raw_id = object.get("id").filter(|id| !id.is_null());
+    let id = raw_id.and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok());
+    let reject = |error: JsonRpcError| Err(json_rpc_error(id.clone(), error));
+
+    if object.get("jsonrpc").and_then(|v| v.as_str()) != Some(JSONRPC_VERSION) {
+        return reject(invalid_request("jsonrpc must be \"2.0\""));
+    }
+    if raw_id.is_some() && id.is_none() {
+        return reject(invalid_request("id must be a string or an integer"));
+    }
+
+    // MCP only uses named params
+    let bad_params = object
+        .get("params")
+        .is_some_and(|p| !p.is_object() && !p.is_null());
+    match object.get("method") {
+        Some(Value::String(_)) if bad_params => {
+            return reject(JsonRpcError {
+                code: error_codes::INVALID_PARAMS,
+                message: "Invalid params: params must be an object".to_string(),
+                data: None,
+            });
+        }
+        Some(Value::String(_)) => {}
+        Some(_) => return reject(invalid_request("method must be a string")),
+        None if !object.contains_key("result") && !object.contains_key("error") => {
+            return reject(invalid_request("expected a method, result or error"));
+        }
+        None => {}
+    }
+
+    JsonRpcMessage::from_value(value)
+        .map_err(|e| json_rpc_error(id, invalid_request(&e.to_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_code(item: &JsonRpcItem) -> i64 {
+        item.as_ref().unwrap_err()["error"]["code"]
+            .as_i64()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let request = parse_message(json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}));
+        assert!(matches!(request, Ok(JsonRpcMessage::Request(_))));
+
+        let wrong_version = parse_message(json!({"jsonrpc": "1.0", "id": 7, "method": "ping"}));
+        assert_eq!(error_code(&wrong_version), -32600);
+        assert_eq!(wrong_version.unwrap_err()["id"], 7);
+
+        let no_method = parse_message(json!({"jsonrpc": "2.0", "id": 1}));
+        assert_eq!(error_code(&no_method), -32600);
+
+        let bad_params = parse_message(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": [1, 2]
+        }));
+        assert_eq!(error_code(&bad_params), -32602);
+
+        let not_object = parse_message(json!("ping"));
+        assert!(not_object.unwrap_err()["id"].is_null());
+    }
+}
@@ -5,13 +5,23 @@
 
 use crate::api::AppState;
 use crate::auth::AuthenticatedUser;
-use crate::db::models::{CreateMcpServerRequest, McpServerInfo, UpdateMcpServerRequest};
+use crate::db::models::{
+    CreateMcpServerRequest, McpServerInfo, OutputValidation, UpdateMcpServerRequest,
+};
+use crate::mcp::naming::slugify;
+use crate::mcp::protocol::Root;
+use crate::mcp::roots::validate_root_uri;
+use crate::mcp::schema;
+use crate::mcp::{
+    BreakerStatus, CancelSignal, NegotiatedSessionInfo, ServerNaming, ToolCallResult,
+};
 use crate::utils::{validate_url_for_ssrf, AppError};
 use axum::{
     extract::{Path, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -46,6 +56,17 @@ pub async fn list_mcp_servers(
     }))
 }
 
+/// MCP server details, including what the backend negotiated
+#[derive(Debug, Serialize, ToSchema)]
+pub struct McpServerDetails {
+    #[serde(flatten)]
+    pub server: McpServerInfo,
+    /// Result of the initialize handshake, if the backend is reachable
+    pub negotiated: Option<NegotiatedSessionInfo>,
+    /// State of the server's circuit breaker
+    pub breaker: BreakerStatus,
+}
+
 /// Get a specific MCP server
 #[utoipa::path(
     get,
@@ -55,7 +76,7 @@ pub async fn list_mcp_servers(
         ("server_id" = Uuid, Path, description = "MCP Server ID")
     ),
     responses(
-        (status = 200, description = "MCP server details", body = McpServerInfo),
+        (status = 200, description = "MCP server details", body = McpServerDetails),
         (status = 404, description = "Server not found"),
         (status = 401, description = "Unauthorized")
     ),
@@ -67,7 +88,7 @@ pub async fn get_mcp_server(
     State(state): State<AppState>,
     Path(server_id): Path<Uuid>,
     _user: AuthenticatedUser,
-) -> Result<Json<McpServerInfo>, AppError> {
+) -> Result<Json<McpServerDetails>, AppError> {
     let server = state
         .db
         .mcp_servers()
@@ -75,7 +96,25 @@ pub async fn get_mcp_server(
         .await?
         .ok_or_else(|| AppError::NotFound("MCP server not found".to_string()))?;
 
-    Ok(Json(server.into()))
+    // Reuse the gateway's session, connecting on demand for active servers
+    let negotiated = match state.proxy.session_info(server_id).await {
+        Some(info) => Some(info),
+        None if server.is_active => match state.proxy.session(&server).await {
+            Ok(session) => Some(session.info()),
+            Err(e) => {
+                tracing::warn!("Failed to connect to MCP server {}: {}", server.name, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let breaker = state.proxy.breaker_status(&server).await;
+
+    Ok(Json(McpServerDetails {
+        server: server.into(),
+        negotiated,
+        breaker,
+    }))
 }
 
 /// Create MCP server request schema for OpenAPI
@@ -96,6 +135,42 @@ pub struct CreateMcpServerSchema {
     pub args: Option<Vec<String>>,
     /// Environment variables
     pub env: Option<std::collections::HashMap<String, String>>,
+    /// Unique namespace prefixed to this server's tools and prompts
+    /// (derived from the name when omitted)
+    #[schema(example = "my-mcp-server")]
+    pub namespace: Option<String>,
+    /// Expose backend tools under different names (backend name → new name)
+    pub tool_renames: Option<std::collections::HashMap<String, String>>,
+    /// Extra global names accepted for a tool (alias → backend name)
+    pub tool_aliases: Option<std::collections::HashMap<String, String>>,
+    /// Relay the server's sampling requests to clients (default true)
+    pub allow_sampling: Option<bool>,
+    /// Relay the server's elicitation requests to clients (default true)
+    pub allow_elicitation: Option<bool>,
+    /// Fixed roots the server sees instead of the roots of clients
+    pub roots: Option<Vec<Root>>,
+    /// `file://` directory client roots are narrowed to
+    #[schema(example = "file:///home/user/project")]
+    pub roots_scope: Option<String>,
+    /// How structured tool results are checked against the tools'
+    /// `outputSchema` (default warn)
+    pub output_validation: Option<OutputValidation>,
+    /// Time allowed to connect to the server (default 10000)
+    #[schema(example = 10000)]
+    pub connect_timeout_ms: Option<i32>,
+    /// Time the server gets to answer a request (default 30000)
+    #[schema(example = 30000)]
+    pub request_timeout_ms: Option<i32>,
+    /// Retries of failed lists, `resources/read` and `ping` (default 2)
+    pub max_retries: Option<i32>,
+    /// Delay before the first retry, doubled with jitter on each further
+    /// one (default 200)
+    pub retry_backoff_ms: Option<i32>,
+    /// Consecutive failures that open the circuit breaker, 0 to disable it
+    /// (default 5)
+    pub breaker_threshold: Option<i32>,
+    /// Time an open circuit rejects requests before trying again (default 30)
+    pub breaker_cooldown_secs: Option<i32>,
 }
 
 /// Create a new MCP server
@@ -111,6 +186,7 @@ pub struct CreateMcpServerSchema {
         (status = 201, description = "MCP server created", body = McpServerInfo),
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Namespace or alias already in use"),
         (status = 422, description = "Security violation - URL blocked")
     ),
     security(
@@ -120,13 +196,35 @@ pub struct CreateMcpServerSchema {
 pub async fn create_mcp_server(
     State(state): State<AppState>,
     _user: AuthenticatedUser,
-    Json(payload): Json<CreateMcpServerRequest>,
+    Json(mut payload): Json<CreateMcpServerRequest>,
 ) -> Result<Json<McpServerInfo>, AppError> {
     // OWASP API7:2023 - Server Side Request Forgery (SSRF) Prevention
     // Validate URL to block localhost, private IPs, and cloud metadata endpoints
     validate_url_for_ssrf(&payload.url)?;
+    validate_roots(payload.roots.as_deref(), payload.roots_scope.as_deref())?;
+    validate_resilience([
+        payload.connect_timeout_ms,
+        payload.request_timeout_ms,
+        payload.max_retries,
+        payload.retry_backoff_ms,
+        payload.breaker_threshold,
+        payload.breaker_cooldown_secs,
+    ])?;
+
+    let naming = ServerNaming {
+        namespace: payload
+            .namespace
+            .take()
+            .unwrap_or_else(|| slugify(&payload.name)),
+        tool_renames: payload.tool_renames.clone().unwrap_or_default(),
+        tool_aliases: payload.tool_aliases.clone().unwrap_or_default(),
+    };
+    validate_naming(&state, None, &naming).await?;
+    payload.namespace = Some(naming.namespace);
 
     let server = state.db.mcp_servers().create(&payload).await?;
+    state.catalog.invalidate_server(server.id).await;
+
     Ok(Json(server.into()))
 }
 
@@ -147,6 +245,35 @@ pub struct UpdateMcpServerSchema {
     pub env: Option<std::collections::HashMap<String, String>>,
     /// Whether the server is active
     pub is_active: Option<bool>,
+    /// Unique namespace prefixed to this server's tools and prompts
+    pub namespace: Option<String>,
+    /// Expose backend tools under different names (backend name → new name)
+    pub tool_renames: Option<std::collections::HashMap<String, String>>,
+    /// Extra global names accepted for a tool (alias → backend name)
+    pub tool_aliases: Option<std::collections::HashMap<String, String>>,
+    /// Relay the server's sampling requests to clients
+    pub allow_sampling: Option<bool>,
+    /// Relay the server's elicitation requests to clients
+    pub allow_elicitation: Option<bool>,
+    /// Fixed roots the server sees instead of the roots of clients
+    /// (an empty list switches back to client roots)
+    pub roots: Option<Vec<Root>>,
+    /// `file://` directory client roots are narrowed to (empty to remove)
+    pub roots_scope: Option<String>,
+    /// How structured tool results are checked against the tools' `outputSchema`
+    pub output_validation: Option<OutputValidation>,
+    /// Time allowed to connect to the server
+    pub connect_timeout_ms: Option<i32>,
+    /// Time the server gets to answer a request
+    pub request_timeout_ms: Option<i32>,
+    /// Retries of failed lists, `resources/read` and `ping`
+    pub max_retries: Option<i32>,
+    /// Delay before the first retry
+    pub retry_backoff_ms: Option<i32>,
+    /// Consecutive failures that open the circuit breaker (0 disables it)
+    pub breaker_threshold: Option<i32>,
+    /// Time an open circuit rejects requests before trying again
+    pub breaker_cooldown_secs: Option<i32>,
 }
 
 /// Update an MCP server
@@ -165,6 +292,7 @@ pub struct UpdateMcpServerSchema {
         (status = 200, description = "MCP server updated", body = McpServerInfo),
         (status = 404, description = "Server not found"),
         (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Namespace or alias already in use"),
         (status = 422, description = "Security violation - URL blocked")
     ),
     security(
@@ -182,6 +310,39 @@ pub async fn update_mcp_server(
     if let Some(ref url) = payload.url {
         validate_url_for_ssrf(url)?;
     }
+    validate_roots(payload.roots.as_deref(), payload.roots_scope.as_deref())?;
+    validate_resilience([
+        payload.connect_timeout_ms,
+        payload.request_timeout_ms,
+        payload.max_retries,
+        payload.retry_backoff_ms,
+        payload.breaker_threshold,
+        payload.breaker_cooldown_secs,
+    ])?;
+
+    if payload.namespace.is_some()
+        || payload.tool_renames.is_some()
+        || payload.tool_aliases.is_some()
+    {
+        let existing = state
+            .db
+            .mcp_servers()
+            .find_by_id(server_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("MCP server not found".to_string()))?;
+
+        let mut naming = ServerNaming::from(&existing);
+        if let Some(ref namespace) = payload.namespace {
+            naming.namespace = namespace.clone();
+        }
+        if let Some(ref renames) = payload.tool_renames {
+            naming.tool_renames = renames.clone();
+        }
+        if let Some(ref aliases) = payload.tool_aliases {
+            naming.tool_aliases = aliases.clone();
+        }
+        validate_naming(&state, Some(server_id), &naming).await?;
+    }
 
     let server = state
         .db
@@ -190,9 +351,73 @@ pub async fn update_mcp_server(
         .await?
         .ok_or_else(|| AppError::NotFound("MCP server not found".to_string()))?;
 
+    // Drop any live connection so the next request uses the new configuration
+    state.proxy.disconnect(server_id).await;
+    state.catalog.invalidate_server(server_id).await;
+
     Ok(Json(server.into()))
 }
 
+/// Reject configured roots or a roots scope that are not `file://` directories
+///
+/// An empty scope is allowed, as it removes the scope.
+fn validate_roots(roots: Option<&[Root]>, scope: Option<&str>) -> Result<(), AppError> {
+    for root in roots.unwrap_or_default() {
+        validate_root_uri(&root.uri)?;
+    }
+    match scope {
+        Some(scope) if !scope.is_empty() => validate_root_uri(scope),
+        _ => Ok(()),
+    }
+}
+
+/// Allowed values of the resilience settings, in the order they are validated
+const RESILIENCE_LIMITS: [(&str, RangeInclusive<i32>); 6] = [
+    ("connect_timeout_ms", 1..=600_000),
+    ("request_timeout_ms", 1..=3_600_000),
+    ("max_retries", 0..=10),
+    ("retry_backoff_ms", 0..=60_000),
+    ("breaker_threshold", 0..=1000),
+    ("breaker_cooldown_secs", 1..=86_400),
+];
+
+/// Reject resilience settings outside [`RESILIENCE_LIMITS`]
+fn validate_resilience(values: [Option<i32>; 6]) -> Result<(), AppError> {
+    for ((name, range), value) in RESILIENCE_LIMITS.iter().zip(values) {
+        match value {
+            Some(value) if !range.contains(&value) => {
+                return Err(AppError::Validation(format!(
+                    "{} must be between {} and {}",
+                    name,
+                    range.start(),
+                    range.end()
+                )))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reject a naming configuration that is invalid or clashes with other servers
+async fn validate_naming(
+    state: &AppState,
+    server_id: Option<Uuid>,
+    naming: &ServerNaming,
+) -> Result<(), AppError> {
+    let others: Vec<ServerNaming> = state
+        .db
+        .mcp_servers()
+        .list_all(true)
+        .await?
+        .iter()
+        .filter(|s| Some(s.id) != server_id)
+        .map(ServerNaming::from)
+        .collect();
+
+    state.catalog.naming().validate(naming, &others)
+}
+
 /// Delete an MCP server
 #[utoipa::path(
     delete,
@@ -221,6 +446,9 @@ pub async fn delete_mcp_server(
         return Err(AppError::NotFound("MCP server not found".to_string()));
     }
 
+    state.proxy.disconnect(server_id).await;
+    state.catalog.invalidate_server(server_id).await;
+
     Ok(())
 }
 
@@ -234,25 +462,38 @@ pub struct McpToolRequest {
 /// MCP tool execution response
 #[derive(Debug, Serialize, ToSchema)]
 pub struct McpToolResponse {
-    /// Tool execution result
-    pub result: serde_json::Value,
+    /// Tool execution result, as returned by the MCP server
+    pub result: ToolCallResult,
+    /// Whether the tool itself reported a failure (`isError` in the result)
+    pub is_error: bool,
 }
 
 /// Execute a tool on an MCP server
+///
+/// A tool that runs but reports a failure still returns 200 with `is_error`
+/// set. Errors from the MCP server itself map to 400 (invalid params), 501
+/// (unknown method) or 502; an unreachable server is 502, a server whose
+/// circuit breaker is open is 503 and a server that does not answer in time
+/// is 504.
 #[utoipa::path(
     post,
     path = "/api/v1/mcp/servers/{server_id}/tools/{tool_name}/execute",
     tag = "mcp",
     params(
         ("server_id" = Uuid, Path, description = "MCP Server ID"),
-        ("tool_name" = String, Path, description = "Tool name to execute")
+        ("tool_name" = String, Path, description = "Tool name to execute, as the server names it")
     ),
     request_body = McpToolRequest,
     responses(
-        (status = 200, description = "Tool executed successfully", body = McpToolResponse),
+        (status = 200, description = "Tool executed (see is_error)", body = McpToolResponse),
+        (status = 400, description = "Invalid tool arguments"),
+        (status = 401, description = "Unauthorized"),
         (status = 404, description = "Server or tool not found"),
-        (status = 500, description = "Tool execution failed"),
-        (status = 401, description = "Unauthorized")
+        (status = 409, description = "Server is inactive"),
+        (status = 501, description = "Server does not support tool calls"),
+        (status = 502, description = "Server unreachable or returned an error"),
+        (status = 503, description = "Server skipped while its circuit breaker is open"),
+        (status = 504, description = "Server did not answer in time")
     ),
     security(
         ("bearer_auth" = [])
@@ -264,23 +505,62 @@ pub async fn execute_mcp_tool(
     _user: AuthenticatedUser,
     Json(payload): Json<McpToolRequest>,
 ) -> Result<Json<McpToolResponse>, AppError> {
-    // Verify server exists
-    let _server = state
+    let server = state
         .db
         .mcp_servers()
         .find_by_id(server_id)
         .await?
         .ok_or_else(|| AppError::NotFound("MCP server not found".to_string()))?;
 
-    // TODO: Implement actual MCP tool execution via MCP proxy
-    // For now, return a placeholder response
+    if !server.is_active {
+        return Err(AppError::Conflict("MCP server is inactive".to_string()));
+    }
+
+    // Give a clear 404 for unknown tools; if the list is unavailable, let the call decide
+    let tool = match state.catalog.tool(&server, &tool_name).await {
+        Ok(Some(tool)) => Some(tool),
+        Ok(None) => return Err(AppError::NotFound(format!("Tool '{}' not found", tool_name))),
+        Err(e) => {
+            tracing::debug!("Could not list tools on {}: {}", server.name, e);
+            None
+        }
+    };
+    if let Some(ref tool) = tool {
+        let violations = schema::check_arguments(tool, &payload.arguments);
+        if !violations.is_empty() {
+            return Err(AppError::Validation(format!(
+                "Invalid arguments for tool '{}': {}",
+                tool_name,
+                schema::join(&violations)
+            )));
+        }
+    }
+
     tracing::info!("Executing tool '{}' on server {}", tool_name, server_id);
 
+    let result = state
+        .proxy
+        .call_tool(
+            &server,
+            &tool_name,
+            payload.arguments,
+            None,
+            CancelSignal::never(),
+        )
+        .await?;
+
+    if let Some(ref tool) = tool {
+        schema::check_output(&server, tool, &result).map_err(|violations| {
+            AppError::Upstream(format!(
+                "Tool '{}' returned a result not matching its outputSchema: {}",
+                tool_name,
+                schema::join(&violations)
+            ))
+        })?;
+    }
+
     Ok(Json(McpToolResponse {
-        result: serde_json::json!({
-            "status": "not_implemented",
-            "message": format!("Tool execution for '{}' is not yet implemented", tool_name),
-            "arguments": payload.arguments
-        }),
+        is_error: result.is_error,
+        result,
     }))
 }
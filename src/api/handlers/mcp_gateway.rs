@@ -1,23 +1,34 @@
 //! MCP Gateway handler - implements the MCP protocol endpoint for Claude
 
+use crate::api::extractors::{invalid_request, json_rpc_error, JsonRpcBody, JsonRpcItem};
 use crate::api::AppState;
+use crate::auth::AuthenticatedUser;
 use crate::mcp::protocol::{
-    InitializeResult, JsonRpcRequest, JsonRpcResponse, PromptsCapability, ResourcesCapability,
-    ServerCapabilities, ServerInfo, ToolsCapability, MCP_PROTOCOL_VERSION,
+    error_codes, version, ClientCapabilities, CompletionsCapability, ElicitationCapability,
+    GetPromptParams, GetPromptResult, InitializeResult, JsonRpcMessage, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, PromptsCapability, ProtocolVersion, ReadResourceResult,
+    RequestId, ResourceUriParams, ResourcesCapability, RootsCapability, SamplingCapability,
+    ServerCapabilities, ServerInfo, ToolCallParams, ToolsCapability, MCP_PROTOCOL_VERSION,
+};
+use crate::mcp::schema;
+use crate::mcp::transport::streamable_http::SESSION_ID_HEADER;
+use crate::mcp::{
+    BackendSession, CancelSignal, CatalogKind, ClientSession, McpProxy, ProgressRegistration,
 };
-use crate::mcp::McpProxy;
 use crate::utils::AppError;
 use axum::{
     extract::State,
-    http::{header, HeaderMap, HeaderValue},
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::{sse::Event, IntoResponse, Response, Sse},
     Json,
 };
 use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
 use std::convert::Infallible;
 use std::sync::Arc;
+use uuid::Uuid;
 
 /// MCP Gateway state
 #[derive(Clone)]
@@ -27,45 +38,160 @@ pub struct McpGatewayState {
 }
 
 /// Handle MCP protocol requests at /mcp endpoint
+///
+/// Each session speaks the protocol revision negotiated in `initialize`,
+/// and results are adapted to it whatever revision the backends speak.
+/// Besides requests and notifications, clients POST their answers to
+/// requests relayed from backends over the GET /mcp stream. `initialize`
+/// opens a session; everything else must carry its `Mcp-Session-Id` and
+/// be sent with the API key that opened the session.
+/// `notifications/cancelled` aborts a request of the session still in
+/// flight and is passed on to the backend serving it, and
+/// `notifications/roots/list_changed` reaches every backend that sees
+/// client roots. Progress reported
+/// for a request's `_meta.progressToken` arrives on the GET /mcp stream.
+///
+/// A JSON-RPC batch (an array of messages) is dispatched concurrently and
+/// answered with an array holding a response for each request in it.
+/// Malformed bodies and failures are answered with JSON-RPC error objects.
 pub async fn mcp_gateway(
     State(state): State<AppState>,
-    Json(request): Json<JsonRpcRequest>,
+    user: AuthenticatedUser,
+    request_headers: HeaderMap,
+    body: JsonRpcBody,
+) -> Response {
+    let owner_id = match user.api_key_id() {
+        Ok(id) => id,
+        Err(e) => return error_reply(e),
+    };
+    let result = match body {
+        JsonRpcBody::Batch(batch) => handle_batch(&state, owner_id, &request_headers, batch).await,
+        JsonRpcBody::Single(message) => {
+            handle_message(&state, owner_id, &request_headers, message).await
+        }
+    };
+    result.unwrap_or_else(error_reply)
+}
+
+/// Handle a single JSON-RPC message
+async fn handle_message(
+    state: &AppState,
+    owner_id: Uuid,
+    headers: &HeaderMap,
+    message: JsonRpcMessage,
 ) -> Result<Response, AppError> {
-    let proxy = McpProxy::new();
+    if let JsonRpcMessage::Request(request) = &message {
+        if request.method == "initialize" {
+            return handle_initialize(state, owner_id, request).await;
+        }
+    }
 
-    tracing::debug!("MCP Gateway received: {} (id: {:?})", request.method, request.id);
+    let session = client_session(state, owner_id, headers).await?;
+    match dispatch(state, &session, message).await? {
+        Some(response) => Ok(reply(&response, session.version())),
+        None => Ok(accepted(session.version())),
+    }
+}
 
-    // Add MCP protocol headers
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "mcp-protocol-version",
-        HeaderValue::from_static(MCP_PROTOCOL_VERSION),
-    );
+/// Handle a JSON-RPC batch
+///
+/// `initialize` must be sent on its own, so it is refused inside a batch.
+/// A batch of only notifications and responses is accepted with 202.
+async fn handle_batch(
+    state: &AppState,
+    owner_id: Uuid,
+    headers: &HeaderMap,
+    batch: Vec<JsonRpcItem>,
+) -> Result<Response, AppError> {
+    let session = client_session(state, owner_id, headers).await?;
+    let replies = batch.into_iter().map(|item| {
+        let session = &session;
+        async move {
+            let message = match item {
+                Ok(JsonRpcMessage::Request(request)) if request.method == "initialize" => {
+                    let error = invalid_request("initialize must not be part of a batch");
+                    return Ok(Some(json_rpc_error(request.id, error)));
+                }
+                Ok(message) => message,
+                Err(error) => return Ok(Some(error)),
+            };
+            let response = dispatch(state, session, message).await?;
+            Ok::<_, AppError>(response.map(|r| json!(r)))
+        }
+    });
 
-    // Handle notifications (no id) - they don't expect a response
-    if request.id.is_none() {
-        match request.method.as_str() {
-            "initialized" | "notifications/cancelled" => {
-                // Return 202 Accepted for notifications
-                return Ok((headers, axum::http::StatusCode::ACCEPTED).into_response());
+    let replies = futures::future::join_all(replies)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    let replies: Vec<Value> = replies.into_iter().flatten().collect();
+
+    if replies.is_empty() {
+        return Ok(accepted(session.version()));
+    }
+    Ok(reply(&replies, session.version()))
+}
+
+/// Handle one message of an established session
+///
+/// Returns the response to a request; notifications and responses to
+/// relayed server requests get none.
+async fn dispatch(
+    state: &AppState,
+    session: &ClientSession,
+    message: JsonRpcMessage,
+) -> Result<Option<JsonRpcResponse>, AppError> {
+    let request = match message {
+        JsonRpcMessage::Request(request) => request,
+        JsonRpcMessage::Notification(notification) => {
+            tracing::debug!("MCP Gateway received notification: {}", notification.method);
+            match notification.method.as_str() {
+                "notifications/initialized" | "initialized" => {
+                    state.sessions.mark_initialized(session.id).await?;
+                }
+                "notifications/cancelled" => handle_cancelled(state, session, notification).await,
+                "notifications/roots/list_changed" => state.proxy.notify_roots_changed().await,
+                _ => {}
             }
-            _ => {
-                // Unknown notification - still return 202
-                return Ok((headers, axum::http::StatusCode::ACCEPTED).into_response());
+            return Ok(None);
+        }
+        JsonRpcMessage::Response(response) => {
+            let id = response.id.clone();
+            if !state.relay.complete(session.id, response).await {
+                tracing::debug!("Dropping client response for unknown id {}", id);
             }
+            return Ok(None);
         }
-    }
+    };
+
+    tracing::debug!("MCP Gateway received: {} (id: {:?})", request.method, request.id);
 
     // For requests with id, process and return response
-    let id = request.id.unwrap();
-    let response = match request.method.as_str() {
-        "initialize" => handle_initialize(id).await,
-        "tools/list" => handle_tools_list(&state, &proxy, id).await,
-        "tools/call" => handle_tools_call(&state, &proxy, id, request.params).await,
-        "resources/list" => handle_resources_list(&state, &proxy, id).await,
-        "resources/read" => handle_resources_read(&state, &proxy, id, request.params).await,
-        "prompts/list" => handle_prompts_list(&state, &proxy, id).await,
-        "prompts/get" => handle_prompts_get(&state, &proxy, id, request.params).await,
+    let Some(id) = request.id else {
+        return Ok(None);
+    };
+    let proxy = state.proxy.clone();
+    // Track the request so the client can cancel it; the guard forgets it once answered
+    let (_in_flight, cancel) = state.in_flight.register(session.id, id.clone()).await;
+    let mut params = request.params;
+    let _progress = route_progress(state, session, params.as_mut()).await;
+    let mut response = match request.method.as_str() {
+        "tools/list" => handle_tools_list(state, id, params).await,
+        "tools/call" => handle_tools_call(state, session, &proxy, id, params, cancel).await,
+        "resources/list" => handle_resources_list(state, id, params).await,
+        "resources/templates/list" => handle_resource_templates_list(state, id, params).await,
+        "resources/read" => {
+            handle_resources_read(state, session, &proxy, id, params, cancel).await
+        }
+        "resources/subscribe" => {
+            handle_resources_subscription(state, session, id, params, true).await
+        }
+        "resources/unsubscribe" => {
+            handle_resources_subscription(state, session, id, params, false).await
+        }
+        "prompts/list" => handle_prompts_list(state, id, params).await,
+        "prompts/get" => handle_prompts_get(state, session, &proxy, id, params, cancel).await,
+        "completion/complete" => handle_completion(state, &proxy, id, params, cancel).await,
         "ping" => handle_ping(id).await,
         _ => JsonRpcResponse::error(
             id,
@@ -75,20 +201,184 @@ pub async fn mcp_gateway(
         ),
     };
 
-    Ok((headers, Json(response)).into_response())
+    // Backends of any revision contribute to results, so adapt them to the client's
+    if let Some(result) = response.result.as_mut() {
+        version::adapt_result(&request.method, result, session.version());
+    }
+
+    Ok(Some(response))
+}
+
+/// JSON reply carrying the MCP protocol header
+fn reply<T: Serialize>(body: &T, version: ProtocolVersion) -> Response {
+    (protocol_headers(version), Json(body)).into_response()
+}
+
+/// 202 Accepted for notifications and responses, which get no answer
+fn accepted(version: ProtocolVersion) -> Response {
+    (protocol_headers(version), axum::http::StatusCode::ACCEPTED).into_response()
+}
+
+/// Headers announcing the protocol revision a response is in
+fn protocol_headers(version: ProtocolVersion) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "mcp-protocol-version",
+        HeaderValue::from_static(version.as_str()),
+    );
+    headers
+}
+
+/// A failure of the message as a whole, as a JSON-RPC error without id
+///
+/// Session problems keep the HTTP status the transport spec asks for (400
+/// for a missing session, 404 for an unknown one) so clients re-initialize.
+fn error_reply(error: AppError) -> Response {
+    let status = match &error {
+        AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
+        AppError::NotFound(_) => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    let mut response = reply(
+        &json_rpc_error(None, error.to_json_rpc_error()),
+        ProtocolVersion::LATEST,
+    );
+    *response.status_mut() = status;
+    response
+}
+
+/// Answer a request with the JSON-RPC form of an error
+fn error_response(id: RequestId, error: &AppError) -> JsonRpcResponse {
+    let error = error.to_json_rpc_error();
+    JsonRpcResponse::error(id, error.code, &error.message, error.data)
 }
 
-/// Handle initialize request
-async fn handle_initialize(id: crate::mcp::protocol::RequestId) -> JsonRpcResponse {
+/// Handle notifications/cancelled - abort one of the session's requests
+///
+/// Unknown or already answered ids are ignored, as the spec asks.
+async fn handle_cancelled(
+    state: &AppState,
+    session: &ClientSession,
+    notification: JsonRpcNotification,
+) {
+    let params = notification.params.unwrap_or_default();
+    let Some(id) = params
+        .get("requestId")
+        .and_then(|id| serde_json::from_value::<RequestId>(id.clone()).ok())
+    else {
+        tracing::debug!("Ignoring cancellation without a request id");
+        return;
+    };
+    let reason = params
+        .get("reason")
+        .and_then(|r| r.as_str())
+        .map(String::from);
+
+    if !state.in_flight.cancel(session.id, &id, reason).await {
+        tracing::debug!("Ignoring cancellation of request {} not in flight", id);
+    }
+}
+
+/// Swap the client's `_meta.progressToken` for one routed back to its session
+///
+/// The backend only ever sees the gateway's token, so tokens of different
+/// clients cannot collide on a shared backend session.
+async fn route_progress(
+    state: &AppState,
+    session: &ClientSession,
+    params: Option<&mut Value>,
+) -> Option<ProgressRegistration> {
+    let meta = params?.get_mut("_meta")?.as_object_mut()?;
+    let token = serde_json::from_value(meta.get("progressToken")?.clone()).ok()?;
+
+    let registration = state.relay.track_progress(session.id, token).await;
+    meta.insert("progressToken".to_string(), json!(registration.token()));
+    Some(registration)
+}
+
+/// The session a request belongs to, from its `Mcp-Session-Id` header
+///
+/// A missing header is a bad request; an unknown or expired session, or
+/// one opened with another API key, is 404 so the client knows to
+/// initialize again. An `MCP-Protocol-Version`
+/// header naming a revision the gateway does not speak is refused too.
+async fn client_session(
+    state: &AppState,
+    owner_id: Uuid,
+    headers: &HeaderMap,
+) -> Result<ClientSession, AppError> {
+    if let Some(requested) = headers.get("mcp-protocol-version") {
+        let requested = requested.to_str().unwrap_or_default();
+        if ProtocolVersion::parse(requested).is_none() {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported MCP-Protocol-Version: {}",
+                requested
+            )));
+        }
+    }
+
+    let header = headers.get(SESSION_ID_HEADER).ok_or_else(|| {
+        AppError::BadRequest("Missing Mcp-Session-Id header; send initialize first".to_string())
+    })?;
+
+    let session = match header.to_str().ok().and_then(|v| Uuid::parse_str(v).ok()) {
+        Some(id) => state.sessions.get(id, owner_id).await?,
+        None => None,
+    };
+
+    session.ok_or_else(|| AppError::NotFound("MCP session not found".to_string()))
+}
+
+/// Handle initialize request, opening a new session
+///
+/// The client gets the revision it asked for when the gateway speaks it,
+/// and the latest revision otherwise.
+async fn handle_initialize(
+    state: &AppState,
+    owner_id: Uuid,
+    request: &JsonRpcRequest,
+) -> Result<Response, AppError> {
+    let id = request.id.clone().unwrap_or(0i64.into());
+    let params = request.params.as_ref();
+    let capabilities = params
+        .and_then(|p| p.get("capabilities"))
+        .and_then(|c| serde_json::from_value(c.clone()).ok())
+        .unwrap_or_default();
+    let client_info = params
+        .and_then(|p| p.get("clientInfo"))
+        .and_then(|c| serde_json::from_value(c.clone()).ok());
+    let requested = params
+        .and_then(|p| p.get("protocolVersion"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let version = ProtocolVersion::negotiate(requested);
+
+    let session = state
+        .sessions
+        .create(owner_id, version.as_str(), capabilities, client_info)
+        .await?;
+    offer_client_capabilities(state).await;
+
+    // Completions are only worth advertising if some backend can answer them;
+    // only backends already connected are asked, so initialize never waits
+    let completions = state
+        .proxy
+        .any_session(BackendSession::supports_completions)
+        .await
+        .then_some(CompletionsCapability {});
+
     let result = InitializeResult {
-        protocol_version: MCP_PROTOCOL_VERSION.to_string(),
+        protocol_version: version.to_string(),
         capabilities: ServerCapabilities {
-            tools: Some(ToolsCapability { list_changed: false }),
+            // Backend list_changed notifications are relayed on the GET /mcp stream,
+            // as are updates of subscribed resources
+            tools: Some(ToolsCapability { list_changed: true }),
             resources: Some(ResourcesCapability {
-                subscribe: false,
-                list_changed: false,
+                subscribe: true,
+                list_changed: true,
             }),
-            prompts: Some(PromptsCapability { list_changed: false }),
+            prompts: Some(PromptsCapability { list_changed: true }),
+            completions,
         },
         server_info: ServerInfo {
             name: "metamcp".to_string(),
@@ -96,307 +386,458 @@ async fn handle_initialize(id: crate::mcp::protocol::RequestId) -> JsonRpcRespon
         },
     };
 
-    JsonRpcResponse::success(id, serde_json::to_value(result).unwrap())
+    let response = JsonRpcResponse::success(id, serde_json::to_value(result).unwrap());
+
+    let mut headers = protocol_headers(version);
+    if let Ok(value) = HeaderValue::from_str(&session.id.to_string()) {
+        headers.insert(SESSION_ID_HEADER, value);
+    }
+
+    Ok((headers, Json(response)).into_response())
+}
+
+/// Offer backends the client capabilities some live session declared
+///
+/// Backends are only told about sampling, elicitation and roots while a
+/// client that can answer `sampling/createMessage`, `elicitation/create` or
+/// `roots/list` is around.
+async fn offer_client_capabilities(state: &AppState) {
+    let sampling = state
+        .sessions
+        .any(|s| s.capabilities.sampling.is_some())
+        .await;
+    let elicitation = state
+        .sessions
+        .any(|s| s.capabilities.elicitation.is_some())
+        .await;
+    let roots = state
+        .sessions
+        .any(|s| s.capabilities.roots.is_some())
+        .await;
+    let capabilities = ClientCapabilities {
+        // Clients' roots can change at any time and the change is passed on
+        roots: roots.then_some(RootsCapability { list_changed: true }),
+        sampling: sampling.then_some(SamplingCapability {}),
+        elicitation: elicitation.then_some(ElicitationCapability {}),
+    };
+    state.proxy.offer_client_capabilities(capabilities).await;
 }
 
 /// Handle tools/list - aggregate tools from all backend servers
 async fn handle_tools_list(
     state: &AppState,
-    proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
 ) -> JsonRpcResponse {
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
-        }
-    };
-
-    let mut all_tools: Vec<Value> = Vec::new();
-    let mut tool_server_map: HashMap<String, String> = HashMap::new();
-
-    for server in &servers {
-        match proxy.list_tools(server).await {
-            Ok(tools) => {
-                for tool in tools {
-                    if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
-                        // Prefix tool name with server name to avoid collisions
-                        let prefixed_name = format!("{}_{}", server.name, name);
-                        let mut tool_with_prefix = tool.clone();
-                        if let Some(obj) = tool_with_prefix.as_object_mut() {
-                            obj.insert("name".to_string(), json!(prefixed_name.clone()));
-                            // Add original name and server info for routing
-                            obj.insert("_original_name".to_string(), json!(name));
-                            obj.insert("_server_id".to_string(), json!(server.id.to_string()));
-                        }
-                        tool_server_map.insert(prefixed_name.clone(), server.id.to_string());
-                        all_tools.push(tool_with_prefix);
-                    }
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Failed to list tools from {}: {}", server.name, e);
-            }
-        }
-    }
-
-    JsonRpcResponse::success(id, json!({ "tools": all_tools }))
+    list_catalog(state, id, CatalogKind::Tools, params).await
 }
 
 /// Handle tools/call - route to appropriate backend server
 async fn handle_tools_call(
     state: &AppState,
+    session: &ClientSession,
     proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
     params: Option<Value>,
+    cancel: CancelSignal,
 ) -> JsonRpcResponse {
-    let params = match params {
-        Some(p) => p,
-        None => return JsonRpcResponse::error(id, -32602, "Missing params", None),
+    let params: ToolCallParams = match parse_params(params) {
+        Ok(p) => p,
+        Err(message) => return JsonRpcResponse::error(id, -32602, &message, None),
     };
-
-    let tool_name = match params.get("name").and_then(|n| n.as_str()) {
-        Some(n) => n,
-        None => return JsonRpcResponse::error(id, -32602, "Missing tool name", None),
+    let arguments = match params.arguments {
+        Value::Null => json!({}),
+        arguments => arguments,
     };
 
-    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
-
-    // Parse the prefixed tool name to find server and original tool name
-    // Format: servername_toolname
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
+    let route = match state
+        .catalog
+        .resolve(CatalogKind::Tools, &params.name)
+        .await
+    {
+        Ok(Some(route)) => route,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Unknown tool: {}", params.name),
+                None,
+            )
         }
+        Err(e) => return error_response(id, &e),
     };
 
-    // Find matching server by prefix
-    for server in &servers {
-        let prefix = format!("{}_", server.name);
-        if tool_name.starts_with(&prefix) {
-            let original_tool_name = &tool_name[prefix.len()..];
-            match proxy.call_tool(server, original_tool_name, arguments).await {
-                Ok(result) => return JsonRpcResponse::success(id, result),
-                Err(e) => {
-                    return JsonRpcResponse::error(
-                        id,
-                        -32000,
-                        &format!("Tool call failed: {}", e),
-                        None,
-                    )
-                }
-            }
+    // Without the tool's definition at hand the backend judges the call alone
+    let tool = state
+        .catalog
+        .tool(&route.server, &route.original)
+        .await
+        .ok()
+        .flatten();
+    if let Some(ref tool) = tool {
+        let violations = schema::check_arguments(tool, &arguments);
+        if !violations.is_empty() {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!(
+                    "Invalid arguments for tool {}: {}",
+                    params.name,
+                    schema::join(&violations)
+                ),
+                Some(json!({ "violations": violations })),
+            );
         }
     }
 
-    JsonRpcResponse::error(id, -32602, &format!("Unknown tool: {}", tool_name), None)
+    // Sampling and elicitation the tool needs go back to this session
+    let _call = state.relay.track_call(route.server.id, session.id).await;
+    let result = match proxy
+        .call_tool(
+            &route.server,
+            &route.original,
+            arguments,
+            params.meta,
+            cancel,
+        )
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => return error_response(id, &e),
+    };
+
+    if let Some(ref tool) = tool {
+        if let Err(violations) = schema::check_output(&route.server, tool, &result) {
+            return JsonRpcResponse::error(
+                id,
+                error_codes::UPSTREAM_ERROR,
+                &format!(
+                    "Tool {} returned a result not matching its outputSchema: {}",
+                    params.name,
+                    schema::join(&violations)
+                ),
+                Some(json!({ "violations": violations })),
+            );
+        }
+    }
+    JsonRpcResponse::success(id, json!(result))
 }
 
 /// Handle resources/list - aggregate resources from all backend servers
 async fn handle_resources_list(
     state: &AppState,
-    proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
 ) -> JsonRpcResponse {
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
-        }
-    };
-
-    let mut all_resources: Vec<Value> = Vec::new();
-
-    for server in &servers {
-        match proxy.list_resources(server).await {
-            Ok(resources) => {
-                for resource in resources {
-                    // Add server prefix to URI to avoid collisions
-                    let mut resource_with_server = resource.clone();
-                    if let Some(obj) = resource_with_server.as_object_mut() {
-                        let uri_opt = obj.get("uri").and_then(|u| u.as_str()).map(|s| s.to_string());
-                        if let Some(uri) = uri_opt {
-                            let prefixed_uri = format!("{}:{}", server.name, uri);
-                            obj.insert("uri".to_string(), json!(prefixed_uri));
-                            obj.insert("_original_uri".to_string(), json!(uri));
-                            obj.insert("_server_id".to_string(), json!(server.id.to_string()));
-                        }
-                    }
-                    all_resources.push(resource_with_server);
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Failed to list resources from {}: {}", server.name, e);
-            }
-        }
-    }
+    list_catalog(state, id, CatalogKind::Resources, params).await
+}
 
-    JsonRpcResponse::success(id, json!({ "resources": all_resources }))
+/// Handle resources/templates/list - aggregate resource templates from all backend servers
+async fn handle_resource_templates_list(
+    state: &AppState,
+    id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
+) -> JsonRpcResponse {
+    list_catalog(state, id, CatalogKind::ResourceTemplates, params).await
 }
 
 /// Handle resources/read - route to appropriate backend server
 async fn handle_resources_read(
     state: &AppState,
+    session: &ClientSession,
     proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
     params: Option<Value>,
+    cancel: CancelSignal,
 ) -> JsonRpcResponse {
-    let params = match params {
-        Some(p) => p,
-        None => return JsonRpcResponse::error(id, -32602, "Missing params", None),
-    };
-
-    let uri = match params.get("uri").and_then(|u| u.as_str()) {
-        Some(u) => u,
-        None => return JsonRpcResponse::error(id, -32602, "Missing uri", None),
+    let params: ResourceUriParams = match parse_params(params) {
+        Ok(p) => p,
+        Err(message) => return JsonRpcResponse::error(id, -32602, &message, None),
     };
 
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
+    let route = match state.catalog.resolve_resource(&params.uri).await {
+        Ok(Some(route)) => route,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Unknown resource: {}", params.uri),
+                None,
+            )
         }
+        Err(e) => return error_response(id, &e),
     };
 
-    // Parse prefixed URI: servername:original_uri
-    for server in &servers {
-        let prefix = format!("{}:", server.name);
-        if uri.starts_with(&prefix) {
-            let original_uri = &uri[prefix.len()..];
+    let _call = state.relay.track_call(route.server.id, session.id).await;
+    let params = ResourceUriParams {
+        uri: route.original,
+        meta: params.meta,
+    };
+    let request =
+        crate::mcp::protocol::JsonRpcRequest::new(1i64, "resources/read", Some(json!(params)));
+
+    match proxy
+        .forward_cancellable(&route.server, request, cancel)
+        .await
+    {
+        Ok(response) => relay_result::<ReadResourceResult>(id, response),
+        Err(e) => error_response(id, &e),
+    }
+}
 
-            let request = crate::mcp::protocol::JsonRpcRequest::new(
-                1i64,
-                "resources/read",
-                Some(json!({ "uri": original_uri })),
-            );
+/// Handle resources/subscribe and resources/unsubscribe
+///
+/// The prefixed URI is resolved to its backend, which is subscribed under
+/// its own URI; updates arrive on the GET /mcp stream.
+async fn handle_resources_subscription(
+    state: &AppState,
+    session: &ClientSession,
+    id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
+    subscribe: bool,
+) -> JsonRpcResponse {
+    let params: ResourceUriParams = match parse_params(params) {
+        Ok(p) => p,
+        Err(message) => return JsonRpcResponse::error(id, -32602, &message, None),
+    };
 
-            match proxy.forward_request(server, request).await {
-                Ok(response) => {
-                    if let Some(result) = response.result {
-                        return JsonRpcResponse::success(id, result);
-                    } else if let Some(error) = response.error {
-                        return JsonRpcResponse::error(id, error.code, &error.message, error.data);
-                    }
-                }
-                Err(e) => {
-                    return JsonRpcResponse::error(
-                        id,
-                        -32000,
-                        &format!("Resource read failed: {}", e),
-                        None,
-                    )
-                }
-            }
+    let route = match state.catalog.resolve_resource(&params.uri).await {
+        Ok(Some(route)) => route,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Unknown resource: {}", params.uri),
+                None,
+            )
         }
-    }
+        Err(e) => return error_response(id, &e),
+    };
+
+    let subscriptions = &state.subscriptions;
+    let result = if subscribe {
+        subscriptions
+            .subscribe(session.id, &route.server, &route.original)
+            .await
+    } else {
+        subscriptions
+            .unsubscribe(session.id, &route.server, &route.original)
+            .await
+    };
 
-    JsonRpcResponse::error(id, -32602, &format!("Unknown resource: {}", uri), None)
+    match result {
+        Ok(()) => JsonRpcResponse::success(id, json!({})),
+        Err(e) => error_response(id, &e),
+    }
 }
 
 /// Handle prompts/list - aggregate prompts from all backend servers
 async fn handle_prompts_list(
     state: &AppState,
+    id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
+) -> JsonRpcResponse {
+    list_catalog(state, id, CatalogKind::Prompts, params).await
+}
+
+/// Handle prompts/get - route to appropriate backend server
+async fn handle_prompts_get(
+    state: &AppState,
+    session: &ClientSession,
     proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
+    params: Option<Value>,
+    cancel: CancelSignal,
 ) -> JsonRpcResponse {
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
-        }
+    let mut params: GetPromptParams = match parse_params(params) {
+        Ok(p) => p,
+        Err(message) => return JsonRpcResponse::error(id, -32602, &message, None),
     };
 
-    let mut all_prompts: Vec<Value> = Vec::new();
-
-    for server in &servers {
-        match proxy.list_prompts(server).await {
-            Ok(prompts) => {
-                for prompt in prompts {
-                    let mut prompt_with_server = prompt.clone();
-                    if let Some(obj) = prompt_with_server.as_object_mut() {
-                        let name_opt = obj.get("name").and_then(|n| n.as_str()).map(|s| s.to_string());
-                        if let Some(name) = name_opt {
-                            let prefixed_name = format!("{}_{}", server.name, name);
-                            obj.insert("name".to_string(), json!(prefixed_name));
-                            obj.insert("_original_name".to_string(), json!(name));
-                            obj.insert("_server_id".to_string(), json!(server.id.to_string()));
-                        }
-                    }
-                    all_prompts.push(prompt_with_server);
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Failed to list prompts from {}: {}", server.name, e);
-            }
+    let route = match state
+        .catalog
+        .resolve(CatalogKind::Prompts, &params.name)
+        .await
+    {
+        Ok(Some(route)) => route,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Unknown prompt: {}", params.name),
+                None,
+            )
         }
-    }
+        Err(e) => return error_response(id, &e),
+    };
 
-    JsonRpcResponse::success(id, json!({ "prompts": all_prompts }))
+    let _call = state.relay.track_call(route.server.id, session.id).await;
+    params.name = route.original;
+    let request =
+        crate::mcp::protocol::JsonRpcRequest::new(1i64, "prompts/get", Some(json!(params)));
+
+    match proxy
+        .forward_cancellable(&route.server, request, cancel)
+        .await
+    {
+        Ok(response) => relay_result::<GetPromptResult>(id, response),
+        Err(e) => error_response(id, &e),
+    }
 }
 
-/// Handle prompts/get - route to appropriate backend server
-async fn handle_prompts_get(
+/// Handle completion/complete - route to the backend owning the referenced prompt or resource
+async fn handle_completion(
     state: &AppState,
     proxy: &McpProxy,
     id: crate::mcp::protocol::RequestId,
     params: Option<Value>,
+    cancel: CancelSignal,
 ) -> JsonRpcResponse {
-    let params = match params {
+    let mut params = match params {
         Some(p) => p,
         None => return JsonRpcResponse::error(id, -32602, "Missing params", None),
     };
 
-    let prompt_name = match params.get("name").and_then(|n| n.as_str()) {
-        Some(n) => n,
-        None => return JsonRpcResponse::error(id, -32602, "Missing prompt name", None),
+    let ref_type = params.pointer("/ref/type").and_then(|t| t.as_str());
+    let (key, kind) = match ref_type {
+        Some("ref/prompt") => ("name", CatalogKind::Prompts),
+        Some("ref/resource") => ("uri", CatalogKind::ResourceTemplates),
+        _ => return JsonRpcResponse::error(id, -32602, "Invalid completion reference", None),
+    };
+    let exposed = match params["ref"].get(key).and_then(|v| v.as_str()) {
+        Some(v) => v.to_string(),
+        None => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Missing reference {}", key),
+                None,
+            )
+        }
     };
 
-    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
-
-    let servers = match state.db.mcp_servers().list_all(false).await {
-        Ok(s) => s,
-        Err(e) => {
-            return JsonRpcResponse::error(id, -32000, &format!("Database error: {}", e), None)
+    // A resource reference names a template or, less often, a concrete URI
+    let route = match state.catalog.resolve(kind, &exposed).await {
+        Ok(None) if kind == CatalogKind::ResourceTemplates => {
+            state.catalog.resolve_resource(&exposed).await
         }
+        other => other,
+    };
+    let route = match route {
+        Ok(Some(route)) => route,
+        Ok(None) => {
+            return JsonRpcResponse::error(
+                id,
+                -32602,
+                &format!("Unknown reference: {}", exposed),
+                None,
+            )
+        }
+        Err(e) => return error_response(id, &e),
     };
 
-    // Parse prefixed prompt name
-    for server in &servers {
-        let prefix = format!("{}_", server.name);
-        if prompt_name.starts_with(&prefix) {
-            let original_name = &prompt_name[prefix.len()..];
-
-            let request = crate::mcp::protocol::JsonRpcRequest::new(
-                1i64,
-                "prompts/get",
-                Some(json!({
-                    "name": original_name,
-                    "arguments": arguments
-                })),
-            );
+    match proxy.session(&route.server).await {
+        Ok(session) if !session.supports_completions() => {
+            return JsonRpcResponse::error(
+                id,
+                -32601,
+                &format!("Server {} does not support completions", route.server.name),
+                None,
+            )
+        }
+        Ok(_) => {}
+        Err(e) => return error_response(id, &e),
+    }
 
-            match proxy.forward_request(server, request).await {
-                Ok(response) => {
-                    if let Some(result) = response.result {
-                        return JsonRpcResponse::success(id, result);
-                    } else if let Some(error) = response.error {
-                        return JsonRpcResponse::error(id, error.code, &error.message, error.data);
-                    }
-                }
-                Err(e) => {
-                    return JsonRpcResponse::error(
-                        id,
-                        -32000,
-                        &format!("Prompt get failed: {}", e),
-                        None,
-                    )
-                }
+    params["ref"][key] = json!(route.original);
+    let request =
+        crate::mcp::protocol::JsonRpcRequest::new(1i64, "completion/complete", Some(params));
+
+    match proxy
+        .forward_cancellable(&route.server, request, cancel)
+        .await
+    {
+        Ok(response) => relay_response(id, response),
+        Err(e) => error_response(id, &e),
+    }
+}
+
+/// Answer a list request with a page of the catalog
+async fn list_catalog(
+    state: &AppState,
+    id: crate::mcp::protocol::RequestId,
+    kind: CatalogKind,
+    params: Option<Value>,
+) -> JsonRpcResponse {
+    let cursor = match params.as_ref().and_then(|p| p.get("cursor")) {
+        None | Some(Value::Null) => None,
+        Some(Value::String(cursor)) => Some(cursor.as_str()),
+        Some(_) => return JsonRpcResponse::error(id, -32602, "Invalid cursor", None),
+    };
+
+    let page = state.catalog.view(kind).await.and_then(|view| {
+        let meta = view.meta();
+        Ok((view.page(kind, cursor, state.catalog.page_size())?, meta))
+    });
+    match page {
+        Ok((page, meta)) => {
+            let mut result = json!({ kind.result_key(): page.items });
+            if let Some(next_cursor) = page.next_cursor {
+                result["nextCursor"] = json!(next_cursor);
             }
+            JsonRpcResponse::success(id, with_meta(result, meta))
+        }
+        Err(e) => error_response(id, &e),
+    }
+}
+
+/// Pass a backend's result or error through under the client's id
+fn relay_response(
+    id: crate::mcp::protocol::RequestId,
+    response: JsonRpcResponse,
+) -> JsonRpcResponse {
+    match (response.result, response.error) {
+        (_, Some(error)) => JsonRpcResponse::error(id, error.code, &error.message, error.data),
+        (Some(result), None) => JsonRpcResponse::success(id, result),
+        (None, None) => {
+            let error = AppError::Upstream("Empty response from MCP server".to_string());
+            error_response(id, &error)
+        }
+    }
+}
+
+/// Pass a backend's result through as a `T`, or its error, under the client's id
+///
+/// A result that does not parse as `T` is the backend's fault.
+fn relay_result<T: DeserializeOwned + Serialize>(
+    id: crate::mcp::protocol::RequestId,
+    response: JsonRpcResponse,
+) -> JsonRpcResponse {
+    let mut response = relay_response(id, response);
+    let Some(result) = response.result.take() else {
+        return response;
+    };
+    match serde_json::from_value::<T>(result) {
+        Ok(result) => JsonRpcResponse::success(response.id, json!(result)),
+        Err(e) => {
+            let error = AppError::Upstream(format!("Invalid result from MCP server: {}", e));
+            error_response(response.id, &error)
         }
     }
+}
 
-    JsonRpcResponse::error(id, -32602, &format!("Unknown prompt: {}", prompt_name), None)
+/// Parse a request's params, or explain why they are invalid
+fn parse_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T, String> {
+    let params = params.ok_or_else(|| "Missing params".to_string())?;
+    serde_json::from_value(params).map_err(|e| format!("Invalid params: {}", e))
+}
+
+/// Attach a `_meta` object to a result, if there is one
+fn with_meta(mut result: Value, meta: Option<Value>) -> Value {
+    if let (Some(meta), Some(obj)) = (meta, result.as_object_mut()) {
+        obj.insert("_meta".to_string(), meta);
+    }
+    result
 }
 
 /// Handle ping request
@@ -422,9 +863,16 @@ pub async fn mcp_health() -> Json<McpHealthResponse> {
 
 /// Handle GET requests to /mcp - returns persistent SSE stream for MCP protocol
 /// This is required by Claude Code's HTTP transport for server-to-client messages
+///
+/// Notifications and requests from backends are relayed on this stream for
+/// as long as the client stays connected.
 pub async fn mcp_gateway_sse(
-    State(_state): State<AppState>,
-) -> impl IntoResponse {
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    request_headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let session = client_session(&state, user.api_key_id()?, &request_headers).await?;
+
     // Create a persistent SSE stream that stays open
     // First send an endpoint event, then keep the connection alive with periodic pings
     let endpoint_msg = json!({
@@ -440,24 +888,49 @@ pub async fn mcp_gateway_sse(
         Ok::<_, Infallible>(Event::default().data(endpoint_msg.to_string()))
     });
 
-    // Create a keep-alive stream that sends pings every 30 seconds to keep connection open
-    let keep_alive = stream::unfold((), |_| async {
-        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-        Some((Ok::<_, Infallible>(Event::default().comment("ping")), ()))
+    // Relay backend messages until the client disconnects, which unregisters it
+    let connection = state
+        .relay
+        .connect(session.id, session.capabilities.clone())
+        .await;
+    tracing::debug!(client_id = %connection.id(), "MCP client opened event stream");
+    let messages = stream::unfold(connection, |mut connection| async move {
+        let message = connection.recv().await?;
+        Some((
+            Ok::<_, Infallible>(Event::default().data(message.to_string())),
+            connection,
+        ))
     });
 
-    let combined = initial.chain(keep_alive);
+    let combined = initial.chain(messages);
 
     // Add MCP protocol version header to SSE response
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "mcp-protocol-version",
-        HeaderValue::from_static(MCP_PROTOCOL_VERSION),
-    );
+    let headers = protocol_headers(session.version());
 
-    (headers, Sse::new(combined).keep_alive(
+    let sse = Sse::new(combined).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(std::time::Duration::from_secs(15))
             .text("ping"),
-    ))
+    );
+
+    Ok((headers, sse).into_response())
+}
+
+/// Handle DELETE requests to /mcp - ends the client's session
+///
+/// Any open GET /mcp stream of the session is closed and its resource
+/// subscriptions are dropped.
+pub async fn mcp_gateway_delete(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    request_headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    let session = client_session(&state, user.api_key_id()?, &request_headers).await?;
+
+    state.sessions.remove(session.id).await?;
+    state.relay.end_session(session.id).await;
+    state.subscriptions.end_session(session.id).await;
+    offer_client_capabilities(&state).await;
+
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -9,6 +9,6 @@ pub use auth::{authenticate, AuthRequest, AuthResponse};
 pub use health::{health_check, HealthResponse};
 pub use mcp::{
     create_mcp_server, delete_mcp_server, execute_mcp_tool, get_mcp_server, list_mcp_servers,
-    update_mcp_server, ListMcpServersResponse, McpToolRequest, McpToolResponse,
+    update_mcp_server, ListMcpServersResponse, McpServerDetails, McpToolRequest, McpToolResponse,
 };
 pub use mcp_gateway::mcp_gateway;
@@ -3,12 +3,17 @@
 //! This module provides the REST API for MetaMCP with security hardening
 //! based on OWASP API Security Top 10 guidelines.
 
+pub mod extractors;
 pub mod handlers;
 pub mod middleware;
 pub mod routes;
 
 use crate::auth::AuthService;
 use crate::db::Database;
+use crate::mcp::{
+    SharedCatalog, SharedClientRelay, SharedClientSessions, SharedInFlightRequests,
+    SharedMcpProxy, SharedResourceSubscriptions,
+};
 use axum::{
     http::{header, Method, StatusCode},
     response::{IntoResponse, Json},
@@ -26,6 +31,12 @@ use utoipa_swagger_ui::SwaggerUi;
 pub struct AppState {
     pub db: Database,
     pub auth: Arc<AuthService>,
+    pub proxy: SharedMcpProxy,
+    pub catalog: SharedCatalog,
+    pub relay: SharedClientRelay,
+    pub sessions: SharedClientSessions,
+    pub in_flight: SharedInFlightRequests,
+    pub subscriptions: SharedResourceSubscriptions,
 }
 
 /// OpenAPI documentation
@@ -47,11 +58,26 @@ pub struct AppState {
             handlers::auth::AuthRequest,
             handlers::auth::AuthResponse,
             handlers::mcp::ListMcpServersResponse,
+            handlers::mcp::McpServerDetails,
             handlers::mcp::McpToolRequest,
             handlers::mcp::McpToolResponse,
             handlers::mcp::CreateMcpServerSchema,
             handlers::mcp::UpdateMcpServerSchema,
             crate::db::models::McpServerInfo,
+            crate::db::models::OutputValidation,
+            crate::mcp::NegotiatedSessionInfo,
+            crate::mcp::BreakerStatus,
+            crate::mcp::BreakerState,
+            crate::mcp::ToolCallResult,
+            crate::mcp::Content,
+            crate::mcp::TextContent,
+            crate::mcp::MediaContent,
+            crate::mcp::EmbeddedResource,
+            crate::mcp::Resource,
+            crate::mcp::ResourceContent,
+            crate::mcp::Annotations,
+            crate::mcp::Role,
+            crate::mcp::Root,
             crate::utils::ErrorResponse,
         )
     ),
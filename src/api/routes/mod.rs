@@ -19,8 +19,13 @@ pub fn public_routes() -> Router<AppState> {
 pub fn protected_routes(state: AppState) -> Router<AppState> {
     Router::new()
         // MCP Gateway endpoint (for Claude and other MCP clients)
-        // Support both GET (for SSE/info) and POST (for JSON-RPC)
-        .route("/mcp", get(handlers::mcp_gateway::mcp_gateway_sse).post(handlers::mcp_gateway))
+        // GET opens the event stream, POST carries JSON-RPC, DELETE ends the session
+        .route(
+            "/mcp",
+            get(handlers::mcp_gateway::mcp_gateway_sse)
+                .post(handlers::mcp_gateway)
+                .delete(handlers::mcp_gateway::mcp_gateway_delete),
+        )
         // MCP server management
         .route(
             "/api/v1/mcp/servers",
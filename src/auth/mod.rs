@@ -7,5 +7,5 @@ mod service;
 
 pub use api_key::ApiKeyEncryption;
 pub use jwt::{Claims, JwtService};
-pub use middleware::{auth_middleware, AuthenticatedUser};
+pub use middleware::{auth_middleware, get_claims, AuthenticatedUser};
 pub use service::AuthService;
@@ -1,7 +1,14 @@
 //! Application settings and configuration
 
+use crate::mcp::catalog::DEFAULT_CATALOG_TTL;
+use crate::mcp::client_session::DEFAULT_SESSION_IDLE_TIMEOUT;
+use crate::mcp::naming::DEFAULT_SEPARATOR;
+use crate::mcp::pagination::DEFAULT_PAGE_SIZE;
+use crate::mcp::transport::SERVER_REQUEST_TIMEOUT;
+use crate::mcp::{FanOutTimeouts, NamingScheme};
 use crate::utils::AppError;
 use std::env;
+use std::time::Duration;
 
 /// Application configuration
 #[derive(Debug, Clone)]
@@ -23,6 +30,27 @@ pub struct Config {
 
     /// Log level
     pub log_level: String,
+
+    /// Deadlines for aggregated list requests across backends
+    pub fanout_timeouts: FanOutTimeouts,
+
+    /// How long backend tool/resource/prompt lists are cached
+    pub catalog_ttl: Duration,
+
+    /// How exposed tool and prompt names are built
+    pub naming: NamingScheme,
+
+    /// Number of items per page of the gateway's list results
+    pub page_size: usize,
+
+    /// Whether gateway client sessions are also stored in Postgres
+    pub session_persist: bool,
+
+    /// How long a gateway client session survives without requests
+    pub session_idle_timeout: Duration,
+
+    /// How long clients get to answer requests relayed from backends (e.g. sampling)
+    pub server_request_timeout: Duration,
 }
 
 impl Config {
@@ -55,6 +83,59 @@ impl Config {
 
         let log_level = env::var("RUST_LOG").unwrap_or_else(|_| "info,metamcp=debug".to_string());
 
+        let defaults = FanOutTimeouts::default();
+        let fanout_timeouts = FanOutTimeouts {
+            per_backend: duration_ms("MCP_LIST_BACKEND_TIMEOUT_MS", defaults.per_backend)?,
+            overall: duration_ms("MCP_LIST_TIMEOUT_MS", defaults.overall)?,
+        };
+
+        let catalog_ttl = match env::var("MCP_CATALOG_TTL_SECS") {
+            Ok(value) => value.parse::<u64>().map(Duration::from_secs).map_err(|_| {
+                AppError::Config("MCP_CATALOG_TTL_SECS must be a number of seconds".to_string())
+            })?,
+            Err(_) => DEFAULT_CATALOG_TTL,
+        };
+
+        let separator = env::var("MCP_TOOL_NAME_SEPARATOR")
+            .unwrap_or_else(|_| DEFAULT_SEPARATOR.to_string());
+        let naming = NamingScheme::new(&separator)?;
+
+        let page_size = match env::var("MCP_PAGE_SIZE") {
+            Ok(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| {
+                    AppError::Config("MCP_PAGE_SIZE must be a positive number".to_string())
+                })?,
+            Err(_) => DEFAULT_PAGE_SIZE,
+        };
+
+        let session_persist = match env::var("MCP_SESSION_PERSIST") {
+            Ok(value) => value.parse::<bool>().map_err(|_| {
+                AppError::Config("MCP_SESSION_PERSIST must be true or false".to_string())
+            })?,
+            Err(_) => false,
+        };
+
+        let session_idle_timeout = match env::var("MCP_SESSION_IDLE_TIMEOUT_SECS") {
+            Ok(value) => value.parse::<u64>().map(Duration::from_secs).map_err(|_| {
+                AppError::Config(
+                    "MCP_SESSION_IDLE_TIMEOUT_SECS must be a number of seconds".to_string(),
+                )
+            })?,
+            Err(_) => DEFAULT_SESSION_IDLE_TIMEOUT,
+        };
+
+        let server_request_timeout = match env::var("MCP_SERVER_REQUEST_TIMEOUT_SECS") {
+            Ok(value) => value.parse::<u64>().map(Duration::from_secs).map_err(|_| {
+                AppError::Config(
+                    "MCP_SERVER_REQUEST_TIMEOUT_SECS must be a number of seconds".to_string(),
+                )
+            })?,
+            Err(_) => SERVER_REQUEST_TIMEOUT,
+        };
+
         Ok(Self {
             database_url,
             jwt_secret,
@@ -62,6 +143,13 @@ impl Config {
             server_host,
             server_port,
             log_level,
+            fanout_timeouts,
+            catalog_ttl,
+            naming,
+            page_size,
+            session_persist,
+            session_idle_timeout,
+            server_request_timeout,
         })
     }
 
@@ -70,3 +158,14 @@ impl Config {
         format!("{}:{}", self.server_host, self.server_port)
     }
 }
+
+/// Read a millisecond duration from the environment, falling back to `default`
+fn duration_ms(name: &str, default: Duration) -> Result<Duration, AppError> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .map_err(|_| AppError::Config(format!("{} must be a number of milliseconds", name))),
+        Err(_) => Ok(default),
+    }
+}
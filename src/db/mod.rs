@@ -11,7 +11,7 @@ pub use models::{
     ApiKey, ApiKeyInfo, CreateApiKeyRequest, CreateMcpServerRequest, McpProtocol, McpServer,
     McpServerInfo, UpdateMcpServerRequest,
 };
-pub use repositories::{ApiKeyRepository, McpServerRepository};
+pub use repositories::{ApiKeyRepository, McpClientSessionRepository, McpServerRepository};
 
 /// Database connection wrapper
 #[derive(Clone)]
@@ -51,6 +51,11 @@ impl Database {
         McpServerRepository::new(self.pool.clone())
     }
 
+    /// Get MCP client session repository
+    pub fn client_sessions(&self) -> McpClientSessionRepository {
+        McpClientSessionRepository::new(self.pool.clone())
+    }
+
     /// Run database migrations
     pub async fn run_migrations(&self) -> Result<(), sqlx::migrate::MigrateError> {
         sqlx::migrate!("./migrations").run(&self.pool).await
@@ -0,0 +1,19 @@
+//! MCP client session model
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// A gateway client session stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct McpClientSession {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub protocol_version: String,
+    pub capabilities: serde_json::Value,
+    pub client_info: Option<serde_json::Value>,
+    pub initialized: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
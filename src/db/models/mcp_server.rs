@@ -1,5 +1,6 @@
 //! MCP Server configuration model
 
+use crate::mcp::protocol::Root;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
@@ -7,18 +8,26 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// MCP Server protocol type
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, sqlx::Type)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, sqlx::Type)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum McpProtocol {
+    #[default]
     Http,
     Sse,
     Stdio,
 }
 
-impl Default for McpProtocol {
-    fn default() -> Self {
-        Self::Http
-    }
+/// How a server's structured tool results are checked against the tools' `outputSchema`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OutputValidation {
+    /// Mismatching results are replaced by an error
+    Enforce,
+    /// Mismatches are logged and the result passed on
+    #[default]
+    Warn,
+    Off,
 }
 
 /// MCP Server configuration stored in the database
@@ -34,6 +43,69 @@ pub struct McpServer {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Unique slug prefixed to the tools and prompts this server exposes
+    pub namespace: String,
+    /// Backend tool name → name exposed instead (JSON object)
+    pub tool_renames: Option<serde_json::Value>,
+    /// Extra global tool names → backend tool name (JSON object)
+    pub tool_aliases: Option<serde_json::Value>,
+    /// Whether the server may ask clients for LLM sampling
+    pub allow_sampling: bool,
+    /// Whether the server may ask users for input through clients
+    pub allow_elicitation: bool,
+    /// Roots answered to `roots/list` instead of asking clients (JSON array)
+    pub roots: Option<serde_json::Value>,
+    /// `file://` directory client roots are narrowed to
+    pub roots_scope: Option<String>,
+    /// How structured tool results are checked against `outputSchema`
+    pub output_validation: OutputValidation,
+    /// Time allowed to establish a connection to the server
+    pub connect_timeout_ms: i32,
+    /// Time the server gets to answer a request
+    pub request_timeout_ms: i32,
+    /// Retries of failed idempotent requests (lists, `resources/read`, `ping`)
+    pub max_retries: i32,
+    /// Delay before the first retry, doubled (with jitter) on each further one
+    pub retry_backoff_ms: i32,
+    /// Consecutive failures that open the circuit breaker (0 disables it)
+    pub breaker_threshold: i32,
+    /// Time an open circuit waits before letting a request through again
+    pub breaker_cooldown_secs: i32,
+}
+
+#[cfg(test)]
+impl McpServer {
+    /// An active `http` server at `url`, named and namespaced `name`, with default settings
+    pub fn test_fixture(name: &str, url: &str) -> Self {
+        use crate::mcp::resilience::*;
+
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            url: url.to_string(),
+            protocol: "http".to_string(),
+            command: None,
+            args: None,
+            env: None,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            namespace: name.to_string(),
+            tool_renames: None,
+            tool_aliases: None,
+            allow_sampling: true,
+            allow_elicitation: true,
+            roots: None,
+            roots_scope: None,
+            output_validation: OutputValidation::default(),
+            connect_timeout_ms: DEFAULT_CONNECT_TIMEOUT_MS,
+            request_timeout_ms: DEFAULT_REQUEST_TIMEOUT_MS,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff_ms: DEFAULT_RETRY_BACKOFF_MS,
+            breaker_threshold: DEFAULT_BREAKER_THRESHOLD,
+            breaker_cooldown_secs: DEFAULT_BREAKER_COOLDOWN_SECS,
+        }
+    }
 }
 
 /// Request to create a new MCP server configuration
@@ -46,6 +118,20 @@ pub struct CreateMcpServerRequest {
     pub command: Option<String>,
     pub args: Option<Vec<String>>,
     pub env: Option<std::collections::HashMap<String, String>>,
+    pub namespace: Option<String>,
+    pub tool_renames: Option<std::collections::HashMap<String, String>>,
+    pub tool_aliases: Option<std::collections::HashMap<String, String>>,
+    pub allow_sampling: Option<bool>,
+    pub allow_elicitation: Option<bool>,
+    pub roots: Option<Vec<Root>>,
+    pub roots_scope: Option<String>,
+    pub output_validation: Option<OutputValidation>,
+    pub connect_timeout_ms: Option<i32>,
+    pub request_timeout_ms: Option<i32>,
+    pub max_retries: Option<i32>,
+    pub retry_backoff_ms: Option<i32>,
+    pub breaker_threshold: Option<i32>,
+    pub breaker_cooldown_secs: Option<i32>,
 }
 
 /// Request to update an MCP server configuration
@@ -58,6 +144,20 @@ pub struct UpdateMcpServerRequest {
     pub args: Option<Vec<String>>,
     pub env: Option<std::collections::HashMap<String, String>>,
     pub is_active: Option<bool>,
+    pub namespace: Option<String>,
+    pub tool_renames: Option<std::collections::HashMap<String, String>>,
+    pub tool_aliases: Option<std::collections::HashMap<String, String>>,
+    pub allow_sampling: Option<bool>,
+    pub allow_elicitation: Option<bool>,
+    pub roots: Option<Vec<Root>>,
+    pub roots_scope: Option<String>,
+    pub output_validation: Option<OutputValidation>,
+    pub connect_timeout_ms: Option<i32>,
+    pub request_timeout_ms: Option<i32>,
+    pub max_retries: Option<i32>,
+    pub retry_backoff_ms: Option<i32>,
+    pub breaker_threshold: Option<i32>,
+    pub breaker_cooldown_secs: Option<i32>,
 }
 
 /// MCP Server info for API responses
@@ -65,9 +165,26 @@ pub struct UpdateMcpServerRequest {
 pub struct McpServerInfo {
     pub id: Uuid,
     pub name: String,
+    pub namespace: String,
     pub url: String,
     pub protocol: String,
     pub is_active: bool,
+    #[schema(value_type = Option<Object>)]
+    pub tool_renames: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    pub tool_aliases: Option<serde_json::Value>,
+    pub allow_sampling: bool,
+    pub allow_elicitation: bool,
+    #[schema(value_type = Option<Vec<Root>>)]
+    pub roots: Option<serde_json::Value>,
+    pub roots_scope: Option<String>,
+    pub output_validation: OutputValidation,
+    pub connect_timeout_ms: i32,
+    pub request_timeout_ms: i32,
+    pub max_retries: i32,
+    pub retry_backoff_ms: i32,
+    pub breaker_threshold: i32,
+    pub breaker_cooldown_secs: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -77,9 +194,23 @@ impl From<McpServer> for McpServerInfo {
         Self {
             id: server.id,
             name: server.name,
+            namespace: server.namespace,
             url: server.url,
             protocol: server.protocol,
             is_active: server.is_active,
+            tool_renames: server.tool_renames,
+            tool_aliases: server.tool_aliases,
+            allow_sampling: server.allow_sampling,
+            allow_elicitation: server.allow_elicitation,
+            roots: server.roots,
+            roots_scope: server.roots_scope,
+            output_validation: server.output_validation,
+            connect_timeout_ms: server.connect_timeout_ms,
+            request_timeout_ms: server.request_timeout_ms,
+            max_retries: server.max_retries,
+            retry_backoff_ms: server.retry_backoff_ms,
+            breaker_threshold: server.breaker_threshold,
+            breaker_cooldown_secs: server.breaker_cooldown_secs,
             created_at: server.created_at,
             updated_at: server.updated_at,
         }
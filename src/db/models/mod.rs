@@ -1,7 +1,12 @@
 //! Database models
 
 pub mod api_key;
+pub mod client_session;
 pub mod mcp_server;
 
 pub use api_key::{ApiKey, ApiKeyInfo, CreateApiKeyRequest};
-pub use mcp_server::{CreateMcpServerRequest, McpProtocol, McpServer, McpServerInfo, UpdateMcpServerRequest};
+pub use client_session::McpClientSession;
+pub use mcp_server::{
+    CreateMcpServerRequest, McpProtocol, McpServer, McpServerInfo, OutputValidation,
+    UpdateMcpServerRequest,
+};
@@ -0,0 +1,98 @@
+//! MCP client session repository for database operations
+
+use crate::db::models::McpClientSession;
+use crate::utils::AppResult;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Repository for gateway client session database operations
+#[derive(Clone)]
+pub struct McpClientSessionRepository {
+    pool: PgPool,
+}
+
+impl McpClientSessionRepository {
+    /// Create a new client session repository
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new session
+    pub async fn create(&self, session: &McpClientSession) -> AppResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO mcp_client_sessions
+                (id, owner_id, protocol_version, capabilities, client_info, initialized, created_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.owner_id)
+        .bind(&session.protocol_version)
+        .bind(&session.capabilities)
+        .bind(&session.client_info)
+        .bind(session.initialized)
+        .bind(session.created_at)
+        .bind(session.last_seen_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find a session by ID, if it belongs to `owner_id`
+    pub async fn find_owned(&self, id: Uuid, owner_id: Uuid) -> AppResult<Option<McpClientSession>> {
+        let session = sqlx::query_as::<_, McpClientSession>(
+            "SELECT * FROM mcp_client_sessions WHERE id = $1 AND owner_id = $2",
+        )
+        .bind(id)
+        .bind(owner_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Record that the client finished the initialize handshake
+    pub async fn mark_initialized(&self, id: Uuid) -> AppResult<()> {
+        sqlx::query(
+            "UPDATE mcp_client_sessions SET initialized = true, last_seen_at = NOW() WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record activity on a session
+    pub async fn touch(&self, id: Uuid) -> AppResult<()> {
+        sqlx::query("UPDATE mcp_client_sessions SET last_seen_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Delete a session
+    pub async fn delete(&self, id: Uuid) -> AppResult<bool> {
+        let result = sqlx::query("DELETE FROM mcp_client_sessions WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete sessions with no activity since `cutoff`
+    pub async fn delete_idle(&self, cutoff: DateTime<Utc>) -> AppResult<u64> {
+        let result = sqlx::query("DELETE FROM mcp_client_sessions WHERE last_seen_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,6 +1,11 @@
 //! MCP Server repository for database operations
 
 use crate::db::models::{McpServer, CreateMcpServerRequest, UpdateMcpServerRequest};
+use crate::mcp::resilience::{
+    DEFAULT_BREAKER_COOLDOWN_SECS, DEFAULT_BREAKER_THRESHOLD, DEFAULT_CONNECT_TIMEOUT_MS,
+    DEFAULT_MAX_RETRIES, DEFAULT_REQUEST_TIMEOUT_MS, DEFAULT_RETRY_BACKOFF_MS,
+};
+use crate::mcp::protocol::Root;
 use crate::utils::AppResult;
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -27,11 +32,15 @@ impl McpServerRepository {
 
         let args_json = request.args.as_ref().map(|a| serde_json::json!(a));
         let env_json = request.env.as_ref().map(|e| serde_json::json!(e));
+        let renames_json = request.tool_renames.as_ref().map(|r| serde_json::json!(r));
+        let aliases_json = request.tool_aliases.as_ref().map(|a| serde_json::json!(a));
+        let roots_json = roots_json(request.roots.as_ref());
+        let roots_scope = request.roots_scope.as_deref().filter(|s| !s.is_empty());
 
         let server = sqlx::query_as::<_, McpServer>(
             r#"
-            INSERT INTO mcp_servers (name, url, protocol, command, args, env, is_active, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, true, NOW(), NOW())
+            INSERT INTO mcp_servers (name, url, protocol, command, args, env, namespace, tool_renames, tool_aliases, allow_sampling, allow_elicitation, roots, roots_scope, output_validation, connect_timeout_ms, request_timeout_ms, max_retries, retry_backoff_ms, breaker_threshold, breaker_cooldown_secs, is_active, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, true, NOW(), NOW())
             RETURNING *
             "#,
         )
@@ -41,6 +50,20 @@ impl McpServerRepository {
         .bind(&request.command)
         .bind(args_json)
         .bind(env_json)
+        .bind(&request.namespace)
+        .bind(renames_json)
+        .bind(aliases_json)
+        .bind(request.allow_sampling.unwrap_or(true))
+        .bind(request.allow_elicitation.unwrap_or(true))
+        .bind(roots_json)
+        .bind(roots_scope)
+        .bind(request.output_validation.unwrap_or_default())
+        .bind(request.connect_timeout_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS))
+        .bind(request.request_timeout_ms.unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS))
+        .bind(request.max_retries.unwrap_or(DEFAULT_MAX_RETRIES))
+        .bind(request.retry_backoff_ms.unwrap_or(DEFAULT_RETRY_BACKOFF_MS))
+        .bind(request.breaker_threshold.unwrap_or(DEFAULT_BREAKER_THRESHOLD))
+        .bind(request.breaker_cooldown_secs.unwrap_or(DEFAULT_BREAKER_COOLDOWN_SECS))
         .fetch_one(&self.pool)
         .await?;
 
@@ -120,6 +143,62 @@ impl McpServerRepository {
             updates.push(format!("is_active = ${}", param_count));
             param_count += 1;
         }
+        if request.namespace.is_some() {
+            updates.push(format!("namespace = ${}", param_count));
+            param_count += 1;
+        }
+        if request.tool_renames.is_some() {
+            updates.push(format!("tool_renames = ${}", param_count));
+            param_count += 1;
+        }
+        if request.tool_aliases.is_some() {
+            updates.push(format!("tool_aliases = ${}", param_count));
+            param_count += 1;
+        }
+        if request.allow_sampling.is_some() {
+            updates.push(format!("allow_sampling = ${}", param_count));
+            param_count += 1;
+        }
+        if request.allow_elicitation.is_some() {
+            updates.push(format!("allow_elicitation = ${}", param_count));
+            param_count += 1;
+        }
+        if request.roots.is_some() {
+            updates.push(format!("roots = ${}", param_count));
+            param_count += 1;
+        }
+        if request.roots_scope.is_some() {
+            updates.push(format!("roots_scope = ${}", param_count));
+            param_count += 1;
+        }
+        if request.output_validation.is_some() {
+            updates.push(format!("output_validation = ${}", param_count));
+            param_count += 1;
+        }
+        if request.connect_timeout_ms.is_some() {
+            updates.push(format!("connect_timeout_ms = ${}", param_count));
+            param_count += 1;
+        }
+        if request.request_timeout_ms.is_some() {
+            updates.push(format!("request_timeout_ms = ${}", param_count));
+            param_count += 1;
+        }
+        if request.max_retries.is_some() {
+            updates.push(format!("max_retries = ${}", param_count));
+            param_count += 1;
+        }
+        if request.retry_backoff_ms.is_some() {
+            updates.push(format!("retry_backoff_ms = ${}", param_count));
+            param_count += 1;
+        }
+        if request.breaker_threshold.is_some() {
+            updates.push(format!("breaker_threshold = ${}", param_count));
+            param_count += 1;
+        }
+        if request.breaker_cooldown_secs.is_some() {
+            updates.push(format!("breaker_cooldown_secs = ${}", param_count));
+            param_count += 1;
+        }
 
         if updates.is_empty() {
             return self.find_by_id(id).await;
@@ -156,6 +235,48 @@ impl McpServerRepository {
         if let Some(is_active) = request.is_active {
             query_builder = query_builder.bind(is_active);
         }
+        if let Some(ref namespace) = request.namespace {
+            query_builder = query_builder.bind(namespace);
+        }
+        if let Some(ref renames) = request.tool_renames {
+            query_builder = query_builder.bind(serde_json::json!(renames));
+        }
+        if let Some(ref aliases) = request.tool_aliases {
+            query_builder = query_builder.bind(serde_json::json!(aliases));
+        }
+        if let Some(allow_sampling) = request.allow_sampling {
+            query_builder = query_builder.bind(allow_sampling);
+        }
+        if let Some(allow_elicitation) = request.allow_elicitation {
+            query_builder = query_builder.bind(allow_elicitation);
+        }
+        if request.roots.is_some() {
+            query_builder = query_builder.bind(roots_json(request.roots.as_ref()));
+        }
+        if let Some(ref roots_scope) = request.roots_scope {
+            query_builder = query_builder.bind(Some(roots_scope).filter(|s| !s.is_empty()));
+        }
+        if let Some(output_validation) = request.output_validation {
+            query_builder = query_builder.bind(output_validation);
+        }
+        if let Some(connect_timeout_ms) = request.connect_timeout_ms {
+            query_builder = query_builder.bind(connect_timeout_ms);
+        }
+        if let Some(request_timeout_ms) = request.request_timeout_ms {
+            query_builder = query_builder.bind(request_timeout_ms);
+        }
+        if let Some(max_retries) = request.max_retries {
+            query_builder = query_builder.bind(max_retries);
+        }
+        if let Some(retry_backoff_ms) = request.retry_backoff_ms {
+            query_builder = query_builder.bind(retry_backoff_ms);
+        }
+        if let Some(breaker_threshold) = request.breaker_threshold {
+            query_builder = query_builder.bind(breaker_threshold);
+        }
+        if let Some(breaker_cooldown_secs) = request.breaker_cooldown_secs {
+            query_builder = query_builder.bind(breaker_cooldown_secs);
+        }
 
         query_builder = query_builder.bind(id);
 
@@ -194,3 +315,8 @@ impl McpServerRepository {
         Ok(())
     }
 }
+
+/// Configured roots as stored; an empty list means the server uses client roots
+fn roots_json(roots: Option<&Vec<Root>>) -> Option<serde_json::Value> {
+    roots.filter(|r| !r.is_empty()).map(|r| serde_json::json!(r))
+}
@@ -1,7 +1,9 @@
 //! Database repositories
 
 pub mod api_key;
+pub mod client_session;
 pub mod mcp_server;
 
 pub use api_key::ApiKeyRepository;
+pub use client_session::McpClientSessionRepository;
 pub use mcp_server::McpServerRepository;
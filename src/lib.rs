@@ -16,6 +16,6 @@ pub use api::{create_router, AppState};
 pub use auth::AuthService;
 pub use config::Config;
 pub use db::Database;
-pub use mcp::{McpProxy, McpServerManager};
+pub use mcp::{Catalog, ClientRelay, McpProxy, McpServerManager};
 pub use streaming::StreamManager;
 pub use utils::{AppError, AppResult};
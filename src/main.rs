@@ -1,7 +1,8 @@
 //! MetaMCP Server - Main entry point
 
 use anyhow::Result;
-use metamcp::{api, AuthService, Config, Database};
+use metamcp::mcp::{ClientSessions, InFlightRequests, ResourceSubscriptions};
+use metamcp::{api, AuthService, Catalog, ClientRelay, Config, Database, McpProxy, StreamManager};
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -36,10 +37,50 @@ async fn main() -> Result<()> {
         db.clone(),
     ));
 
+    // Shared proxy holding live backend connections
+    let proxy = Arc::new(
+        McpProxy::new()
+            .with_fanout_timeouts(config.fanout_timeouts)
+            .with_server_request_timeout(config.server_request_timeout),
+    );
+
+    // Cached view of backend tools, resources and prompts
+    let catalog = Catalog::new(
+        db.clone(),
+        proxy.clone(),
+        config.catalog_ttl,
+        config.naming.clone(),
+        config.page_size,
+    );
+
+    // Resource subscriptions shared by client sessions
+    let subscriptions = Arc::new(ResourceSubscriptions::new(proxy.clone()));
+
+    // Forwards backend notifications and requests to clients on GET /mcp
+    let relay = ClientRelay::new(
+        &proxy,
+        catalog.clone(),
+        Arc::new(StreamManager::new()),
+        subscriptions.clone(),
+    )
+    .await;
+
+    // Sessions of clients connected to the /mcp gateway
+    let mut sessions = ClientSessions::new(config.session_idle_timeout);
+    if config.session_persist {
+        sessions = sessions.with_persistence(db.clone());
+    }
+
     // Create application state
     let state = api::AppState {
         db,
         auth: auth_service,
+        proxy: proxy.clone(),
+        catalog,
+        relay,
+        sessions: Arc::new(sessions),
+        in_flight: Arc::new(InFlightRequests::new()),
+        subscriptions,
     };
 
     // Create router
@@ -51,7 +92,39 @@ async fn main() -> Result<()> {
     tracing::info!("Swagger UI available at http://{}/swagger-ui", bind_addr);
 
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
+
+    // End backend sessions and stop stdio servers
+    tracing::info!("Closing backend MCP connections...");
+    proxy.shutdown().await;
 
     Ok(())
 }
+
+/// Wait for Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        if let Ok(mut signal) =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        {
+            signal.recv().await;
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received");
+}
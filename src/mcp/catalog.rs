@@ -0,0 +1,641 @@
+//! Cached catalog of the tools, resources, resource templates and prompts
+//! exposed by the gateway
+//!
+//! The catalog keeps each backend's list results for a TTL so that list and
+//! call requests do not hit Postgres and every backend each time. Entries
+//! are dropped when a server is created, updated or deleted, and when a
+//! backend sends one of the `notifications/*/list_changed` notifications.
+//! Calls are routed through an exact map from the exposed name to the
+//! owning server and the backend's original name.
+
+use crate::db::models::McpServer;
+use crate::db::Database;
+use crate::mcp::fanout::{fan_out, unavailable_meta, UnavailableBackend};
+use crate::mcp::naming::{NamingScheme, ServerNaming};
+use crate::mcp::pagination::{paginate, Page};
+use crate::mcp::protocol::{ResourceTemplate, Tool};
+use crate::mcp::proxy::{McpProxy, SharedMcpProxy};
+use crate::mcp::session::BackendSession;
+use crate::mcp::transport::BackendNotification;
+use crate::utils::AppError;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Default time a backend's list results are served from cache
+pub const DEFAULT_CATALOG_TTL: Duration = Duration::from_secs(300);
+
+/// The kinds of items a backend can list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CatalogKind {
+    Tools,
+    Resources,
+    ResourceTemplates,
+    Prompts,
+}
+
+impl CatalogKind {
+    /// Key holding the items in a list result (e.g. `tools`)
+    pub fn result_key(self) -> &'static str {
+        match self {
+            Self::Tools => "tools",
+            Self::Resources => "resources",
+            Self::ResourceTemplates => "resourceTemplates",
+            Self::Prompts => "prompts",
+        }
+    }
+
+    /// The kind a `notifications/*/list_changed` method refers to
+    pub fn from_list_changed(method: &str) -> Option<Self> {
+        match method {
+            "notifications/tools/list_changed" => Some(Self::Tools),
+            "notifications/resources/list_changed" => Some(Self::Resources),
+            "notifications/prompts/list_changed" => Some(Self::Prompts),
+            _ => None,
+        }
+    }
+
+    /// Whether the backend advertised the capability for this kind
+    fn is_supported(self, session: &BackendSession) -> bool {
+        match self {
+            Self::Tools => session.supports_tools(),
+            Self::Resources | Self::ResourceTemplates => session.supports_resources(),
+            Self::Prompts => session.supports_prompts(),
+        }
+    }
+
+    /// Field holding the item's routing key (`name`, or the URI for resources)
+    fn key_field(self) -> &'static str {
+        match self {
+            Self::Resources => "uri",
+            Self::ResourceTemplates => "uriTemplate",
+            _ => "name",
+        }
+    }
+
+    /// Name under which `server`'s item `original` is exposed to clients
+    fn expose(self, scheme: &NamingScheme, server: &ServerNaming, original: &str) -> String {
+        match self {
+            Self::Tools => scheme.tool_name(server, original),
+            Self::Resources | Self::ResourceTemplates => {
+                format!("{}:{}", server.namespace, original)
+            }
+            Self::Prompts => scheme.qualify(&server.namespace, original),
+        }
+    }
+}
+
+/// Where a call for an exposed name must be sent
+#[derive(Debug, Clone)]
+pub struct CatalogRoute {
+    pub server: McpServer,
+    /// The name (or URI) the backend itself uses
+    pub original: String,
+}
+
+/// Aggregated items of one kind across all active backends
+#[derive(Debug, Default)]
+pub struct CatalogView {
+    /// Items as exposed to clients, with prefixed names
+    pub items: Vec<Value>,
+    /// Backends whose items could not be fetched
+    pub unavailable: Vec<UnavailableBackend>,
+    routes: HashMap<String, CatalogRoute>,
+}
+
+impl CatalogView {
+    /// Build the exposed view from per-server list results, in server order
+    fn build(
+        kind: CatalogKind,
+        scheme: &NamingScheme,
+        lists: Vec<(McpServer, Vec<Value>)>,
+    ) -> Self {
+        let mut view = Self::default();
+        let field = kind.key_field();
+        let mut aliases = Vec::new();
+
+        for (server, items) in lists {
+            let naming = ServerNaming::from(&server);
+            for item in items {
+                let Some(original) = item.get(field).and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let exposed = kind.expose(scheme, &naming, original);
+                if let Some(existing) = view.routes.get(&exposed) {
+                    tracing::warn!(
+                        "{} '{}' from {} collides with {}; keeping the first",
+                        kind.result_key(),
+                        exposed,
+                        server.name,
+                        existing.server.name
+                    );
+                    continue;
+                }
+
+                let mut exposed_item = item.clone();
+                if let Some(obj) = exposed_item.as_object_mut() {
+                    obj.insert(field.to_string(), json!(exposed));
+                    // Original name and server info for clients that want them
+                    obj.insert(format!("_original_{}", field), json!(original));
+                    obj.insert("_server_id".to_string(), json!(server.id.to_string()));
+                }
+
+                view.routes.insert(
+                    exposed,
+                    CatalogRoute {
+                        server: server.clone(),
+                        original: original.to_string(),
+                    },
+                );
+                view.items.push(exposed_item);
+            }
+
+            if kind == CatalogKind::Tools {
+                aliases.extend(
+                    naming
+                        .tool_aliases
+                        .into_iter()
+                        .map(|(alias, original)| (alias, server.clone(), original)),
+                );
+            }
+        }
+
+        // Aliases route calls but are not listed
+        for (alias, server, original) in aliases {
+            let exists = view
+                .routes
+                .values()
+                .any(|r| r.server.id == server.id && r.original == original);
+            if !exists {
+                tracing::debug!(
+                    "Alias '{}' points at unknown tool '{}' on {}",
+                    alias,
+                    original,
+                    server.name
+                );
+                continue;
+            }
+            if view.routes.contains_key(&alias) {
+                tracing::warn!(
+                    "Alias '{}' on {} collides with a listed tool; ignoring it",
+                    alias,
+                    server.name
+                );
+                continue;
+            }
+            view.routes.insert(alias, CatalogRoute { server, original });
+        }
+
+        view
+    }
+
+    /// Route for an exposed name, if it is in the catalog
+    pub fn route(&self, exposed: &str) -> Option<&CatalogRoute> {
+        self.routes.get(exposed)
+    }
+
+    /// Route for a URI expanded from one of the listed resource templates
+    ///
+    /// The first template that matches wins, in server order.
+    fn match_template(&self, uri: &str) -> Option<CatalogRoute> {
+        self.items.iter().find_map(|item| {
+            let template: ResourceTemplate = serde_json::from_value(item.clone()).ok()?;
+            if !template.matches(uri) {
+                return None;
+            }
+            let route = self.route(&template.uri_template)?;
+            let original = uri.strip_prefix(&format!("{}:", route.server.namespace))?;
+            Some(CatalogRoute {
+                server: route.server.clone(),
+                original: original.to_string(),
+            })
+        })
+    }
+
+    /// The page of items following `cursor`, which must come from the same kind of list
+    pub fn page(
+        self,
+        kind: CatalogKind,
+        cursor: Option<&str>,
+        page_size: usize,
+    ) -> Result<Page, AppError> {
+        paginate(
+            kind.result_key(),
+            kind.key_field(),
+            self.items,
+            cursor,
+            page_size,
+        )
+    }
+
+    /// `_meta` object describing unavailable backends, if there were any
+    pub fn meta(&self) -> Option<Value> {
+        unavailable_meta(&self.unavailable)
+    }
+}
+
+/// A cached value and when it was fetched
+struct Cached<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.fetched_at.elapsed() < ttl
+    }
+}
+
+#[derive(Default)]
+struct CatalogState {
+    servers: Option<Cached<Vec<McpServer>>>,
+    entries: HashMap<(Uuid, CatalogKind), Cached<Vec<Value>>>,
+    /// Bumped on every invalidation so in-flight fetches don't store stale data
+    generation: u64,
+}
+
+/// In-memory catalog of backend tools, resources and prompts
+pub struct Catalog {
+    db: Database,
+    proxy: SharedMcpProxy,
+    ttl: Duration,
+    naming: NamingScheme,
+    page_size: usize,
+    state: RwLock<CatalogState>,
+}
+
+impl Catalog {
+    /// Create the catalog and start listening for backend `list_changed` notifications
+    pub fn new(
+        db: Database,
+        proxy: SharedMcpProxy,
+        ttl: Duration,
+        naming: NamingScheme,
+        page_size: usize,
+    ) -> Arc<Self> {
+        let catalog = Arc::new(Self {
+            db,
+            proxy,
+            ttl,
+            naming,
+            page_size,
+            state: RwLock::new(CatalogState::default()),
+        });
+
+        let mut notifications = catalog.proxy.subscribe_notifications();
+        let weak = Arc::downgrade(&catalog);
+        tokio::spawn(async move {
+            loop {
+                let received = notifications.recv().await;
+                let Some(catalog) = weak.upgrade() else {
+                    break;
+                };
+                match received {
+                    Ok(notification) => catalog.handle_notification(&notification).await,
+                    // We may have missed a list_changed, so trust nothing
+                    Err(RecvError::Lagged(_)) => catalog.invalidate_all().await,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        catalog
+    }
+
+    /// The scheme used to build exposed names
+    pub fn naming(&self) -> &NamingScheme {
+        &self.naming
+    }
+
+    /// Number of items in a page of a list result
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Active servers, read from the database at most once per TTL
+    pub async fn servers(&self) -> Result<Vec<McpServer>, AppError> {
+        if let Some(cached) = &self.state.read().await.servers {
+            if cached.is_fresh(self.ttl) {
+                return Ok(cached.value.clone());
+            }
+        }
+
+        let servers = self.db.mcp_servers().list_all(false).await?;
+
+        let mut state = self.state.write().await;
+        state
+            .entries
+            .retain(|(id, _), _| servers.iter().any(|s| s.id == *id));
+        state.servers = Some(Cached::new(servers.clone()));
+
+        Ok(servers)
+    }
+
+    /// Aggregated items of `kind`, refreshing backends whose entries expired
+    pub async fn view(&self, kind: CatalogKind) -> Result<CatalogView, AppError> {
+        let servers = self.servers().await?;
+
+        let (generation, mut lists, stale) = {
+            let state = self.state.read().await;
+            let mut lists: HashMap<Uuid, Vec<Value>> = HashMap::new();
+            let mut stale = Vec::new();
+            for server in &servers {
+                match state.entries.get(&(server.id, kind)) {
+                    Some(cached) if cached.is_fresh(self.ttl) => {
+                        lists.insert(server.id, cached.value.clone());
+                    }
+                    _ => stale.push(server.clone()),
+                }
+            }
+            (state.generation, lists, stale)
+        };
+
+        let mut unavailable = Vec::new();
+        if !stale.is_empty() {
+            let fetched = fan_out(stale, self.proxy.fanout_timeouts(), |server| {
+                let proxy = self.proxy.clone();
+                async move { fetch(&proxy, &server, kind).await.map(Some) }
+            })
+            .await;
+
+            let mut state = self.state.write().await;
+            for (server, items) in fetched.results {
+                if state.generation == generation {
+                    state
+                        .entries
+                        .insert((server.id, kind), Cached::new(items.clone()));
+                }
+                lists.insert(server.id, items);
+            }
+            unavailable = fetched.unavailable;
+        }
+
+        let ordered = servers
+            .into_iter()
+            .filter_map(|server| lists.remove(&server.id).map(|items| (server, items)))
+            .collect();
+
+        let mut view = CatalogView::build(kind, &self.naming, ordered);
+        view.unavailable = unavailable;
+        Ok(view)
+    }
+
+    /// Items of `kind` from a single server, from cache when fresh
+    pub async fn server_items(
+        &self,
+        server: &McpServer,
+        kind: CatalogKind,
+    ) -> Result<Vec<Value>, AppError> {
+        let generation = {
+            let state = self.state.read().await;
+            if let Some(cached) = state.entries.get(&(server.id, kind)) {
+                if cached.is_fresh(self.ttl) {
+                    return Ok(cached.value.clone());
+                }
+            }
+            state.generation
+        };
+
+        let items = fetch(&self.proxy, server, kind).await?;
+
+        let mut state = self.state.write().await;
+        if state.generation == generation {
+            state
+                .entries
+                .insert((server.id, kind), Cached::new(items.clone()));
+        }
+
+        Ok(items)
+    }
+
+    /// A server's tool as the backend defines it, from cache when fresh
+    pub async fn tool(&self, server: &McpServer, original: &str) -> Result<Option<Tool>, AppError> {
+        let tools = self.server_items(server, CatalogKind::Tools).await?;
+        let tool = tools
+            .into_iter()
+            .find(|t| t.get("name").and_then(|n| n.as_str()) == Some(original));
+        Ok(tool.and_then(|t| serde_json::from_value(t).ok()))
+    }
+
+    /// Find the server and original name behind an exposed name
+    pub async fn resolve(
+        &self,
+        kind: CatalogKind,
+        exposed: &str,
+    ) -> Result<Option<CatalogRoute>, AppError> {
+        Ok(self.view(kind).await?.route(exposed).cloned())
+    }
+
+    /// Find the server and original URI behind an exposed resource URI
+    ///
+    /// URIs produced from resource templates never appear in a list result,
+    /// so they are routed to the server owning the template they match.
+    /// Anything else under a known `namespace:` prefix goes to that server.
+    pub async fn resolve_resource(&self, uri: &str) -> Result<Option<CatalogRoute>, AppError> {
+        if let Some(route) = self.resolve(CatalogKind::Resources, uri).await? {
+            return Ok(Some(route));
+        }
+        let templates = self.view(CatalogKind::ResourceTemplates).await?;
+        if let Some(route) = templates.match_template(uri) {
+            return Ok(Some(route));
+        }
+
+        Ok(self.servers().await?.into_iter().find_map(|server| {
+            let original = uri
+                .strip_prefix(&format!("{}:", server.namespace))?
+                .to_string();
+            Some(CatalogRoute { server, original })
+        }))
+    }
+
+    /// Forget everything cached about a server (after it was created, updated or deleted)
+    pub async fn invalidate_server(&self, server_id: Uuid) {
+        let mut state = self.state.write().await;
+        state.servers = None;
+        state.entries.retain(|(id, _), _| *id != server_id);
+        state.generation += 1;
+    }
+
+    /// Forget one server's cached items of `kind`
+    ///
+    /// Templates are announced with the resources, so they are dropped with them.
+    pub async fn invalidate(&self, server_id: Uuid, kind: CatalogKind) {
+        let mut state = self.state.write().await;
+        state.entries.remove(&(server_id, kind));
+        if kind == CatalogKind::Resources {
+            state
+                .entries
+                .remove(&(server_id, CatalogKind::ResourceTemplates));
+        }
+        state.generation += 1;
+    }
+
+    /// Forget everything
+    pub async fn invalidate_all(&self) {
+        let mut state = self.state.write().await;
+        state.servers = None;
+        state.entries.clear();
+        state.generation += 1;
+    }
+
+    async fn handle_notification(&self, notification: &BackendNotification) {
+        if let Some(kind) = CatalogKind::from_list_changed(&notification.notification.method) {
+            tracing::debug!(
+                server_name = %notification.server_name,
+                "{} list changed, invalidating catalog",
+                kind.result_key()
+            );
+            self.invalidate(notification.server_id, kind).await;
+        }
+    }
+}
+
+/// Ask a backend for its items of `kind`
+async fn fetch(
+    proxy: &McpProxy,
+    server: &McpServer,
+    kind: CatalogKind,
+) -> Result<Vec<Value>, AppError> {
+    // Backends that never advertised the capability list nothing
+    if !kind.is_supported(&*proxy.session(server).await?) {
+        return Ok(Vec::new());
+    }
+
+    match kind {
+        CatalogKind::Tools => proxy.list_tools(server).await,
+        CatalogKind::Resources => proxy.list_resources(server).await,
+        CatalogKind::ResourceTemplates => proxy.list_resource_templates(server).await,
+        CatalogKind::Prompts => proxy.list_prompts(server).await,
+    }
+}
+
+/// Shared catalog instance
+pub type SharedCatalog = Arc<Catalog>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(namespace: &str) -> McpServer {
+        McpServer::test_fixture(namespace, "http://localhost")
+    }
+
+    #[test]
+    fn test_view_routes_exact_names() {
+        let github = McpServer {
+            tool_renames: Some(json!({"search_code": "search"})),
+            tool_aliases: Some(json!({"code_search": "search_code", "gone": "missing"})),
+            ..server("github")
+        };
+        let enterprise = server("github-enterprise");
+        let view = CatalogView::build(
+            CatalogKind::Tools,
+            &NamingScheme::default(),
+            vec![
+                (
+                    github.clone(),
+                    vec![json!({"name": "search_code"}), json!({"name": "get_issue"})],
+                ),
+                (enterprise.clone(), vec![json!({"name": "search_code"})]),
+            ],
+        );
+
+        let names: Vec<&str> = view
+            .items
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "github_search",
+                "github_get_issue",
+                "github-enterprise_search_code"
+            ]
+        );
+
+        let route = view.route("github-enterprise_search_code").unwrap();
+        assert_eq!(route.server.id, enterprise.id);
+        assert_eq!(route.original, "search_code");
+
+        let alias = view.route("code_search").unwrap();
+        assert_eq!(alias.server.id, github.id);
+        assert_eq!(alias.original, "search_code");
+
+        assert!(view.route("github_search_code").is_none());
+        assert!(view.route("gone").is_none());
+    }
+
+    #[test]
+    fn test_view_prefixes_resource_uris() {
+        let files = server("files");
+        let view = CatalogView::build(
+            CatalogKind::Resources,
+            &NamingScheme::default(),
+            vec![(
+                files.clone(),
+                vec![json!({"uri": "file:///a.txt", "name": "a"})],
+            )],
+        );
+
+        assert_eq!(view.items[0]["uri"], "files:file:///a.txt");
+        assert_eq!(view.items[0]["_original_uri"], "file:///a.txt");
+        assert_eq!(
+            view.route("files:file:///a.txt").unwrap().original,
+            "file:///a.txt"
+        );
+    }
+
+    #[test]
+    fn test_template_uris_route_to_their_server() {
+        let (files, db) = (server("files"), server("db"));
+        let view = CatalogView::build(
+            CatalogKind::ResourceTemplates,
+            &NamingScheme::default(),
+            vec![
+                (
+                    files.clone(),
+                    vec![json!({"uriTemplate": "file:///{+path}", "name": "file"})],
+                ),
+                (
+                    db.clone(),
+                    vec![json!({"uriTemplate": "db://{table}/{id}", "name": "row"})],
+                ),
+            ],
+        );
+        assert_eq!(view.items[1]["uriTemplate"], "db:db://{table}/{id}");
+
+        let route = view.match_template("db:db://users/42").unwrap();
+        assert_eq!(route.server.id, db.id);
+        assert_eq!(route.original, "db://users/42");
+
+        let route = view.match_template("files:file:///src/main.rs").unwrap();
+        assert_eq!(route.server.id, files.id);
+        assert_eq!(route.original, "file:///src/main.rs");
+
+        assert!(view.match_template("db:db://users").is_none());
+    }
+
+    #[test]
+    fn test_list_changed_methods() {
+        assert_eq!(
+            CatalogKind::from_list_changed("notifications/tools/list_changed"),
+            Some(CatalogKind::Tools)
+        );
+        assert_eq!(
+            CatalogKind::from_list_changed("notifications/progress"),
+            None
+        );
+    }
+}
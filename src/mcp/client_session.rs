@@ -0,0 +1,308 @@
+//! Sessions of clients connected to the `/mcp` gateway
+//!
+//! `initialize` creates a session whose id is returned in the
+//! `Mcp-Session-Id` header, and every later request must carry that id.
+//! Each session remembers the protocol version and the capabilities the
+//! client announced, and belongs to the API key that opened it: other keys
+//! cannot use, resume or end it, and are told it does not exist. Sessions
+//! live in memory; with persistence enabled they are also written to
+//! Postgres so clients keep their session across a restart. Sessions idle
+//! for longer than the idle timeout are forgotten.
+
+use crate::db::models::McpClientSession;
+use crate::db::Database;
+use crate::mcp::protocol::{ClientCapabilities, ClientInfo, ProtocolVersion};
+use crate::utils::AppError;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Default time a session survives without requests
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often activity on a persisted session is written back
+const TOUCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A client's session with the gateway
+#[derive(Debug, Clone)]
+pub struct ClientSession {
+    pub id: Uuid,
+    /// API key the session was opened with
+    pub owner_id: Uuid,
+    /// Protocol version agreed on in `initialize`
+    pub protocol_version: String,
+    pub capabilities: ClientCapabilities,
+    pub client_info: Option<ClientInfo>,
+    /// Whether the client sent `notifications/initialized`
+    pub initialized: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ClientSession {
+    /// The session's protocol revision
+    pub fn version(&self) -> ProtocolVersion {
+        ProtocolVersion::parse(&self.protocol_version).unwrap_or(ProtocolVersion::LATEST)
+    }
+
+    fn to_row(&self) -> McpClientSession {
+        McpClientSession {
+            id: self.id,
+            owner_id: self.owner_id,
+            protocol_version: self.protocol_version.clone(),
+            capabilities: serde_json::to_value(&self.capabilities).unwrap_or_default(),
+            client_info: self
+                .client_info
+                .as_ref()
+                .and_then(|info| serde_json::to_value(info).ok()),
+            initialized: self.initialized,
+            created_at: self.created_at,
+            last_seen_at: Utc::now(),
+        }
+    }
+
+    fn from_row(row: McpClientSession) -> Self {
+        Self {
+            id: row.id,
+            owner_id: row.owner_id,
+            protocol_version: row.protocol_version,
+            capabilities: serde_json::from_value(row.capabilities).unwrap_or_default(),
+            client_info: row.client_info.and_then(|v| serde_json::from_value(v).ok()),
+            initialized: row.initialized,
+            created_at: row.created_at,
+        }
+    }
+}
+
+struct Entry {
+    session: ClientSession,
+    last_seen: Instant,
+    /// When activity was last written to the database
+    persisted: Instant,
+}
+
+/// Store of gateway client sessions
+pub struct ClientSessions {
+    db: Option<Database>,
+    idle_timeout: Duration,
+    sessions: RwLock<HashMap<Uuid, Entry>>,
+}
+
+impl ClientSessions {
+    /// Create an in-memory session store
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            db: None,
+            idle_timeout,
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Also keep sessions in Postgres
+    pub fn with_persistence(mut self, db: Database) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Open a session for a client that sent `initialize` with `owner_id`'s key
+    pub async fn create(
+        &self,
+        owner_id: Uuid,
+        protocol_version: &str,
+        capabilities: ClientCapabilities,
+        client_info: Option<ClientInfo>,
+    ) -> Result<ClientSession, AppError> {
+        let session = ClientSession {
+            id: Uuid::new_v4(),
+            owner_id,
+            protocol_version: protocol_version.to_string(),
+            capabilities,
+            client_info,
+            initialized: false,
+            created_at: Utc::now(),
+        };
+
+        if let Some(db) = &self.db {
+            db.client_sessions().create(&session.to_row()).await?;
+            let cutoff =
+                Utc::now() - chrono::Duration::from_std(self.idle_timeout).unwrap_or_default();
+            db.client_sessions().delete_idle(cutoff).await?;
+        }
+
+        let now = Instant::now();
+        let mut sessions = self.sessions.write().await;
+        sessions.retain(|_, entry| entry.last_seen.elapsed() < self.idle_timeout);
+        sessions.insert(
+            session.id,
+            Entry {
+                session: session.clone(),
+                last_seen: now,
+                persisted: now,
+            },
+        );
+
+        tracing::debug!(session_id = %session.id, "MCP client session created");
+
+        Ok(session)
+    }
+
+    /// Look up a live session of `owner_id`, recording activity on it
+    ///
+    /// Sessions of other API keys are not found.
+    pub async fn get(&self, id: Uuid, owner_id: Uuid) -> Result<Option<ClientSession>, AppError> {
+        let mut touch = false;
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(entry) = sessions.get_mut(&id) {
+                if entry.session.owner_id != owner_id {
+                    return Ok(None);
+                }
+                if entry.last_seen.elapsed() >= self.idle_timeout {
+                    sessions.remove(&id);
+                    drop(sessions);
+                    self.delete_persisted(id).await?;
+                    return Ok(None);
+                }
+
+                entry.last_seen = Instant::now();
+                if entry.persisted.elapsed() >= TOUCH_INTERVAL {
+                    entry.persisted = entry.last_seen;
+                    touch = true;
+                }
+                let session = entry.session.clone();
+                drop(sessions);
+
+                if let (true, Some(db)) = (touch, &self.db) {
+                    db.client_sessions().touch(id).await?;
+                }
+                return Ok(Some(session));
+            }
+        }
+
+        // Not in memory: the session may predate a restart
+        let Some(db) = &self.db else {
+            return Ok(None);
+        };
+        let Some(row) = db.client_sessions().find_owned(id, owner_id).await? else {
+            return Ok(None);
+        };
+        let idle = (Utc::now() - row.last_seen_at).to_std().unwrap_or_default();
+        if idle >= self.idle_timeout {
+            db.client_sessions().delete(id).await?;
+            return Ok(None);
+        }
+        db.client_sessions().touch(id).await?;
+
+        let session = ClientSession::from_row(row);
+        let now = Instant::now();
+        self.sessions.write().await.insert(
+            id,
+            Entry {
+                session: session.clone(),
+                last_seen: now,
+                persisted: now,
+            },
+        );
+
+        Ok(Some(session))
+    }
+
+    /// Whether any live session in memory satisfies `predicate`
+    pub async fn any(&self, predicate: impl Fn(&ClientSession) -> bool) -> bool {
+        self.sessions
+            .read()
+            .await
+            .values()
+            .any(|entry| entry.last_seen.elapsed() < self.idle_timeout && predicate(&entry.session))
+    }
+
+    /// Record that the client completed the initialize handshake
+    pub async fn mark_initialized(&self, id: Uuid) -> Result<(), AppError> {
+        if let Some(entry) = self.sessions.write().await.get_mut(&id) {
+            entry.session.initialized = true;
+        }
+        if let Some(db) = &self.db {
+            db.client_sessions().mark_initialized(id).await?;
+        }
+        Ok(())
+    }
+
+    /// End a session; returns `false` if it did not exist
+    pub async fn remove(&self, id: Uuid) -> Result<bool, AppError> {
+        let removed = self.sessions.write().await.remove(&id).is_some();
+        let persisted = self.delete_persisted(id).await?;
+        if removed || persisted {
+            tracing::debug!(session_id = %id, "MCP client session ended");
+        }
+        Ok(removed || persisted)
+    }
+
+    async fn delete_persisted(&self, id: Uuid) -> Result<bool, AppError> {
+        match &self.db {
+            Some(db) => db.client_sessions().delete(id).await,
+            None => Ok(false),
+        }
+    }
+}
+
+/// Shared session store
+pub type SharedClientSessions = Arc<ClientSessions>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::protocol::MCP_PROTOCOL_VERSION;
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let sessions = ClientSessions::new(DEFAULT_SESSION_IDLE_TIMEOUT);
+        let owner = Uuid::new_v4();
+        let session = sessions
+            .create(
+                owner,
+                MCP_PROTOCOL_VERSION,
+                ClientCapabilities::default(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!session.initialized);
+
+        sessions.mark_initialized(session.id).await.unwrap();
+        let found = sessions.get(session.id, owner).await.unwrap().unwrap();
+        assert!(found.initialized);
+        assert_eq!(found.protocol_version, MCP_PROTOCOL_VERSION);
+
+        // Another API key cannot see the session
+        assert!(sessions
+            .get(session.id, Uuid::new_v4())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(sessions.get(session.id, owner).await.unwrap().is_some());
+
+        assert!(sessions.remove(session.id).await.unwrap());
+        assert!(sessions.get(session.id, owner).await.unwrap().is_none());
+        assert!(!sessions.remove(session.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_expire() {
+        let sessions = ClientSessions::new(Duration::from_millis(50));
+        let owner = Uuid::new_v4();
+        let session = sessions
+            .create(
+                owner,
+                MCP_PROTOCOL_VERSION,
+                ClientCapabilities::default(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(sessions.get(session.id, owner).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,196 @@
+//! Checks on `elicitation/create` requests relayed to clients
+//!
+//! A backend asks the user for input with a `requestedSchema`, which the
+//! spec restricts to a flat object of primitive properties so clients can
+//! render it as a simple form. Schemas outside that subset (nesting,
+//! references, arrays...) are refused before they reach a client, and the
+//! client's answer must be an `accept` with primitive values, a `decline`
+//! or a `cancel`.
+
+use crate::utils::AppError;
+use serde_json::{Map, Value};
+
+/// Keywords every property schema may carry
+const COMMON_KEYWORDS: &[&str] = &["type", "title", "description", "default"];
+
+/// Formats a string property may declare
+const STRING_FORMATS: &[&str] = &["email", "uri", "date", "date-time"];
+
+/// Check that a `requestedSchema` stays within the subset clients must support
+pub fn validate_requested_schema(schema: &Value) -> Result<(), AppError> {
+    let schema = schema
+        .as_object()
+        .ok_or_else(|| invalid("requestedSchema must be an object"))?;
+    check_keywords(
+        schema,
+        &["type", "title", "description", "properties", "required"],
+        "requestedSchema",
+    )?;
+    if schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+        return Err(invalid("requestedSchema must have type \"object\""));
+    }
+
+    let properties = schema
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .ok_or_else(|| invalid("requestedSchema must have an object of properties"))?;
+    for (name, property) in properties {
+        validate_property(name, property)?;
+    }
+
+    if let Some(required) = schema.get("required") {
+        let required = required
+            .as_array()
+            .ok_or_else(|| invalid("required must be an array of property names"))?;
+        for name in required {
+            match name.as_str() {
+                Some(name) if properties.contains_key(name) => {}
+                _ => {
+                    return Err(invalid(&format!(
+                        "required names unknown property {}",
+                        name
+                    )))
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a client's answer to an elicitation
+pub fn validate_result(result: &Value) -> Result<(), AppError> {
+    match result.get("action").and_then(|a| a.as_str()) {
+        Some("accept") => {}
+        Some("decline" | "cancel") => return Ok(()),
+        _ => return Err(invalid("action must be accept, decline or cancel")),
+    }
+
+    let Some(content) = result.get("content") else {
+        return Ok(());
+    };
+    let content = content
+        .as_object()
+        .ok_or_else(|| invalid("content must be an object"))?;
+    for (name, value) in content {
+        if !matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_)) {
+            return Err(invalid(&format!(
+                "content of {} must be a primitive value",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_property(name: &str, property: &Value) -> Result<(), AppError> {
+    let property = property
+        .as_object()
+        .ok_or_else(|| invalid(&format!("property {} must be an object", name)))?;
+
+    let extra: &[&str] = match property.get("type").and_then(|t| t.as_str()) {
+        Some("string") if property.contains_key("enum") => &["enum", "enumNames"],
+        Some("string") => &["minLength", "maxLength", "format"],
+        Some("number" | "integer") => &["minimum", "maximum"],
+        Some("boolean") => &[],
+        _ => {
+            return Err(invalid(&format!(
+                "property {} must be a string, number, integer or boolean",
+                name
+            )))
+        }
+    };
+    let allowed: Vec<&str> = COMMON_KEYWORDS.iter().chain(extra).copied().collect();
+    check_keywords(property, &allowed, name)?;
+
+    if let Some(format) = property.get("format") {
+        if !format.as_str().is_some_and(|f| STRING_FORMATS.contains(&f)) {
+            return Err(invalid(&format!(
+                "property {} has an unsupported format",
+                name
+            )));
+        }
+    }
+    if let Some(values) = property.get("enum") {
+        let all_strings = values
+            .as_array()
+            .is_some_and(|v| !v.is_empty() && v.iter().all(Value::is_string));
+        if !all_strings {
+            return Err(invalid(&format!("enum of {} must list strings", name)));
+        }
+    }
+    Ok(())
+}
+
+/// Refuse keywords outside `allowed`, which could smuggle in nested schemas
+fn check_keywords(
+    object: &Map<String, Value>,
+    allowed: &[&str],
+    what: &str,
+) -> Result<(), AppError> {
+    match object.keys().find(|k| !allowed.contains(&k.as_str())) {
+        Some(keyword) => Err(invalid(&format!(
+            "{} uses unsupported keyword {}",
+            what, keyword
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn invalid(message: &str) -> AppError {
+    AppError::Validation(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flat_schemas_are_accepted() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "title": "Name", "maxLength": 50},
+                "email": {"type": "string", "format": "email"},
+                "age": {"type": "integer", "minimum": 0},
+                "confirm": {"type": "boolean", "default": false},
+                "color": {"type": "string", "enum": ["red", "green"], "enumNames": ["Red", "Green"]}
+            },
+            "required": ["name", "confirm"]
+        });
+        assert!(validate_requested_schema(&schema).is_ok());
+    }
+
+    #[test]
+    fn test_unsafe_schemas_are_refused() {
+        let nested = json!({
+            "type": "object",
+            "properties": {"address": {"type": "object", "properties": {}}}
+        });
+        let array = json!({
+            "type": "object",
+            "properties": {"tags": {"type": "array", "items": {"type": "string"}}}
+        });
+        let reference = json!({
+            "type": "object",
+            "properties": {"name": {"type": "string", "$ref": "#/defs/name"}}
+        });
+        let unknown_required = json!({
+            "type": "object",
+            "properties": {},
+            "required": ["name"]
+        });
+        for schema in [nested, array, reference, unknown_required, json!("string")] {
+            assert!(validate_requested_schema(&schema).is_err(), "{}", schema);
+        }
+    }
+
+    #[test]
+    fn test_validate_result() {
+        assert!(validate_result(&json!({"action": "accept", "content": {"ok": true}})).is_ok());
+        assert!(validate_result(&json!({"action": "decline"})).is_ok());
+        assert!(validate_result(&json!({"action": "accept", "content": {"a": [1]}})).is_err());
+        assert!(validate_result(&json!({"action": "maybe"})).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Concurrent fan-out of a request across backend MCP servers
+//!
+//! Aggregated list operations query every backend at once, bounded by a
+//! per-backend deadline and an overall deadline. Whatever arrives in time is
+//! returned; backends that failed or ran out of time are reported alongside
+//! the results so clients can tell the list is partial.
+
+use crate::db::models::McpServer;
+use crate::utils::AppError;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+/// Default time a single backend gets to answer a list request
+pub const DEFAULT_BACKEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time an aggregated list request may take overall
+pub const DEFAULT_OVERALL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// `_meta` key under which unavailable backends are reported
+pub const UNAVAILABLE_META_KEY: &str = "metamcp/unavailableBackends";
+
+/// How one backend's call ended
+type Outcome<T> = Result<Option<T>, (UnavailableReason, String)>;
+
+/// Deadlines applied to a fan-out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FanOutTimeouts {
+    /// Deadline for each individual backend
+    pub per_backend: Duration,
+    /// Deadline for the whole fan-out
+    pub overall: Duration,
+}
+
+impl Default for FanOutTimeouts {
+    fn default() -> Self {
+        Self {
+            per_backend: DEFAULT_BACKEND_TIMEOUT,
+            overall: DEFAULT_OVERALL_TIMEOUT,
+        }
+    }
+}
+
+/// Why a backend is missing from an aggregated result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnavailableReason {
+    /// The backend did not answer before a deadline
+    Timeout,
+    /// The backend answered with an error or could not be reached
+    Error,
+    /// The backend was skipped because its circuit breaker is open
+    CircuitOpen,
+}
+
+/// A backend that did not contribute to an aggregated result
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnavailableBackend {
+    pub server_id: Uuid,
+    pub server_name: String,
+    pub reason: UnavailableReason,
+    pub message: String,
+}
+
+/// Results gathered from a fan-out, in the order the servers were given
+#[derive(Debug)]
+pub struct FanOut<T> {
+    pub results: Vec<(McpServer, T)>,
+    pub unavailable: Vec<UnavailableBackend>,
+}
+
+impl<T> FanOut<T> {
+    /// `_meta` object describing unavailable backends, if there were any
+    pub fn meta(&self) -> Option<Value> {
+        unavailable_meta(&self.unavailable)
+    }
+}
+
+/// `_meta` object listing `unavailable` backends, or `None` if the list is empty
+pub fn unavailable_meta(unavailable: &[UnavailableBackend]) -> Option<Value> {
+    if unavailable.is_empty() {
+        return None;
+    }
+    Some(json!({ UNAVAILABLE_META_KEY: unavailable }))
+}
+
+/// Run `call` against every server concurrently
+///
+/// `call` returns `Ok(None)` for a backend that should be skipped without
+/// being reported (e.g. it never advertised the capability). Each call runs
+/// on its own task, so a backend that misses the deadline keeps going in the
+/// background and its session is ready for the next request.
+pub async fn fan_out<T, F, Fut>(
+    servers: Vec<McpServer>,
+    timeouts: FanOutTimeouts,
+    call: F,
+) -> FanOut<T>
+where
+    T: Send + 'static,
+    F: Fn(McpServer) -> Fut,
+    Fut: Future<Output = Result<Option<T>, AppError>> + Send + 'static,
+{
+    let deadline = Instant::now() + timeouts.overall;
+
+    let mut pending: FuturesUnordered<_> = servers
+        .iter()
+        .enumerate()
+        .map(|(index, server)| {
+            let task = tokio::spawn(call(server.clone()));
+            async move {
+                let outcome = match tokio::time::timeout(timeouts.per_backend, task).await {
+                    Ok(Ok(Err(e @ AppError::UpstreamTimeout(_)))) => {
+                        Err((UnavailableReason::Timeout, e.to_string()))
+                    }
+                    Ok(Ok(Err(e @ AppError::CircuitOpen(_)))) => {
+                        Err((UnavailableReason::CircuitOpen, e.to_string()))
+                    }
+                    Ok(Ok(result)) => result.map_err(|e| (UnavailableReason::Error, e.to_string())),
+                    Ok(Err(e)) => Err((UnavailableReason::Error, format!("Task failed: {}", e))),
+                    Err(_) => Err((
+                        UnavailableReason::Timeout,
+                        format!("No response within {}ms", timeouts.per_backend.as_millis()),
+                    )),
+                };
+                (index, outcome)
+            }
+        })
+        .collect();
+
+    let mut outcomes: Vec<Option<Outcome<T>>> = servers.iter().map(|_| None).collect();
+    while let Ok(Some((index, outcome))) = tokio::time::timeout_at(deadline, pending.next()).await {
+        outcomes[index] = Some(outcome);
+    }
+
+    let mut fan_out = FanOut {
+        results: Vec::new(),
+        unavailable: Vec::new(),
+    };
+
+    for (server, outcome) in servers.into_iter().zip(outcomes) {
+        let (reason, message) = match outcome {
+            Some(Ok(Some(value))) => {
+                fan_out.results.push((server, value));
+                continue;
+            }
+            Some(Ok(None)) => continue,
+            Some(Err(failure)) => failure,
+            None => (
+                UnavailableReason::Timeout,
+                format!(
+                    "Aggregation deadline of {}ms reached",
+                    timeouts.overall.as_millis()
+                ),
+            ),
+        };
+
+        tracing::warn!(server_name = %server.name, ?reason, "Backend unavailable: {}", message);
+        fan_out.unavailable.push(UnavailableBackend {
+            server_id: server.id,
+            server_name: server.name,
+            reason,
+            message,
+        });
+    }
+
+    fan_out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(name: &str) -> McpServer {
+        McpServer::test_fixture(name, "http://localhost")
+    }
+
+    async fn respond(server: McpServer) -> Result<Option<String>, AppError> {
+        match server.name.as_str() {
+            "slow" => {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(Some("late".to_string()))
+            }
+            "broken" => Err(AppError::McpProtocol("boom".to_string())),
+            "tripped" => Err(AppError::CircuitOpen("tripped".to_string())),
+            "skipped" => Ok(None),
+            name => {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(Some(name.to_string()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_reports_slow_and_failed_backends() {
+        let servers = vec![
+            server("a"),
+            server("slow"),
+            server("broken"),
+            server("tripped"),
+            server("skipped"),
+            server("b"),
+        ];
+        let timeouts = FanOutTimeouts {
+            per_backend: Duration::from_millis(200),
+            overall: Duration::from_secs(2),
+        };
+
+        let started = std::time::Instant::now();
+        let result = fan_out(servers, timeouts, respond).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let values: Vec<&str> = result.results.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, vec!["a", "b"]);
+
+        let unavailable: Vec<(&str, UnavailableReason)> = result
+            .unavailable
+            .iter()
+            .map(|u| (u.server_name.as_str(), u.reason))
+            .collect();
+        assert_eq!(
+            unavailable,
+            vec![
+                ("slow", UnavailableReason::Timeout),
+                ("broken", UnavailableReason::Error),
+                ("tripped", UnavailableReason::CircuitOpen)
+            ]
+        );
+        assert!(result.meta().unwrap()[UNAVAILABLE_META_KEY].is_array());
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_overall_deadline() {
+        let timeouts = FanOutTimeouts {
+            per_backend: Duration::from_secs(10),
+            overall: Duration::from_millis(200),
+        };
+
+        let started = std::time::Instant::now();
+        let result = fan_out(vec![server("a"), server("slow")], timeouts, respond).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        assert_eq!(result.results.len(), 1);
+        assert_eq!(result.unavailable.len(), 1);
+        assert_eq!(result.unavailable[0].reason, UnavailableReason::Timeout);
+    }
+}
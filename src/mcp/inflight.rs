@@ -0,0 +1,156 @@
+//! Requests from gateway clients that are still being served
+//!
+//! Each request a client sends to `/mcp` is registered under its session and
+//! the client's request id, together with a [`CancelSignal`] that is handed
+//! down to the backend transport. When the client sends
+//! `notifications/cancelled`, the signal fires: the transport forwards the
+//! cancellation to the backend under the id it used on the wire and the
+//! gateway stops waiting for the result.
+
+use crate::mcp::protocol::RequestId;
+use crate::mcp::transport::CancelSignal;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use uuid::Uuid;
+
+type Key = (Uuid, RequestId);
+
+/// A registered request: a token telling registrations apart, and its canceller
+type Entry = (u64, oneshot::Sender<Option<String>>);
+
+/// Client requests currently in flight, by session and request id
+#[derive(Default)]
+pub struct InFlightRequests {
+    requests: Mutex<HashMap<Key, Entry>>,
+    next_token: AtomicU64,
+}
+
+impl InFlightRequests {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a request until the returned guard is dropped
+    ///
+    /// A client reusing the id of a request still in flight replaces it.
+    pub async fn register(
+        self: &Arc<Self>,
+        session_id: Uuid,
+        id: RequestId,
+    ) -> (InFlightGuard, CancelSignal) {
+        let (tx, cancel) = CancelSignal::new();
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let key = (session_id, id);
+        self.requests.lock().await.insert(key.clone(), (token, tx));
+
+        let guard = InFlightGuard {
+            registry: self.clone(),
+            key: Some(key),
+            token,
+        };
+        (guard, cancel)
+    }
+
+    /// Cancel a client's request; returns `false` if it was not in flight
+    pub async fn cancel(&self, session_id: Uuid, id: &RequestId, reason: Option<String>) -> bool {
+        let key = (session_id, id.clone());
+        match self.requests.lock().await.remove(&key) {
+            Some((_, tx)) => {
+                tracing::debug!(session_id = %session_id, request_id = %id, "Cancelling request");
+                tx.send(reason).is_ok()
+            }
+            None => false,
+        }
+    }
+
+    /// Number of requests in flight
+    pub async fn len(&self) -> usize {
+        self.requests.lock().await.len()
+    }
+
+    /// Whether no request is in flight
+    pub async fn is_empty(&self) -> bool {
+        self.requests.lock().await.is_empty()
+    }
+}
+
+/// Removes a request from the registry when it completes
+pub struct InFlightGuard {
+    registry: Arc<InFlightRequests>,
+    key: Option<Key>,
+    token: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        let token = self.token;
+        let remove = move |requests: &mut HashMap<Key, Entry>| {
+            // Leave a later request that reused the id alone
+            if requests.get(&key).is_some_and(|(t, _)| *t == token) {
+                requests.remove(&key);
+            }
+        };
+
+        // try_lock keeps the common case synchronous
+        if let Ok(mut requests) = self.registry.requests.try_lock() {
+            remove(&mut requests);
+            return;
+        }
+        let registry = self.registry.clone();
+        tokio::spawn(async move { remove(&mut *registry.requests.lock().await) });
+    }
+}
+
+/// Shared in-flight registry
+pub type SharedInFlightRequests = Arc<InFlightRequests>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_fires_signal() {
+        let registry = Arc::new(InFlightRequests::new());
+        let session = Uuid::new_v4();
+        let (_guard, cancel) = registry.register(session, RequestId::Number(3)).await;
+
+        assert!(
+            !registry
+                .cancel(Uuid::new_v4(), &RequestId::Number(3), None)
+                .await
+        );
+        assert!(
+            registry
+                .cancel(
+                    session,
+                    &RequestId::Number(3),
+                    Some("user abort".to_string())
+                )
+                .await
+        );
+
+        let reason = tokio::time::timeout(Duration::from_secs(1), cancel.cancelled())
+            .await
+            .unwrap();
+        assert_eq!(reason.as_deref(), Some("user abort"));
+    }
+
+    #[tokio::test]
+    async fn test_guard_removes_request() {
+        let registry = Arc::new(InFlightRequests::new());
+        let (guard, _cancel) = registry
+            .register(Uuid::new_v4(), RequestId::Number(1))
+            .await;
+        assert_eq!(registry.len().await, 1);
+
+        drop(guard);
+        assert!(registry.is_empty().await);
+    }
+}
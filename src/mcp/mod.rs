@@ -1,9 +1,39 @@
 //! MCP (Model Context Protocol) module
 
+pub mod catalog;
+pub mod client_session;
+pub mod elicitation;
+pub mod fanout;
+pub mod inflight;
+pub mod naming;
+pub mod pagination;
 pub mod protocol;
 pub mod proxy;
+pub mod relay;
+pub mod resilience;
+pub mod roots;
+pub mod schema;
 pub mod server_manager;
+pub mod session;
+pub mod subscriptions;
+pub mod transport;
 
+pub use catalog::{Catalog, CatalogKind, CatalogRoute, CatalogView, SharedCatalog};
+pub use client_session::{ClientSession, ClientSessions, SharedClientSessions};
+pub use fanout::{FanOut, FanOutTimeouts, UnavailableBackend, UnavailableReason};
+pub use inflight::{InFlightGuard, InFlightRequests, SharedInFlightRequests};
+pub use naming::{NamingScheme, ServerNaming};
+pub use pagination::{Page, DEFAULT_PAGE_SIZE};
 pub use protocol::*;
 pub use proxy::{McpProxy, SharedMcpProxy};
+pub use relay::{
+    CallRegistration, ClientConnection, ClientRelay, ProgressRegistration, SharedClientRelay,
+};
+pub use resilience::{BreakerState, BreakerStatus, CircuitBreaker, ResiliencePolicy};
 pub use server_manager::{McpServerConfig, McpServerManager, ServerInfo, ServerStatus};
+pub use session::{BackendSession, NegotiatedSessionInfo};
+pub use subscriptions::{ResourceSubscriptions, SharedResourceSubscriptions};
+pub use transport::{
+    BackendEvents, BackendNotification, BackendRequest, CancelSignal, McpTransport, SseTransport,
+    StdioTransport, StreamableHttpTransport,
+};
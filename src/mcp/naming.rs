@@ -0,0 +1,374 @@
+//! Names under which backend tools, prompts and resources are exposed
+//!
+//! Every server has a unique `namespace` slug. A tool is exposed as
+//! `{namespace}{separator}{name}`, where `name` is the backend's own name or
+//! the rename configured for it. Aliases are extra global names that
+//! `tools/call` accepts for a tool but that are not listed. Namespaces only
+//! use `[a-z0-9-]` and the separator never does, so an exposed name can
+//! always be attributed to exactly one server.
+
+use crate::db::models::McpServer;
+use crate::utils::AppError;
+use std::collections::{HashMap, HashSet};
+
+/// Longest tool name many MCP clients accept
+pub const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Longest allowed namespace slug
+pub const MAX_NAMESPACE_LEN: usize = 32;
+
+/// Separator placed between a namespace and a tool or prompt name
+pub const DEFAULT_SEPARATOR: &str = "_";
+
+/// Characters of the hash suffix appended to shortened tool names
+const HASH_SUFFIX_LEN: usize = 8;
+
+/// The naming-related part of a server's configuration
+#[derive(Debug, Clone, Default)]
+pub struct ServerNaming {
+    pub namespace: String,
+    /// Backend tool name → local name exposed instead
+    pub tool_renames: HashMap<String, String>,
+    /// Global alias → backend tool name
+    pub tool_aliases: HashMap<String, String>,
+}
+
+impl From<&McpServer> for ServerNaming {
+    fn from(server: &McpServer) -> Self {
+        Self {
+            namespace: server.namespace.clone(),
+            tool_renames: string_map(server.tool_renames.as_ref()),
+            tool_aliases: string_map(server.tool_aliases.as_ref()),
+        }
+    }
+}
+
+/// How exposed names are built
+#[derive(Debug, Clone)]
+pub struct NamingScheme {
+    separator: String,
+}
+
+impl Default for NamingScheme {
+    fn default() -> Self {
+        Self {
+            separator: DEFAULT_SEPARATOR.to_string(),
+        }
+    }
+}
+
+impl NamingScheme {
+    /// Create a scheme with a custom separator
+    ///
+    /// The separator must be 1-3 characters from `_` and `.` so it can never
+    /// appear inside a namespace.
+    pub fn new(separator: &str) -> Result<Self, AppError> {
+        if separator.is_empty()
+            || separator.len() > 3
+            || !separator.chars().all(|c| c == '_' || c == '.')
+        {
+            return Err(AppError::Config(
+                "Tool name separator must be 1-3 characters of '_' or '.'".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            separator: separator.to_string(),
+        })
+    }
+
+    /// The separator between namespace and name
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    /// `{namespace}{separator}{name}`
+    pub fn qualify(&self, namespace: &str, name: &str) -> String {
+        format!("{}{}{}", namespace, self.separator, name)
+    }
+
+    /// Exposed name of a backend tool, after renames and the length limit
+    pub fn tool_name(&self, naming: &ServerNaming, original: &str) -> String {
+        let local = naming
+            .tool_renames
+            .get(original)
+            .map(String::as_str)
+            .unwrap_or(original);
+        fit_tool_name(self.qualify(&naming.namespace, local))
+    }
+
+    /// Check a server's naming configuration against every other server
+    ///
+    /// Syntax problems are validation errors; clashes with other servers
+    /// are conflicts.
+    pub fn validate(&self, naming: &ServerNaming, others: &[ServerNaming]) -> Result<(), AppError> {
+        validate_namespace(&naming.namespace)?;
+
+        if let Some(other) = others.iter().find(|o| o.namespace == naming.namespace) {
+            return Err(AppError::Conflict(format!(
+                "Namespace '{}' is already used by another server",
+                other.namespace
+            )));
+        }
+
+        // An existing alias must not start resolving into the new namespace
+        let prefix = format!("{}{}", naming.namespace, self.separator);
+        if let Some(alias) = others
+            .iter()
+            .flat_map(|o| o.tool_aliases.keys())
+            .find(|alias| alias.starts_with(&prefix))
+        {
+            return Err(AppError::Conflict(format!(
+                "Namespace '{}' would capture alias '{}' of another server",
+                naming.namespace, alias
+            )));
+        }
+
+        let mut locals = HashSet::new();
+        for (original, local) in &naming.tool_renames {
+            validate_tool_name(local)?;
+            if original.is_empty() {
+                return Err(AppError::Validation(
+                    "Renamed tool name is empty".to_string(),
+                ));
+            }
+            let exposed = self.qualify(&naming.namespace, local);
+            if exposed.len() > MAX_TOOL_NAME_LEN {
+                return Err(AppError::Validation(format!(
+                    "Tool name '{}' exceeds {} characters",
+                    exposed, MAX_TOOL_NAME_LEN
+                )));
+            }
+            if !locals.insert(local) {
+                return Err(AppError::Conflict(format!(
+                    "Several tools are renamed to '{}'",
+                    local
+                )));
+            }
+        }
+
+        let taken: HashSet<&String> = others.iter().flat_map(|o| o.tool_aliases.keys()).collect();
+        for (alias, original) in &naming.tool_aliases {
+            validate_tool_name(alias)?;
+            if original.is_empty() {
+                return Err(AppError::Validation(format!(
+                    "Alias '{}' does not name a tool",
+                    alias
+                )));
+            }
+            if let Some(owner) = std::iter::once(naming)
+                .chain(others)
+                .find(|o| alias.starts_with(&format!("{}{}", o.namespace, self.separator)))
+            {
+                return Err(AppError::Conflict(format!(
+                    "Alias '{}' falls inside namespace '{}'",
+                    alias, owner.namespace
+                )));
+            }
+            if taken.contains(alias) {
+                return Err(AppError::Conflict(format!(
+                    "Alias '{}' is already used by another server",
+                    alias
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that a namespace is a lowercase slug of at most 32 characters
+pub fn validate_namespace(namespace: &str) -> Result<(), AppError> {
+    let valid = !namespace.is_empty()
+        && namespace.len() <= MAX_NAMESPACE_LEN
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !namespace.starts_with('-')
+        && !namespace.ends_with('-');
+
+    if !valid {
+        return Err(AppError::Validation(format!(
+            "Namespace '{}' must be 1-{} characters of a-z, 0-9 and '-', not starting or ending with '-'",
+            namespace, MAX_NAMESPACE_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that a tool name only uses characters clients accept
+fn validate_tool_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_TOOL_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+
+    if !valid {
+        return Err(AppError::Validation(format!(
+            "Tool name '{}' must be 1-{} characters of A-Z, a-z, 0-9, '_', '-' and '.'",
+            name, MAX_TOOL_NAME_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+/// Derive a namespace from a server name (e.g. `GitHub Enterprise` → `github-enterprise`)
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.truncate(MAX_NAMESPACE_LEN);
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "server".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// Shorten a tool name to the client limit, keeping it unique with a hash suffix
+pub fn fit_tool_name(name: String) -> String {
+    if name.len() <= MAX_TOOL_NAME_LEN {
+        return name;
+    }
+
+    // FNV-1a: stable across builds, so shortened names don't change on upgrade
+    let hash = name.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+
+    let mut end = MAX_TOOL_NAME_LEN - HASH_SUFFIX_LEN - 1;
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}-{:08x}", &name[..end], hash)
+}
+
+/// Read a JSON object of strings, ignoring anything else
+fn string_map(value: Option<&serde_json::Value>) -> HashMap<String, String> {
+    value
+        .and_then(|v| v.as_object())
+        .map(|obj| {
+            obj.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(namespace: &str) -> ServerNaming {
+        ServerNaming {
+            namespace: namespace.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("github_enterprise"), "github-enterprise");
+        assert_eq!(slugify("  My Server!! "), "my-server");
+        assert_eq!(slugify("___"), "server");
+    }
+
+    #[test]
+    fn test_tool_name_applies_rename_and_limit() {
+        let scheme = NamingScheme::new("__").unwrap();
+        let mut server = naming("github");
+        server
+            .tool_renames
+            .insert("search_repositories".to_string(), "search".to_string());
+
+        assert_eq!(
+            scheme.tool_name(&server, "search_repositories"),
+            "github__search"
+        );
+        assert_eq!(scheme.tool_name(&server, "get_issue"), "github__get_issue");
+
+        let long = scheme.tool_name(&server, &"x".repeat(80));
+        assert_eq!(long.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(long, scheme.tool_name(&server, &"x".repeat(81)));
+    }
+
+    #[test]
+    fn test_validate_rejects_conflicts() {
+        let scheme = NamingScheme::default();
+        let mut github = naming("github");
+        github
+            .tool_aliases
+            .insert("search".to_string(), "search_code".to_string());
+        let others = vec![github];
+
+        assert!(matches!(
+            scheme.validate(&naming("github"), &others),
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            scheme.validate(&naming("GitHub"), &others),
+            Err(AppError::Validation(_))
+        ));
+
+        let mut enterprise = naming("github-enterprise");
+        enterprise
+            .tool_aliases
+            .insert("search".to_string(), "search".to_string());
+        assert!(matches!(
+            scheme.validate(&enterprise, &others),
+            Err(AppError::Conflict(_))
+        ));
+
+        enterprise.tool_aliases.clear();
+        enterprise
+            .tool_aliases
+            .insert("github_search".to_string(), "search".to_string());
+        assert!(matches!(
+            scheme.validate(&enterprise, &others),
+            Err(AppError::Conflict(_))
+        ));
+
+        enterprise.tool_aliases.clear();
+        enterprise
+            .tool_renames
+            .insert("a".to_string(), "same".to_string());
+        enterprise
+            .tool_renames
+            .insert("b".to_string(), "same".to_string());
+        assert!(matches!(
+            scheme.validate(&enterprise, &others),
+            Err(AppError::Conflict(_))
+        ));
+
+        enterprise.tool_renames.clear();
+        assert!(scheme.validate(&enterprise, &others).is_ok());
+
+        // A new namespace must not take over an existing alias either
+        let mut fetch = naming("fetch");
+        fetch
+            .tool_aliases
+            .insert("web_fetch".to_string(), "fetch".to_string());
+        assert!(matches!(
+            scheme.validate(&naming("web"), &[fetch.clone()]),
+            Err(AppError::Conflict(_))
+        ));
+        assert!(scheme.validate(&naming("web-tools"), &[fetch]).is_ok());
+    }
+
+    #[test]
+    fn test_separator_must_not_overlap_namespace() {
+        assert!(NamingScheme::new("-").is_err());
+        assert!(NamingScheme::new("").is_err());
+        assert!(NamingScheme::new(".").is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+//! Cursor pagination of aggregated list results
+//!
+//! The cursors handed to clients are opaque to them: hex-encoded JSON naming
+//! the list, the exposed key of the last item served and how many items had
+//! been served. Resuming after that item keeps pages stable when backends
+//! come and go between requests; the count is only used if the item itself
+//! disappeared from the list.
+
+use crate::utils::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Default number of items in a page of an aggregated list
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Position in an aggregated list
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Cursor {
+    /// Result key of the list the cursor belongs to (e.g. `tools`)
+    list: String,
+    /// Exposed key of the last item served
+    after: String,
+    /// Number of items served so far
+    served: usize,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        hex::encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Option<Self> {
+        serde_json::from_slice(&hex::decode(cursor).ok()?).ok()
+    }
+}
+
+/// One page of an aggregated list
+#[derive(Debug)]
+pub struct Page {
+    pub items: Vec<Value>,
+    /// Cursor for the next page, if there is one
+    pub next_cursor: Option<String>,
+}
+
+/// Cut the page following `cursor` out of `items`
+///
+/// `list` is the list's result key and `key_field` the field identifying
+/// an item. A cursor that was not issued for this list is rejected as a
+/// validation error.
+pub fn paginate(
+    list: &str,
+    key_field: &str,
+    items: Vec<Value>,
+    cursor: Option<&str>,
+    page_size: usize,
+) -> Result<Page, AppError> {
+    let key = |item: &Value| {
+        item.get(key_field)
+            .and_then(|k| k.as_str())
+            .map(str::to_string)
+    };
+
+    let start = match cursor {
+        None => 0,
+        Some(cursor) => {
+            let cursor = Cursor::decode(cursor)
+                .filter(|c| c.list == list)
+                .ok_or_else(|| AppError::Validation("Invalid cursor".to_string()))?;
+            items
+                .iter()
+                .position(|item| key(item).as_deref() == Some(cursor.after.as_str()))
+                .map(|i| i + 1)
+                .unwrap_or(cursor.served)
+                .min(items.len())
+        }
+    };
+
+    let end = start.saturating_add(page_size.max(1)).min(items.len());
+    let next_cursor = match items.get(end.wrapping_sub(1)).and_then(key) {
+        Some(after) if end < items.len() => Some(
+            Cursor {
+                list: list.to_string(),
+                after,
+                served: end,
+            }
+            .encode(),
+        ),
+        _ => None,
+    };
+
+    let items = items.into_iter().skip(start).take(end - start).collect();
+    Ok(Page { items, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tools(names: &[&str]) -> Vec<Value> {
+        names.iter().map(|n| json!({ "name": n })).collect()
+    }
+
+    fn names(page: &Page) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|i| i["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_pages_follow_cursor() {
+        let all = tools(&["a_1", "a_2", "b_1", "b_2", "c_1"]);
+
+        let first = paginate("tools", "name", all.clone(), None, 2).unwrap();
+        assert_eq!(names(&first), ["a_1", "a_2"]);
+
+        let cursor = first.next_cursor.unwrap();
+        let second = paginate("tools", "name", all.clone(), Some(&cursor), 2).unwrap();
+        assert_eq!(names(&second), ["b_1", "b_2"]);
+
+        // Backend "a" went away: the cursor still resumes after b_2
+        let cursor = second.next_cursor.unwrap();
+        let shrunk = tools(&["b_1", "b_2", "c_1"]);
+        let last = paginate("tools", "name", shrunk, Some(&cursor), 2).unwrap();
+        assert_eq!(names(&last), ["c_1"]);
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_foreign_cursor_is_rejected() {
+        let all = tools(&["a", "b", "c"]);
+        let cursor = paginate("tools", "name", all.clone(), None, 1)
+            .unwrap()
+            .next_cursor
+            .unwrap();
+
+        assert!(paginate("prompts", "name", all.clone(), Some(&cursor), 1).is_err());
+        assert!(paginate("tools", "name", all, Some("not-a-cursor"), 1).is_err());
+    }
+}
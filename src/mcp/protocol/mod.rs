@@ -1,5 +1,7 @@
 //! MCP Protocol definitions
 
 pub mod types;
+pub mod version;
 
 pub use types::*;
+pub use version::ProtocolVersion;
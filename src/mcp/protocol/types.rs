@@ -1,13 +1,45 @@
 //! MCP Protocol types
 
+use crate::utils::patterns;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::version::ProtocolVersion;
 
 /// JSON-RPC version
 pub const JSONRPC_VERSION: &str = "2.0";
 
-/// MCP Protocol version
-pub const MCP_PROTOCOL_VERSION: &str = "2025-03-26";
+/// Latest MCP Protocol version, offered to backends and unknown clients
+pub const MCP_PROTOCOL_VERSION: &str = ProtocolVersion::LATEST.as_str();
+
+/// JSON-RPC error codes
+pub mod error_codes {
+    /// The body is not valid JSON
+    pub const PARSE_ERROR: i32 = -32700;
+    /// The JSON is not a valid JSON-RPC message
+    pub const INVALID_REQUEST: i32 = -32600;
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    pub const INVALID_PARAMS: i32 = -32602;
+    pub const INTERNAL_ERROR: i32 = -32603;
+
+    // Gateway errors, from the range JSON-RPC reserves for implementations
+    /// Failure inside the gateway itself
+    pub const SERVER_ERROR: i32 = -32000;
+    /// A backend could not be reached or gave an unusable answer
+    pub const UPSTREAM_ERROR: i32 = -32001;
+    /// A backend did not answer in time
+    pub const UPSTREAM_TIMEOUT: i32 = -32002;
+    pub const NOT_FOUND: i32 = -32003;
+    pub const UNAUTHORIZED: i32 = -32004;
+    pub const CONFLICT: i32 = -32005;
+    /// A backend's circuit breaker is open, so it was not asked
+    pub const CIRCUIT_OPEN: i32 = -32006;
+
+    /// The client cancelled the request, as in the Language Server Protocol
+    pub const REQUEST_CANCELLED: i32 = -32800;
+}
 
 /// JSON-RPC Request (can also be a notification if id is None)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +81,31 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
+/// Any JSON-RPC message received from a peer
+#[derive(Debug, Clone)]
+pub enum JsonRpcMessage {
+    Request(JsonRpcRequest),
+    Notification(JsonRpcNotification),
+    Response(JsonRpcResponse),
+}
+
+impl JsonRpcMessage {
+    /// Classify a raw JSON value as a request, notification or response
+    ///
+    /// Messages carrying a `method` are requests (with `id`) or notifications
+    /// (without); everything else must be a response.
+    pub fn from_value(value: Value) -> Result<Self, serde_json::Error> {
+        let has_method = value.get("method").is_some();
+        let has_id = value.get("id").is_some_and(|id| !id.is_null());
+
+        match (has_method, has_id) {
+            (true, true) => serde_json::from_value(value).map(Self::Request),
+            (true, false) => serde_json::from_value(value).map(Self::Notification),
+            (false, _) => serde_json::from_value(value).map(Self::Response),
+        }
+    }
+}
+
 /// Request ID (can be string or number)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(untagged)]
@@ -69,6 +126,18 @@ impl From<i64> for RequestId {
     }
 }
 
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestId::String(s) => write!(f, "{}", s),
+            RequestId::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Progress token from a request's `_meta` (string or number, like an id)
+pub type ProgressToken = RequestId;
+
 /// MCP Initialize Request params
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -79,26 +148,47 @@ pub struct InitializeParams {
 }
 
 /// Client capabilities
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ClientCapabilities {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub roots: Option<RootsCapability>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sampling: Option<SamplingCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elicitation: Option<ElicitationCapability>,
 }
 
 /// Roots capability
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RootsCapability {
     #[serde(default)]
     pub list_changed: bool,
 }
 
-/// Sampling capability
+/// A directory or file a client exposes to servers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Root {
+    /// `file://` URI of the root
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Roots list result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootsListResult {
+    pub roots: Vec<Root>,
+}
+
+/// Sampling capability
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SamplingCapability {}
 
+/// Elicitation capability (asking the user for input on a server's behalf)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElicitationCapability {}
+
 /// Client info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientInfo {
@@ -124,6 +214,8 @@ pub struct ServerCapabilities {
     pub resources: Option<ResourcesCapability>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub prompts: Option<PromptsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completions: Option<CompletionsCapability>,
 }
 
 /// Tools capability
@@ -152,6 +244,10 @@ pub struct PromptsCapability {
     pub list_changed: bool,
 }
 
+/// Completions capability (argument autocompletion)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionsCapability {}
+
 /// Server info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerInfo {
@@ -159,50 +255,187 @@ pub struct ServerInfo {
     pub version: String,
 }
 
+/// Who an object is meant for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// Hints on how a client should use content or a resource
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Vec<Role>>,
+    /// Importance from 0 (least) to 1 (most)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+    /// ISO 8601 timestamp of the last change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
 /// Tool definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Tool {
     pub name: String,
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub input_schema: Value,
+    /// Schema the tool's `structuredContent` conforms to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Hints on a tool's behavior; clients must not rely on them for safety
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The tool does not modify its environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// The tool may perform destructive updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// Repeating a call with the same arguments has no further effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// The tool interacts with entities outside the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Tools list result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ToolsListResult {
     pub tools: Vec<Tool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Tool call params
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallParams {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Value::is_null")]
     pub arguments: Value,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
 }
 
 /// Tool call result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolCallResult {
+    #[serde(default)]
     pub content: Vec<Content>,
+    /// Result conforming to the tool's `outputSchema`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
     #[serde(default)]
     pub is_error: bool,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-/// Content types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "lowercase")]
+/// Content of tool results and prompt messages
+///
+/// Content types from newer protocol revisions are kept as they came in
+/// [`Content::Other`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Content {
-    Text { text: String },
-    Image { data: String, mime_type: String },
-    Resource { resource: ResourceContent },
+    Text(TextContent),
+    Image(MediaContent),
+    Audio(MediaContent),
+    /// A resource the client may read, without its contents
+    ResourceLink(Resource),
+    /// A resource with its contents
+    Resource(EmbeddedResource),
+    #[serde(untagged)]
+    Other(Value),
+}
+
+impl Content {
+    /// Plain text content
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(TextContent {
+            text: text.into(),
+            annotations: None,
+            meta: None,
+            extra: Map::new(),
+        })
+    }
+}
+
+/// Text content
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TextContent {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Base64-encoded image or audio content
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaContent {
+    pub data: String,
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Resource embedded in content
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddedResource {
+    pub resource: ResourceContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Resource content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceContent {
     pub uri: String,
@@ -212,40 +445,154 @@ pub struct ResourceContent {
     pub text: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub blob: Option<String>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Resource definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Resource {
     pub uri: String,
     pub name: String,
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
+    /// Size in bytes, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Resources list result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResourcesListResult {
     pub resources: Vec<Resource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Params of requests naming a resource (`resources/read`, `resources/subscribe`...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceUriParams {
+    pub uri: String,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// Resource read result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContent>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Parameterized resource, addressed by an RFC 6570 URI template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplate {
+    pub uri_template: String,
+    pub name: String,
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ResourceTemplate {
+    /// Whether `uri` is an expansion of this template
+    ///
+    /// Simple expressions (`{id}`) match within a path segment; reserved and
+    /// fragment expressions (`{+path}`, `{#frag}`) match anything. URIs come
+    /// from clients, so the template is compiled to a regular expression,
+    /// once, and matching takes time linear in the URI's length.
+    pub fn matches(&self, uri: &str) -> bool {
+        template_pattern(&self.uri_template)
+            .and_then(|pattern| patterns::compiled(&pattern))
+            .is_some_and(|regex| regex.is_match(uri))
+    }
+}
+
+/// Anchored regular expression matching the expansions of `template`
+///
+/// `None` if the template leaves an expression unclosed.
+fn template_pattern(template: &str) -> Option<String> {
+    let mut pattern = String::from(r"(?s)\A");
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        pattern.push_str(&regex::escape(&rest[..open]));
+        let close = open + rest[open..].find('}')?;
+        let unrestricted = rest[open + 1..close].starts_with(['+', '#']);
+        pattern.push_str(if unrestricted { ".*" } else { "[^/?#&]*" });
+        rest = &rest[close + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push_str(r"\z");
+    Some(pattern)
+}
+
+/// Resource templates list result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceTemplatesListResult {
+    pub resource_templates: Vec<ResourceTemplate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Prompt definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Prompt {
     pub name: String,
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub arguments: Vec<PromptArgument>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Prompt argument
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptArgument {
     pub name: String,
+    /// Display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default)]
@@ -254,8 +601,41 @@ pub struct PromptArgument {
 
 /// Prompts list result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PromptsListResult {
     pub prompts: Vec<Prompt>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Prompt get params
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptParams {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub arguments: HashMap<String, String>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// Prompt get result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+    /// Fields without a type here, passed through untouched
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A message of a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: Role,
+    pub content: Content,
 }
 
 impl JsonRpcRequest {
@@ -295,3 +675,68 @@ impl JsonRpcResponse {
         }
     }
 }
+
+impl JsonRpcNotification {
+    /// Create a new JSON-RPC notification
+    pub fn new(method: &str, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_call_result_round_trips() {
+        let result = json!({
+            "content": [
+                {"type": "text", "text": "21°C", "annotations": {"audience": ["user"], "priority": 0.5}},
+                {"type": "audio", "data": "UklGRg==", "mimeType": "audio/wav"},
+                {"type": "resource_link", "uri": "file:///log.txt", "name": "log", "size": 12},
+                {"type": "hologram", "frames": 3}
+            ],
+            "structuredContent": {"temperature": 21},
+            "isError": false,
+            "_meta": {"trace": "abc"},
+            "futureField": true
+        });
+
+        let parsed: ToolCallResult = serde_json::from_value(result.clone()).unwrap();
+        assert!(matches!(parsed.content[1], Content::Audio(_)));
+        assert!(matches!(parsed.content[2], Content::ResourceLink(_)));
+        assert!(matches!(parsed.content[3], Content::Other(_)));
+        assert_eq!(serde_json::to_value(parsed).unwrap(), result);
+    }
+
+    #[test]
+    fn test_tool_round_trips() {
+        let tool = json!({
+            "name": "delete_file",
+            "title": "Delete file",
+            "inputSchema": {"type": "object"},
+            "outputSchema": {"type": "object"},
+            "annotations": {"destructiveHint": true, "idempotentHint": true},
+            "icons": []
+        });
+
+        let parsed: Tool = serde_json::from_value(tool.clone()).unwrap();
+        let annotations = parsed.annotations.as_ref().unwrap();
+        assert_eq!(annotations.destructive_hint, Some(true));
+        assert!(parsed.extra.contains_key("icons"));
+        assert_eq!(serde_json::to_value(parsed).unwrap(), tool);
+    }
+
+    #[test]
+    fn test_text_content() {
+        assert_eq!(
+            serde_json::to_value(Content::text("hi")).unwrap(),
+            json!({"type": "text", "text": "hi"})
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! MCP protocol revisions and translation between them
+//!
+//! Clients and backends each negotiate their own revision with the gateway,
+//! so a message may cross between peers that speak different ones. Results
+//! are adapted to the revision of the peer receiving them: fields a revision
+//! does not know (tool `annotations`, `outputSchema`, `structuredContent`,
+//! `title`...) are dropped, and content types it cannot render become text.
+//! Newer revisions only add to older ones, so results passed upwards are
+//! left as they are.
+
+use serde_json::{json, Value};
+use std::fmt;
+
+/// A revision of the MCP specification, ordered oldest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    V2024_11_05,
+    V2025_03_26,
+    V2025_06_18,
+}
+
+impl ProtocolVersion {
+    /// The newest revision the gateway speaks
+    pub const LATEST: Self = Self::V2025_06_18;
+
+    /// Every revision the gateway speaks, newest first
+    pub const SUPPORTED: [Self; 3] = [Self::V2025_06_18, Self::V2025_03_26, Self::V2024_11_05];
+
+    /// The revision's date string (e.g. `2025-03-26`)
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::V2024_11_05 => "2024-11-05",
+            Self::V2025_03_26 => "2025-03-26",
+            Self::V2025_06_18 => "2025-06-18",
+        }
+    }
+
+    /// Parse a revision the gateway speaks
+    pub fn parse(version: &str) -> Option<Self> {
+        Self::SUPPORTED.into_iter().find(|v| v.as_str() == version)
+    }
+
+    /// The revision to answer a client requesting `requested` with
+    ///
+    /// A supported revision is accepted as is. Otherwise the client is
+    /// offered the latest revision, which is the highest both sides know
+    /// if the client is newer, and lets an older client disconnect.
+    pub fn negotiate(requested: &str) -> Self {
+        Self::parse(requested).unwrap_or(Self::LATEST)
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Adapt the result of `method` for a peer speaking `version`
+pub fn adapt_result(method: &str, result: &mut Value, version: ProtocolVersion) {
+    match method {
+        "tools/list" => {
+            for tool in items(result, "tools") {
+                if version < ProtocolVersion::V2025_03_26 {
+                    tool.remove("annotations");
+                }
+                if version < ProtocolVersion::V2025_06_18 {
+                    tool.remove("outputSchema");
+                    tool.remove("title");
+                }
+            }
+        }
+        "resources/list" | "resources/templates/list" | "prompts/list" => {
+            let key = match method {
+                "resources/list" => "resources",
+                "resources/templates/list" => "resourceTemplates",
+                _ => "prompts",
+            };
+            if version < ProtocolVersion::V2025_06_18 {
+                for item in items(result, key) {
+                    item.remove("title");
+                }
+            }
+        }
+        "tools/call" => {
+            if version < ProtocolVersion::V2025_06_18 {
+                unstructure(result);
+            }
+            if let Some(content) = result.get_mut("content").and_then(Value::as_array_mut) {
+                for block in content {
+                    adapt_content(block, version);
+                }
+            }
+        }
+        "prompts/get" => {
+            if let Some(messages) = result.get_mut("messages").and_then(Value::as_array_mut) {
+                for message in messages {
+                    if let Some(block) = message.get_mut("content") {
+                        adapt_content(block, version);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Adapt the params of a `method` request for a backend speaking `version`
+pub fn adapt_params(method: &str, params: &mut Value, version: ProtocolVersion) {
+    let Some(params) = params.as_object_mut() else {
+        return;
+    };
+    if method == "completion/complete" && version < ProtocolVersion::V2025_06_18 {
+        // Previously completed arguments only exist since 2025-06-18
+        params.remove("context");
+    }
+}
+
+/// The objects listed under `key` in a list result
+fn items<'a>(
+    result: &'a mut Value,
+    key: &str,
+) -> impl Iterator<Item = &'a mut serde_json::Map<String, Value>> {
+    result
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+/// Replace `structuredContent` with its serialized form as text content
+///
+/// Backends should already send both; the text is only added when the
+/// result has no content of its own.
+fn unstructure(result: &mut Value) {
+    let Some(result) = result.as_object_mut() else {
+        return;
+    };
+    let Some(structured) = result.remove("structuredContent") else {
+        return;
+    };
+    let has_content = result
+        .get("content")
+        .and_then(Value::as_array)
+        .is_some_and(|c| !c.is_empty());
+    if !has_content {
+        result.insert(
+            "content".to_string(),
+            json!([{ "type": "text", "text": structured.to_string() }]),
+        );
+    }
+}
+
+/// Turn a content block the revision has no type for into text
+fn adapt_content(block: &mut Value, version: ProtocolVersion) {
+    let text = match block.get("type").and_then(Value::as_str) {
+        Some("audio") if version < ProtocolVersion::V2025_03_26 => {
+            let mime_type = block.get("mimeType").and_then(Value::as_str);
+            format!("[audio: {}]", mime_type.unwrap_or("unknown type"))
+        }
+        Some("resource_link") if version < ProtocolVersion::V2025_06_18 => {
+            let uri = block.get("uri").and_then(Value::as_str).unwrap_or_default();
+            match block.get("name").and_then(Value::as_str) {
+                Some(name) => format!("{} ({})", name, uri),
+                None => uri.to_string(),
+            }
+        }
+        _ => return,
+    };
+    *block = json!({ "type": "text", "text": text });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            ProtocolVersion::negotiate("2024-11-05"),
+            ProtocolVersion::V2024_11_05
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate("2099-01-01"),
+            ProtocolVersion::LATEST
+        );
+        assert_eq!(ProtocolVersion::negotiate("1.0"), ProtocolVersion::LATEST);
+        assert!(ProtocolVersion::V2024_11_05 < ProtocolVersion::V2025_03_26);
+    }
+
+    #[test]
+    fn test_tools_are_adapted_for_old_clients() {
+        let tools = json!({
+            "tools": [{
+                "name": "search",
+                "title": "Search",
+                "inputSchema": {"type": "object"},
+                "outputSchema": {"type": "object"},
+                "annotations": {"readOnlyHint": true}
+            }]
+        });
+
+        let mut result = tools.clone();
+        adapt_result("tools/list", &mut result, ProtocolVersion::V2025_03_26);
+        assert_eq!(result["tools"][0]["annotations"]["readOnlyHint"], true);
+        assert!(result["tools"][0].get("outputSchema").is_none());
+        assert!(result["tools"][0].get("title").is_none());
+
+        let mut result = tools.clone();
+        adapt_result("tools/list", &mut result, ProtocolVersion::V2024_11_05);
+        assert!(result["tools"][0].get("annotations").is_none());
+
+        let mut result = tools.clone();
+        adapt_result("tools/list", &mut result, ProtocolVersion::LATEST);
+        assert_eq!(result, tools);
+    }
+
+    #[test]
+    fn test_structured_content_becomes_text() {
+        let mut result = json!({"structuredContent": {"temperature": 21}, "isError": false});
+        adapt_result("tools/call", &mut result, ProtocolVersion::V2025_03_26);
+        assert!(result.get("structuredContent").is_none());
+        assert_eq!(result["content"][0]["text"], r#"{"temperature":21}"#);
+
+        let mut result = json!({
+            "content": [{"type": "resource_link", "uri": "file:///a.txt", "name": "a"}],
+            "structuredContent": {}
+        });
+        adapt_result("tools/call", &mut result, ProtocolVersion::V2025_03_26);
+        assert_eq!(
+            result["content"],
+            json!([{"type": "text", "text": "a (file:///a.txt)"}])
+        );
+    }
+
+    #[test]
+    fn test_completion_context_is_dropped_for_old_backends() {
+        let mut params = json!({"ref": {}, "argument": {}, "context": {"arguments": {}}});
+        adapt_params("completion/complete", &mut params, ProtocolVersion::LATEST);
+        assert!(params.get("context").is_some());
+        adapt_params(
+            "completion/complete",
+            &mut params,
+            ProtocolVersion::V2025_03_26,
+        );
+        assert!(params.get("context").is_none());
+    }
+}
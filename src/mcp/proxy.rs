@@ -1,137 +1,529 @@
 //! MCP Proxy for routing requests to backend servers
 
 use crate::db::models::McpServer;
-use crate::mcp::protocol::{JsonRpcRequest, JsonRpcResponse};
+use crate::mcp::fanout::FanOutTimeouts;
+use crate::mcp::protocol::{
+    error_codes, ClientCapabilities, JsonRpcError, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, RootsCapability, ToolCallParams, ToolCallResult,
+};
+use crate::mcp::resilience::{is_backend_failure, BreakerStatus, CircuitBreaker, ResiliencePolicy};
+use crate::mcp::server_manager::McpServerManager;
+use crate::mcp::session::{BackendSession, NegotiatedSessionInfo};
+use crate::mcp::transport::{
+    BackendEvents, BackendNotification, BackendRequest, CancelSignal, McpTransport, SseTransport,
+    StdioTransport, StreamableHttpTransport, SubscribedResources, SERVER_REQUEST_TIMEOUT,
+};
 use crate::utils::AppError;
 use reqwest::Client;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use uuid::Uuid;
+
+/// Notifications buffered per subscriber before the slowest one starts lagging
+const NOTIFICATION_CAPACITY: usize = 256;
+
+/// Server requests queued before backends wait to hand over more
+const SERVER_REQUEST_CAPACITY: usize = 64;
+
+/// Pages of a single list method read from a backend before giving up
+const MAX_LIST_PAGES: usize = 1000;
+
+/// Per-server slot, locked while connecting so concurrent callers share one session
+type SessionSlot = Arc<Mutex<Option<Arc<BackendSession>>>>;
 
 /// MCP Proxy for forwarding requests to backend servers
 pub struct McpProxy {
-    http_client: Client,
+    server_manager: Arc<McpServerManager>,
+    sessions: RwLock<HashMap<Uuid, SessionSlot>>,
+    breakers: RwLock<HashMap<Uuid, Arc<CircuitBreaker>>>,
+    /// Resource subscriptions per server, kept across its sessions
+    subscribed: RwLock<HashMap<Uuid, Arc<SubscribedResources>>>,
+    fanout_timeouts: FanOutTimeouts,
+    notifications: broadcast::Sender<BackendNotification>,
+    server_requests: RwLock<Option<mpsc::Sender<BackendRequest>>>,
+    /// Time clients get to answer requests relayed from backends
+    server_request_timeout: Duration,
+    /// Client capabilities the gateway can currently back with a client
+    client_capabilities: RwLock<ClientCapabilities>,
 }
 
 impl McpProxy {
     /// Create a new MCP proxy
     pub fn new() -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
-
         Self {
-            http_client: client,
+            server_manager: Arc::new(McpServerManager::new()),
+            sessions: RwLock::new(HashMap::new()),
+            breakers: RwLock::new(HashMap::new()),
+            subscribed: RwLock::new(HashMap::new()),
+            fanout_timeouts: FanOutTimeouts::default(),
+            notifications: broadcast::channel(NOTIFICATION_CAPACITY).0,
+            server_requests: RwLock::new(None),
+            server_request_timeout: SERVER_REQUEST_TIMEOUT,
+            client_capabilities: RwLock::new(ClientCapabilities::default()),
         }
     }
 
-    /// Forward a JSON-RPC request to a backend MCP server
-    pub async fn forward_request(
+    /// Set the deadlines used when aggregating lists across backends
+    pub fn with_fanout_timeouts(mut self, timeouts: FanOutTimeouts) -> Self {
+        self.fanout_timeouts = timeouts;
+        self
+    }
+
+    /// Deadlines used when aggregating lists across backends
+    pub fn fanout_timeouts(&self) -> FanOutTimeouts {
+        self.fanout_timeouts
+    }
+
+    /// Set how long clients get to answer requests relayed from backends
+    pub fn with_server_request_timeout(mut self, timeout: Duration) -> Self {
+        self.server_request_timeout = timeout;
+        self
+    }
+
+    /// Offer backends the client capabilities connected clients can serve
+    ///
+    /// Sessions negotiated with different capabilities are re-established
+    /// on their next use, so backends learn about the change.
+    pub async fn offer_client_capabilities(&self, capabilities: ClientCapabilities) {
+        let mut offered = self.client_capabilities.write().await;
+        if *offered != capabilities {
+            tracing::debug!(
+                ?capabilities,
+                "Client capabilities offered to backends changed"
+            );
+            *offered = capabilities;
+        }
+    }
+
+    /// Client capabilities to offer a server, as allowed by its policy
+    ///
+    /// A server with configured roots is always offered roots, which never
+    /// change while its session lives.
+    async fn offered_capabilities(&self, server: &McpServer) -> ClientCapabilities {
+        let mut capabilities = self.client_capabilities.read().await.clone();
+        if server.roots.is_some() {
+            capabilities.roots = Some(RootsCapability {
+                list_changed: false,
+            });
+        }
+        if !server.allow_sampling {
+            capabilities.sampling = None;
+        }
+        if !server.allow_elicitation {
+            capabilities.elicitation = None;
+        }
+        capabilities
+    }
+
+    /// Receive notifications sent by any backend
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<BackendNotification> {
+        self.notifications.subscribe()
+    }
+
+    /// Receive requests that backends send to the client side
+    ///
+    /// Until this is called such requests are refused with "method not
+    /// found". Sessions opened earlier keep their previous handler.
+    pub async fn handle_server_requests(&self) -> mpsc::Receiver<BackendRequest> {
+        let (tx, rx) = mpsc::channel(SERVER_REQUEST_CAPACITY);
+        *self.server_requests.write().await = Some(tx);
+        rx
+    }
+
+    /// Tell backends that see client roots that the roots changed
+    ///
+    /// Sessions still being established are skipped; they ask for the
+    /// roots once connected anyway.
+    pub async fn notify_roots_changed(&self) {
+        for session in self.open_sessions().await {
+            let client_roots = session
+                .offered_capabilities()
+                .roots
+                .as_ref()
+                .is_some_and(|r| r.list_changed);
+            if !client_roots {
+                continue;
+            }
+
+            let notification = JsonRpcNotification::new("notifications/roots/list_changed", None);
+            if let Err(e) = session.notify(notification).await {
+                tracing::warn!("Failed to forward roots change to MCP server: {}", e);
+            }
+        }
+    }
+
+    /// Whether any open backend session passes `check`, e.g. advertises a capability
+    ///
+    /// Only sessions already established are asked; none is opened for this.
+    pub async fn any_session(&self, check: fn(&BackendSession) -> bool) -> bool {
+        self.open_sessions()
+            .await
+            .iter()
+            .any(|session| check(session))
+    }
+
+    /// Sessions currently open, skipping those still being established
+    async fn open_sessions(&self) -> Vec<Arc<BackendSession>> {
+        let slots: Vec<SessionSlot> = self.sessions.read().await.values().cloned().collect();
+        slots
+            .iter()
+            .filter_map(|slot| slot.try_lock().ok()?.clone())
+            .collect()
+    }
+
+    /// Get the initialized session for a server, establishing it if needed
+    ///
+    /// A session is re-established when its connection died, the server
+    /// row has been updated since it was opened or the client capabilities
+    /// it would be offered changed. While the server's circuit rejects
+    /// requests this fails with [`AppError::CircuitOpen`] without touching
+    /// the backend.
+    pub async fn session(&self, server: &McpServer) -> Result<Arc<BackendSession>, AppError> {
+        let breaker = self.breaker(server).await;
+        let _permit = breaker
+            .try_acquire()
+            .ok_or_else(|| circuit_open(server, &breaker))?;
+        self.connect(server, &breaker).await
+    }
+
+    /// Session for a server, establishing it if needed
+    ///
+    /// Only establishing a session counts towards the circuit breaker;
+    /// reusing a live one says nothing about the backend.
+    async fn connect(
         &self,
         server: &McpServer,
-        request: JsonRpcRequest,
-    ) -> Result<JsonRpcResponse, AppError> {
-        match server.protocol.as_str() {
-            "http" => self.forward_http(server, request).await,
-            "sse" => {
-                // SSE support is planned for v2
-                Err(AppError::McpProtocol(
-                    "SSE protocol not yet implemented".to_string(),
-                ))
-            }
-            "stdio" => {
-                // stdio support is planned for v2
-                Err(AppError::McpProtocol(
-                    "stdio protocol not yet implemented".to_string(),
-                ))
+        breaker: &CircuitBreaker,
+    ) -> Result<Arc<BackendSession>, AppError> {
+        let (session, established) = self.open(server).await;
+        if established {
+            record(server, breaker, &session);
+        }
+        session
+    }
+
+    /// Session for a server, establishing it if needed, without counting it
+    ///
+    /// Also tells whether the backend was contacted to establish it.
+    async fn open(&self, server: &McpServer) -> (Result<Arc<BackendSession>, AppError>, bool) {
+        let slot = {
+            let sessions = self.sessions.read().await;
+            sessions.get(&server.id).cloned()
+        };
+        let slot = match slot {
+            Some(slot) => slot,
+            None => self
+                .sessions
+                .write()
+                .await
+                .entry(server.id)
+                .or_default()
+                .clone(),
+        };
+
+        let offered = self.offered_capabilities(server).await;
+        let mut guard = slot.lock().await;
+        if let Some(session) = guard.as_ref() {
+            if session.is_usable(server.updated_at, &offered) {
+                return (Ok(session.clone()), false);
             }
-            _ => Err(AppError::McpProtocol(format!(
-                "Unknown protocol: {}",
-                server.protocol
-            ))),
         }
+        if let Some(stale) = guard.take() {
+            stale.close().await;
+        }
+
+        let session = self.establish(server, offered).await;
+        if let Ok(session) = &session {
+            *guard = Some(session.clone());
+        }
+        (session, true)
     }
 
-    /// Forward request via HTTP
-    async fn forward_http(
+    /// Open a transport to a server and run the initialize handshake
+    ///
+    /// The new session subscribes to every resource the server's previous
+    /// sessions were subscribed to, so clients keep getting updates.
+    async fn establish(
         &self,
         server: &McpServer,
-        request: JsonRpcRequest,
-    ) -> Result<JsonRpcResponse, AppError> {
-        let response = self
-            .http_client
-            .post(&server.url)
-            .json(&request)
-            .send()
+        offered: ClientCapabilities,
+    ) -> Result<Arc<BackendSession>, AppError> {
+        let policy = ResiliencePolicy::from(server);
+        let subscribed = self.subscribed(server.id).await;
+        let mut events = BackendEvents::new(server, self.notifications.clone())
+            .with_request_timeout(self.server_request_timeout)
+            .with_subscribed(subscribed.clone());
+        if let Some(requests) = self.server_requests.read().await.clone() {
+            events = events.with_requests(requests);
+        }
+        let transport: Arc<dyn McpTransport> = match server.protocol.as_str() {
+            "http" => Arc::new(StreamableHttpTransport::new(
+                backend_client(&policy, Some(policy.request_timeout))?,
+                server,
+                events,
+            )),
+            "stdio" => {
+                Arc::new(StdioTransport::spawn(self.server_manager.clone(), server, events).await?)
+            }
+            // The event stream stays open, so only connecting is bounded
+            "sse" => Arc::new(
+                SseTransport::connect(backend_client(&policy, None)?, server, events).await?,
+            ),
+            other => {
+                return Err(AppError::McpProtocol(format!(
+                    "Unknown protocol: {}",
+                    other
+                )))
+            }
+        };
+
+        let session =
+            Arc::new(BackendSession::establish(transport, server.updated_at, offered).await?);
+        let init = session.initialize_result();
+        tracing::info!(
+            server_name = %server.name,
+            protocol_version = %init.protocol_version,
+            "Connected to MCP server {} {}",
+            init.server_info.name,
+            init.server_info.version
+        );
+
+        for request in subscribed.requests() {
+            let error = match session.request(request).await {
+                Ok(response) => response.error.map(|e| e.message),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(error) = error {
+                tracing::warn!(server_name = %server.name, "Failed to resubscribe: {}", error);
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Resources the gateway is subscribed to on a server
+    ///
+    /// Unlike the circuit breaker this survives [`McpProxy::disconnect`], so
+    /// the session opened for an updated server subscribes again.
+    async fn subscribed(&self, server_id: Uuid) -> Arc<SubscribedResources> {
+        if let Some(subscribed) = self.subscribed.read().await.get(&server_id) {
+            return subscribed.clone();
+        }
+        self.subscribed
+            .write()
             .await
-            .map_err(|e| {
-                AppError::McpProtocol(format!("Failed to connect to MCP server: {}", e))
-            })?;
+            .entry(server_id)
+            .or_default()
+            .clone()
+    }
 
-        if !response.status().is_success() {
-            return Err(AppError::McpProtocol(format!(
-                "MCP server returned error status: {}",
-                response.status()
-            )));
+    /// The circuit breaker guarding a server
+    ///
+    /// It is created from the server's settings on first use and dropped
+    /// by [`McpProxy::disconnect`], so an updated server starts closed.
+    async fn breaker(&self, server: &McpServer) -> Arc<CircuitBreaker> {
+        if let Some(breaker) = self.breakers.read().await.get(&server.id) {
+            return breaker.clone();
         }
+        let policy = ResiliencePolicy::from(server);
+        self.breakers
+            .write()
+            .await
+            .entry(server.id)
+            .or_insert_with(|| {
+                Arc::new(CircuitBreaker::new(
+                    policy.breaker_threshold,
+                    policy.breaker_cooldown,
+                ))
+            })
+            .clone()
+    }
+
+    /// Current state of a server's circuit breaker
+    pub async fn breaker_status(&self, server: &McpServer) -> BreakerStatus {
+        self.breaker(server).await.status()
+    }
+
+    /// Run `attempt` until it succeeds, fails for good or runs out of retries
+    ///
+    /// Only backend failures (see [`is_backend_failure`]) are retried, after
+    /// the policy's backoff. Each attempt gets its own [`CancelSignal`],
+    /// fired when `cancel` fires; a cancelled request is never retried.
+    /// The call holds one permit from the circuit throughout and its final
+    /// result counts towards the breaker once, however many attempts it
+    /// took. Retries stop once other calls open the circuit, returning the
+    /// last backend error.
+    async fn resilient<T, F, Fut>(
+        &self,
+        server: &McpServer,
+        breaker: &CircuitBreaker,
+        retries: u32,
+        cancel: CancelSignal,
+        mut attempt: F,
+    ) -> Result<T, AppError>
+    where
+        F: FnMut(CancelSignal) -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let policy = ResiliencePolicy::from(server);
+        let cancelled = cancel.cancelled();
+        tokio::pin!(cancelled);
+
+        let Some(_permit) = breaker.try_acquire() else {
+            return Err(circuit_open(server, breaker));
+        };
 
-        let json_response: JsonRpcResponse = response.json().await.map_err(|e| {
-            AppError::McpProtocol(format!("Failed to parse MCP server response: {}", e))
-        })?;
+        let mut retry = 0;
+        let result = loop {
+            let (fire, signal) = CancelSignal::new();
+            let call = attempt(signal);
+            tokio::pin!(call);
+            let error = tokio::select! {
+                result = &mut call => match result {
+                    Err(e) if is_backend_failure(&e) && retry < retries && !breaker.is_open() => e,
+                    result => break result,
+                },
+                reason = &mut cancelled => {
+                    let _ = fire.send(reason);
+                    break call.await;
+                }
+            };
 
-        Ok(json_response)
+            let delay = policy.backoff(retry);
+            retry += 1;
+            tracing::debug!(
+                server_name = %server.name,
+                "Retry {}/{} in {}ms after: {}",
+                retry,
+                retries,
+                delay.as_millis(),
+                error
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = &mut cancelled => {
+                    return Err(AppError::Cancelled(
+                        "Request cancelled by the client".to_string(),
+                    ));
+                }
+            }
+        };
+        record(server, breaker, &result);
+        result
     }
 
-    /// List tools from a backend server
-    pub async fn list_tools(&self, server: &McpServer) -> Result<Vec<serde_json::Value>, AppError> {
-        let request = JsonRpcRequest::new(1i64, "tools/list", None);
-        let response = self.forward_request(server, request).await?;
+    /// Negotiated session details for a server, if a session is open
+    pub async fn session_info(&self, server_id: Uuid) -> Option<NegotiatedSessionInfo> {
+        let slot = self.sessions.read().await.get(&server_id).cloned()?;
+        let guard = slot.lock().await;
+        guard.as_ref().map(|session| session.info())
+    }
 
-        if let Some(error) = response.error {
-            return Err(AppError::McpProtocol(format!(
-                "MCP error: {} (code: {})",
-                error.message, error.code
-            )));
+    /// Close any live session with a server (e.g. after it was deleted)
+    ///
+    /// The server's circuit breaker is reset along with it.
+    pub async fn disconnect(&self, server_id: Uuid) {
+        self.breakers.write().await.remove(&server_id);
+        let slot = self.sessions.write().await.remove(&server_id);
+        if let Some(slot) = slot {
+            if let Some(session) = slot.lock().await.take() {
+                session.close().await;
+            }
         }
+    }
 
-        let result = response
-            .result
-            .ok_or_else(|| AppError::McpProtocol("Empty response from MCP server".to_string()))?;
+    /// Close every live session, ending backend connections
+    pub async fn shutdown(&self) {
+        let slots: Vec<SessionSlot> = self
+            .sessions
+            .write()
+            .await
+            .drain()
+            .map(|(_, s)| s)
+            .collect();
+        for slot in slots {
+            if let Some(session) = slot.lock().await.take() {
+                session.close().await;
+            }
+        }
+    }
+
+    /// Forward a JSON-RPC request to a backend MCP server
+    pub async fn forward_request(
+        &self,
+        server: &McpServer,
+        request: JsonRpcRequest,
+    ) -> Result<JsonRpcResponse, AppError> {
+        self.forward_cancellable(server, request, CancelSignal::never())
+            .await
+    }
 
-        let tools = result
-            .get("tools")
-            .and_then(|t| t.as_array())
-            .cloned()
-            .unwrap_or_default();
+    /// Forward a request that the caller may abandon
+    ///
+    /// When `cancel` fires the backend is told with `notifications/cancelled`
+    /// and [`AppError::Cancelled`] is returned without waiting further.
+    /// Idempotent requests are retried as the server's policy allows.
+    pub async fn forward_cancellable(
+        &self,
+        server: &McpServer,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        let breaker = self.breaker(server).await;
+        let retries = ResiliencePolicy::from(server).retries_for(&request.method);
+        self.resilient(server, &breaker, retries, cancel, |signal| {
+            let request = request.clone();
+            async move {
+                let (session, _) = self.open(server).await;
+                session?.request_cancellable(request, signal).await
+            }
+        })
+        .await
+    }
 
-        Ok(tools)
+    /// List tools from a backend server
+    pub async fn list_tools(&self, server: &McpServer) -> Result<Vec<serde_json::Value>, AppError> {
+        self.list_pages(server, "tools/list", "tools")
+            .await
+            .map_err(list_error)
     }
 
     /// Call a tool on a backend server
+    ///
+    /// `meta` is sent as the request's `_meta` (e.g. to carry a progress
+    /// token). A JSON-RPC error from the backend is returned as
+    /// [`AppError::McpServer`] so callers can tell it apart from transport
+    /// failures.
     pub async fn call_tool(
         &self,
         server: &McpServer,
         tool_name: &str,
         arguments: serde_json::Value,
-    ) -> Result<serde_json::Value, AppError> {
-        let params = serde_json::json!({
-            "name": tool_name,
-            "arguments": arguments
-        });
+        meta: Option<serde_json::Value>,
+        cancel: CancelSignal,
+    ) -> Result<ToolCallResult, AppError> {
+        let params = ToolCallParams {
+            name: tool_name.to_string(),
+            arguments,
+            meta,
+        };
+        let params = serde_json::to_value(params).map_err(|e| AppError::Internal(e.to_string()))?;
 
         let request = JsonRpcRequest::new(1i64, "tools/call", Some(params));
-        let response = self.forward_request(server, request).await?;
+        let response = self.forward_cancellable(server, request, cancel).await?;
 
         if let Some(error) = response.error {
-            return Err(AppError::McpProtocol(format!(
-                "Tool execution failed: {} (code: {})",
-                error.message, error.code
-            )));
+            return Err(AppError::McpServer(error));
         }
 
-        response
+        let result = response
             .result
-            .ok_or_else(|| AppError::McpProtocol("Empty response from tool call".to_string()))
+            .ok_or_else(|| AppError::Upstream("Empty response from tool call".to_string()))?;
+        serde_json::from_value(result)
+            .map_err(|e| AppError::Upstream(format!("Invalid tool result: {}", e)))
     }
 
     /// List resources from a backend server
@@ -139,27 +531,82 @@ impl McpProxy {
         &self,
         server: &McpServer,
     ) -> Result<Vec<serde_json::Value>, AppError> {
-        let request = JsonRpcRequest::new(1i64, "resources/list", None);
-        let response = self.forward_request(server, request).await?;
+        self.list_pages(server, "resources/list", "resources")
+            .await
+            .map_err(list_error)
+    }
 
-        if let Some(error) = response.error {
-            return Err(AppError::McpProtocol(format!(
-                "MCP error: {} (code: {})",
-                error.message, error.code
-            )));
+    /// List resource templates from a backend server
+    ///
+    /// Templates are optional for servers with resources, so a server that
+    /// does not know the method has none.
+    pub async fn list_resource_templates(
+        &self,
+        server: &McpServer,
+    ) -> Result<Vec<serde_json::Value>, AppError> {
+        match self
+            .list_pages(server, "resources/templates/list", "resourceTemplates")
+            .await
+        {
+            Err(AppError::McpServer(error)) if error.code == error_codes::METHOD_NOT_FOUND => {
+                Ok(Vec::new())
+            }
+            result => result.map_err(list_error),
         }
+    }
 
-        let result = response
-            .result
-            .ok_or_else(|| AppError::McpProtocol("Empty response from MCP server".to_string()))?;
+    /// Subscribe to updates of a resource on a backend server
+    pub async fn subscribe_resource(&self, server: &McpServer, uri: &str) -> Result<(), AppError> {
+        self.resource_subscription(server, "resources/subscribe", uri)
+            .await
+    }
 
-        let resources = result
-            .get("resources")
-            .and_then(|r| r.as_array())
-            .cloned()
-            .unwrap_or_default();
+    /// Stop receiving updates of a resource on a backend server
+    pub async fn unsubscribe_resource(
+        &self,
+        server: &McpServer,
+        uri: &str,
+    ) -> Result<(), AppError> {
+        self.resource_subscription(server, "resources/unsubscribe", uri)
+            .await
+    }
+
+    async fn resource_subscription(
+        &self,
+        server: &McpServer,
+        method: &str,
+        uri: &str,
+    ) -> Result<(), AppError> {
+        let session = self.session(server).await?;
+        if !session.supports_resource_subscriptions() {
+            return Err(AppError::McpServer(JsonRpcError {
+                code: error_codes::METHOD_NOT_FOUND,
+                message: format!("{} does not support resource subscriptions", server.name),
+                data: None,
+            }));
+        }
+
+        // Noted before asking, so a session opened meanwhile subscribes too
+        let subscribed = self.subscribed(server.id).await;
+        let subscribing = method == "resources/subscribe";
+        if subscribing {
+            subscribed.insert(uri);
+        } else {
+            subscribed.remove(uri);
+        }
 
-        Ok(resources)
+        let request = JsonRpcRequest::new(1i64, method, Some(serde_json::json!({ "uri": uri })));
+        let result = match self.forward_request(server, request).await {
+            Ok(response) => match response.error {
+                Some(error) => Err(AppError::McpServer(error)),
+                None => Ok(()),
+            },
+            Err(e) => Err(e),
+        };
+        if subscribing && result.is_err() {
+            subscribed.remove(uri);
+        }
+        result
     }
 
     /// List prompts from a backend server
@@ -167,27 +614,124 @@ impl McpProxy {
         &self,
         server: &McpServer,
     ) -> Result<Vec<serde_json::Value>, AppError> {
-        let request = JsonRpcRequest::new(1i64, "prompts/list", None);
-        let response = self.forward_request(server, request).await?;
+        self.list_pages(server, "prompts/list", "prompts")
+            .await
+            .map_err(list_error)
+    }
 
-        if let Some(error) = response.error {
-            return Err(AppError::McpProtocol(format!(
-                "MCP error: {} (code: {})",
-                error.message, error.code
-            )));
+    /// Collect every page of a list method, following `nextCursor`
+    ///
+    /// Errors the backend returns come back as [`AppError::McpServer`]. A
+    /// backend that repeats a cursor or exceeds [`MAX_LIST_PAGES`] is
+    /// treated as broken rather than followed forever.
+    async fn list_pages(
+        &self,
+        server: &McpServer,
+        method: &str,
+        key: &str,
+    ) -> Result<Vec<serde_json::Value>, AppError> {
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_LIST_PAGES {
+            let params = cursor.map(|c| serde_json::json!({ "cursor": c }));
+            let response = self
+                .forward_request(server, JsonRpcRequest::new(1i64, method, params))
+                .await?;
+
+            if let Some(error) = response.error {
+                return Err(AppError::McpServer(error));
+            }
+            let result = response
+                .result
+                .ok_or_else(|| AppError::Upstream("Empty response from MCP server".to_string()))?;
+
+            if let Some(page) = result.get(key).and_then(|v| v.as_array()) {
+                items.extend(page.iter().cloned());
+            }
+
+            cursor = match result.get("nextCursor").and_then(|c| c.as_str()) {
+                None => return Ok(items),
+                Some(next) if !seen.insert(next.to_string()) => {
+                    return Err(AppError::Upstream(format!(
+                        "{} repeated cursor {} in {}",
+                        server.name, next, method
+                    )));
+                }
+                Some(next) => Some(next.to_string()),
+            };
         }
 
-        let result = response
-            .result
-            .ok_or_else(|| AppError::McpProtocol("Empty response from MCP server".to_string()))?;
+        Err(AppError::Upstream(format!(
+            "{} returned more than {} pages of {}",
+            server.name, MAX_LIST_PAGES, method
+        )))
+    }
+}
+
+/// HTTP client for a server, bounded by its connect and request timeouts
+fn backend_client(
+    policy: &ResiliencePolicy,
+    request_timeout: Option<Duration>,
+) -> Result<Client, AppError> {
+    let mut builder = Client::builder().connect_timeout(policy.connect_timeout);
+    if let Some(timeout) = request_timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::Internal(format!("Failed to create HTTP client: {}", e)))
+}
 
-        let prompts = result
-            .get("prompts")
-            .and_then(|p| p.as_array())
-            .cloned()
-            .unwrap_or_default();
+/// Count the outcome of a call to a server towards its circuit breaker
+///
+/// Errors that are not the backend's fault (e.g. cancellation) count for
+/// nothing.
+fn record<T>(server: &McpServer, breaker: &CircuitBreaker, result: &Result<T, AppError>) {
+    match result {
+        Ok(_) | Err(AppError::McpServer(_)) => {
+            if breaker.record_success() {
+                tracing::info!(server_name = %server.name, "Circuit closed for MCP server");
+            }
+        }
+        Err(e) if is_backend_failure(e) => {
+            if breaker.record_failure() {
+                tracing::warn!(
+                    server_name = %server.name,
+                    "Circuit opened for MCP server after {} consecutive failures: {}",
+                    breaker.status().consecutive_failures,
+                    e
+                );
+            }
+        }
+        Err(_) => {}
+    }
+}
+
+/// The error returned instead of calling a server whose circuit is open
+fn circuit_open(server: &McpServer, breaker: &CircuitBreaker) -> AppError {
+    let status = breaker.status();
+    let message = format!(
+        "'{}' failed {} times in a row",
+        server.name, status.consecutive_failures
+    );
+    match status.retry_at {
+        Some(at) => {
+            AppError::CircuitOpen(format!("{}; retrying after {}", message, at.to_rfc3339()))
+        }
+        None => AppError::CircuitOpen(message),
+    }
+}
 
-        Ok(prompts)
+/// Turn a backend error from a list method into the protocol error list callers expect
+fn list_error(error: AppError) -> AppError {
+    match error {
+        AppError::McpServer(error) => AppError::Upstream(format!(
+            "MCP error: {} (code: {})",
+            error.message, error.code
+        )),
+        other => other,
     }
 }
 
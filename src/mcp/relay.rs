@@ -0,0 +1,975 @@
+//! Relay of backend messages to clients connected to the gateway
+//!
+//! Every `GET /mcp` stream is registered as a client with the
+//! [`StreamManager`], tied to the client's gateway session. Notifications
+//! from any backend are namespaced and sent to the streams of gateway
+//! sessions as [`StreamEvent::McpMessage`] events, never to other clients of
+//! the stream manager; requests from a backend (e.g.
+//! `sampling/createMessage`) are sent under a gateway-issued id to a client
+//! whose session declared the matching capability, and the client's answer,
+//! POSTed back to `/mcp` in the same session, is handed to the backend.
+//! Only sessions with calls in flight on the backend are asked, most recent
+//! first, since such a call is why the backend asks; without one the
+//! request is refused, so a backend never sees another tenant's roots or
+//! sends its prompts to a session that never called it. Elicitations
+//! ask a user for input, so they only go to the session whose request
+//! caused them: the one named by a gateway progress token in the request's
+//! `_meta`, or else the only session with calls in flight on the backend.
+//! Without such a caller they are refused. Elicitations are also checked
+//! against the schema subset clients must support. `roots/list`
+//! only gets here for servers without configured roots.
+//!
+//! Progress is routed rather than broadcast: a client's progress token is
+//! swapped for a gateway-issued one before the request reaches the backend,
+//! and `notifications/progress` under that token goes back to the streams of
+//! the client's session with the original token restored. Likewise
+//! `notifications/resources/updated` only reaches the sessions subscribed to
+//! the resource.
+
+use crate::mcp::catalog::{CatalogKind, SharedCatalog};
+use crate::mcp::elicitation;
+use crate::mcp::naming::NamingScheme;
+use crate::mcp::protocol::{
+    ClientCapabilities, JsonRpcNotification, JsonRpcResponse, ProgressToken, RequestId,
+};
+use crate::mcp::proxy::McpProxy;
+use crate::mcp::subscriptions::SharedResourceSubscriptions;
+use crate::mcp::transport::{BackendNotification, BackendRequest};
+use crate::streaming::{EventFilters, SharedStreamManager, StreamEvent};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use uuid::Uuid;
+
+/// Prefix of the ids given to relayed server requests
+const REQUEST_ID_PREFIX: &str = "metamcp-";
+
+/// Prefix of the progress tokens handed to backends
+const PROGRESS_TOKEN_PREFIX: &str = "metamcp-progress-";
+
+/// A client stream and the session it belongs to
+struct Client {
+    id: String,
+    session_id: Uuid,
+    /// What the session declared it can do in `initialize`
+    capabilities: ClientCapabilities,
+}
+
+impl Client {
+    /// Whether the client declared the capability a server request needs
+    fn handles(&self, method: &str) -> bool {
+        match method {
+            "sampling/createMessage" => self.capabilities.sampling.is_some(),
+            "elicitation/create" => self.capabilities.elicitation.is_some(),
+            "roots/list" => self.capabilities.roots.is_some(),
+            _ => true,
+        }
+    }
+}
+
+/// A relayed server request waiting for a client's answer
+struct PendingReply {
+    client_id: String,
+    session_id: Uuid,
+    method: String,
+    reply: oneshot::Sender<JsonRpcResponse>,
+}
+
+/// A client session's request being served by a backend
+struct CallOrigin {
+    id: Uuid,
+    server_id: Uuid,
+    session_id: Uuid,
+}
+
+/// Where progress reported under a gateway token goes
+struct ProgressRoute {
+    session_id: Uuid,
+    token: ProgressToken,
+}
+
+/// Multiplexes backend notifications and requests onto client streams
+pub struct ClientRelay {
+    catalog: SharedCatalog,
+    streams: SharedStreamManager,
+    subscriptions: SharedResourceSubscriptions,
+    /// Connected clients, oldest first
+    clients: RwLock<Vec<Client>>,
+    pending: Mutex<HashMap<RequestId, PendingReply>>,
+    /// Client progress tokens, by the token the backend was given
+    progress: Mutex<HashMap<String, ProgressRoute>>,
+    /// Requests in flight on backends, oldest first
+    calls: Mutex<Vec<CallOrigin>>,
+}
+
+impl ClientRelay {
+    /// Create the relay and start forwarding messages from `proxy`'s backends
+    pub async fn new(
+        proxy: &McpProxy,
+        catalog: SharedCatalog,
+        streams: SharedStreamManager,
+        subscriptions: SharedResourceSubscriptions,
+    ) -> Arc<Self> {
+        let relay = Arc::new(Self {
+            catalog,
+            streams,
+            subscriptions,
+            clients: RwLock::new(Vec::new()),
+            pending: Mutex::new(HashMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            calls: Mutex::new(Vec::new()),
+        });
+
+        let mut notifications = proxy.subscribe_notifications();
+        let weak = Arc::downgrade(&relay);
+        tokio::spawn(async move {
+            loop {
+                let received = notifications.recv().await;
+                let Some(relay) = weak.upgrade() else {
+                    break;
+                };
+                match received {
+                    Ok(notification) => relay.forward_notification(notification).await,
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Client relay missed {} backend notifications", missed)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let mut requests = proxy.handle_server_requests().await;
+        let weak = Arc::downgrade(&relay);
+        tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let Some(relay) = weak.upgrade() else {
+                    break;
+                };
+                relay.forward_request(request).await;
+            }
+        });
+
+        relay
+    }
+
+    /// Register a client stream for a gateway session with `capabilities`
+    pub async fn connect(
+        self: &Arc<Self>,
+        session_id: Uuid,
+        capabilities: ClientCapabilities,
+    ) -> ClientConnection {
+        let filters = EventFilters {
+            event_types: Some(vec!["mcp_message".to_string()]),
+            ..Default::default()
+        };
+        let (id, events) = self.streams.register_client(filters).await;
+        self.clients.write().await.push(Client {
+            id: id.clone(),
+            session_id,
+            capabilities,
+        });
+
+        ClientConnection {
+            id,
+            events,
+            relay: self.clone(),
+        }
+    }
+
+    /// Forget a client stream, failing the requests it was asked to answer
+    pub async fn disconnect(&self, client_id: &str) {
+        self.clients.write().await.retain(|c| c.id != client_id);
+        self.pending
+            .lock()
+            .await
+            .retain(|_, pending| pending.client_id != client_id);
+        self.streams.unregister_client(client_id).await;
+    }
+
+    /// Close every stream of a session that ended
+    pub async fn end_session(&self, session_id: Uuid) {
+        for client_id in self.session_clients(session_id).await {
+            self.disconnect(&client_id).await;
+        }
+    }
+
+    /// Route progress for a client's token back to its session
+    ///
+    /// The returned registration holds the token to send to the backend in
+    /// place of the client's; progress stops being routed once it is dropped.
+    pub async fn track_progress(
+        self: &Arc<Self>,
+        session_id: Uuid,
+        token: ProgressToken,
+    ) -> ProgressRegistration {
+        let backend_token = format!("{}{}", PROGRESS_TOKEN_PREFIX, Uuid::new_v4());
+        self.progress
+            .lock()
+            .await
+            .insert(backend_token.clone(), ProgressRoute { session_id, token });
+
+        ProgressRegistration {
+            token: backend_token,
+            relay: self.clone(),
+        }
+    }
+
+    /// Remember that a session's request is being served by a backend
+    ///
+    /// Requests the backend sends while the returned registration lives
+    /// are relayed to that session.
+    pub async fn track_call(
+        self: &Arc<Self>,
+        server_id: Uuid,
+        session_id: Uuid,
+    ) -> CallRegistration {
+        let id = Uuid::new_v4();
+        self.calls.lock().await.push(CallOrigin {
+            id,
+            server_id,
+            session_id,
+        });
+
+        CallRegistration {
+            id,
+            relay: self.clone(),
+        }
+    }
+
+    /// Hand a client's answer to the backend that asked
+    ///
+    /// Returns `false` if the id does not belong to a request relayed to
+    /// this session. An answer to an elicitation that is not a valid
+    /// accept, decline or cancel reaches the backend as an error.
+    pub async fn complete(&self, session_id: Uuid, mut response: JsonRpcResponse) -> bool {
+        let mut pending = self.pending.lock().await;
+        match pending.get(&response.id) {
+            Some(p) if p.session_id == session_id => {}
+            _ => return false,
+        }
+        let Some(p) = pending.remove(&response.id) else {
+            return false;
+        };
+
+        if let (Some(result), "elicitation/create") = (&response.result, p.method.as_str()) {
+            if let Err(e) = elicitation::validate_result(result) {
+                tracing::debug!("Client sent an invalid elicitation result: {}", e);
+                response = JsonRpcResponse::error(
+                    response.id,
+                    -32603,
+                    &format!("Client sent an invalid elicitation result: {}", e),
+                    None,
+                );
+            }
+        }
+        p.reply.send(response).is_ok()
+    }
+
+    async fn forward_notification(&self, backend: BackendNotification) {
+        if backend.notification.method == "notifications/progress" {
+            self.forward_progress(backend).await;
+            return;
+        }
+
+        // Drop stale entries first so a client re-listing right away sees the change
+        if let Some(kind) = CatalogKind::from_list_changed(&backend.notification.method) {
+            self.catalog.invalidate(backend.server_id, kind).await;
+        }
+
+        // Resource updates only go to the sessions subscribed to the resource;
+        // every session sees every active server, so the rest goes to all of them
+        let subscribers = match backend.notification.method.as_str() {
+            "notifications/resources/updated" => {
+                let uri = backend
+                    .notification
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("uri"))
+                    .and_then(|u| u.as_str())
+                    .unwrap_or_default();
+                Some(self.subscriptions.subscribers(backend.server_id, uri).await)
+            }
+            _ => None,
+        };
+
+        let notification = namespace_notification(
+            self.catalog.naming(),
+            &backend.namespace,
+            backend.notification,
+        );
+        let event = StreamEvent::McpMessage {
+            server_id: backend.server_id.to_string(),
+            message: json!(notification),
+        };
+        let recipients: Vec<String> = self
+            .clients
+            .read()
+            .await
+            .iter()
+            .filter(|c| {
+                subscribers
+                    .as_ref()
+                    .is_none_or(|sessions| sessions.contains(&c.session_id))
+            })
+            .map(|c| c.id.clone())
+            .collect();
+        for client_id in recipients {
+            self.streams.send_to_client(&client_id, event.clone()).await;
+        }
+    }
+
+    async fn forward_progress(&self, backend: BackendNotification) {
+        let mut notification = backend.notification;
+        let Some(params) = notification.params.as_mut().and_then(|p| p.as_object_mut()) else {
+            return;
+        };
+        let route = match params.get("progressToken").and_then(|t| t.as_str()) {
+            Some(token) => self
+                .progress
+                .lock()
+                .await
+                .get(token)
+                .map(|r| (r.session_id, r.token.clone())),
+            None => None,
+        };
+        let Some((session_id, token)) = route else {
+            tracing::debug!(
+                server_name = %backend.server_name,
+                "Dropping progress for a token no client is waiting on"
+            );
+            return;
+        };
+
+        params.insert("progressToken".to_string(), json!(token));
+        let event = StreamEvent::McpMessage {
+            server_id: backend.server_id.to_string(),
+            message: json!(notification),
+        };
+        self.send_to_session(session_id, event).await;
+    }
+
+    async fn send_to_session(&self, session_id: Uuid, event: StreamEvent) {
+        for client_id in self.session_clients(session_id).await {
+            self.streams.send_to_client(&client_id, event.clone()).await;
+        }
+    }
+
+    async fn session_clients(&self, session_id: Uuid) -> Vec<String> {
+        self.clients
+            .read()
+            .await
+            .iter()
+            .filter(|c| c.session_id == session_id)
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    async fn forward_request(&self, backend: BackendRequest) {
+        if backend.request.method == "elicitation/create" {
+            let schema = backend
+                .request
+                .params
+                .as_ref()
+                .and_then(|p| p.get("requestedSchema"))
+                .cloned()
+                .unwrap_or_default();
+            if let Err(e) = elicitation::validate_requested_schema(&schema) {
+                let id = backend.request.id.clone().unwrap_or(0i64.into());
+                let _ =
+                    backend
+                        .reply
+                        .send(JsonRpcResponse::error(id, -32602, &e.to_string(), None));
+                return;
+            }
+        }
+
+        let Some((client_id, session_id)) = self.target(&backend).await else {
+            let id = backend.request.id.clone().unwrap_or(0i64.into());
+            let _ = backend.reply.send(JsonRpcResponse::error(
+                id,
+                -32601,
+                &format!("No connected client can handle {}", backend.request.method),
+                None,
+            ));
+            return;
+        };
+
+        let id = RequestId::String(format!("{}{}", REQUEST_ID_PREFIX, Uuid::new_v4()));
+        let mut request = backend.request;
+        request.id = Some(id.clone());
+
+        {
+            let mut pending = self.pending.lock().await;
+            // Backends stop waiting after a while; forget what they gave up on
+            pending.retain(|_, p| !p.reply.is_closed());
+            pending.insert(
+                id,
+                PendingReply {
+                    client_id: client_id.clone(),
+                    session_id,
+                    method: request.method.clone(),
+                    reply: backend.reply,
+                },
+            );
+        }
+
+        tracing::debug!(
+            server_name = %backend.server_name,
+            method = %request.method,
+            client_id = %client_id,
+            "Relaying server request to client"
+        );
+        self.streams
+            .send_to_client(
+                &client_id,
+                StreamEvent::McpMessage {
+                    server_id: backend.server_id.to_string(),
+                    message: json!(request),
+                },
+            )
+            .await;
+    }
+
+    /// The client stream a server request is relayed to
+    ///
+    /// Only sessions with calls in flight on the backend are asked, most
+    /// recent first. Elicitations only go to their caller.
+    async fn target(&self, backend: &BackendRequest) -> Option<(String, Uuid)> {
+        let method = backend.request.method.as_str();
+        let origins: Vec<Uuid> = if method == "elicitation/create" {
+            vec![self.caller(backend).await?]
+        } else {
+            self.calls
+                .lock()
+                .await
+                .iter()
+                .rev()
+                .filter(|c| c.server_id == backend.server_id)
+                .map(|c| c.session_id)
+                .collect()
+        };
+
+        let clients = self.clients.read().await;
+        let client = origins.iter().find_map(|session_id| {
+            clients
+                .iter()
+                .rev()
+                .find(|c| c.session_id == *session_id && c.handles(method))
+        })?;
+        Some((client.id.clone(), client.session_id))
+    }
+
+    /// The session whose request made the backend ask, if it can be told
+    ///
+    /// A progress token the gateway handed out names it directly; otherwise
+    /// it is the session with calls in flight on the backend, as long as no
+    /// other session has any.
+    async fn caller(&self, backend: &BackendRequest) -> Option<Uuid> {
+        let token = backend
+            .request
+            .params
+            .as_ref()
+            .and_then(|p| p.pointer("/_meta/progressToken"))
+            .and_then(|t| t.as_str());
+        if let Some(token) = token {
+            if let Some(route) = self.progress.lock().await.get(token) {
+                return Some(route.session_id);
+            }
+        }
+
+        let calls = self.calls.lock().await;
+        let mut sessions = calls
+            .iter()
+            .filter(|c| c.server_id == backend.server_id)
+            .map(|c| c.session_id);
+        let first = sessions.next()?;
+        sessions.all(|s| s == first).then_some(first)
+    }
+}
+
+/// A client's registration with the relay; unregisters when dropped
+pub struct ClientConnection {
+    id: String,
+    events: mpsc::Receiver<StreamEvent>,
+    relay: Arc<ClientRelay>,
+}
+
+impl ClientConnection {
+    /// The stream manager's id for this client
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Next JSON-RPC message for the client, or `None` once the relay is gone
+    pub async fn recv(&mut self) -> Option<Value> {
+        loop {
+            if let StreamEvent::McpMessage { message, .. } = self.events.recv().await? {
+                return Some(message);
+            }
+        }
+    }
+}
+
+impl Drop for ClientConnection {
+    fn drop(&mut self) {
+        let relay = self.relay.clone();
+        let id = std::mem::take(&mut self.id);
+        tokio::spawn(async move { relay.disconnect(&id).await });
+    }
+}
+
+/// A client progress token routed through the relay; forgotten when dropped
+pub struct ProgressRegistration {
+    token: String,
+    relay: Arc<ClientRelay>,
+}
+
+impl ProgressRegistration {
+    /// The progress token to send to the backend
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl Drop for ProgressRegistration {
+    fn drop(&mut self) {
+        let relay = self.relay.clone();
+        let token = std::mem::take(&mut self.token);
+        tokio::spawn(async move { relay.progress.lock().await.remove(&token) });
+    }
+}
+
+/// A session's request tracked by the relay; forgotten when dropped
+pub struct CallRegistration {
+    id: Uuid,
+    relay: Arc<ClientRelay>,
+}
+
+impl Drop for CallRegistration {
+    fn drop(&mut self) {
+        let relay = self.relay.clone();
+        let id = self.id;
+        tokio::spawn(async move { relay.calls.lock().await.retain(|c| c.id != id) });
+    }
+}
+
+/// Rewrite a backend notification so clients can tell which server it is about
+///
+/// Log messages get the namespace as (or in front of) their logger and
+/// resource URIs get the same `namespace:` prefix the catalog exposes.
+fn namespace_notification(
+    scheme: &NamingScheme,
+    namespace: &str,
+    mut notification: JsonRpcNotification,
+) -> JsonRpcNotification {
+    let Some(params) = notification.params.as_mut().and_then(|p| p.as_object_mut()) else {
+        return notification;
+    };
+
+    match notification.method.as_str() {
+        "notifications/message" => {
+            let logger = match params.get("logger").and_then(|l| l.as_str()) {
+                Some(logger) => scheme.qualify(namespace, logger),
+                None => namespace.to_string(),
+            };
+            params.insert("logger".to_string(), json!(logger));
+        }
+        "notifications/resources/updated" => {
+            if let Some(uri) = params.get("uri").and_then(|u| u.as_str()) {
+                let uri = format!("{}:{}", namespace, uri);
+                params.insert("uri".to_string(), json!(uri));
+            }
+        }
+        _ => {}
+    }
+
+    notification
+}
+
+/// Shared relay instance
+pub type SharedClientRelay = Arc<ClientRelay>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::mcp::catalog::{Catalog, DEFAULT_CATALOG_TTL};
+    use crate::mcp::client_session::ClientSessions;
+    use crate::mcp::pagination::DEFAULT_PAGE_SIZE;
+    use crate::mcp::subscriptions::ResourceSubscriptions;
+    use crate::streaming::StreamManager;
+    use sqlx::postgres::PgPoolOptions;
+    use std::time::Duration;
+
+    fn sampling() -> ClientCapabilities {
+        ClientCapabilities {
+            sampling: Some(crate::mcp::protocol::SamplingCapability {}),
+            ..Default::default()
+        }
+    }
+
+    fn server_request(
+        server_id: Uuid,
+        method: &str,
+        reply: oneshot::Sender<JsonRpcResponse>,
+    ) -> BackendRequest {
+        BackendRequest {
+            server_id,
+            server_name: "llm".to_string(),
+            namespace: "llm".to_string(),
+            request: crate::mcp::protocol::JsonRpcRequest::new(5i64, method, Some(json!({}))),
+            reply,
+        }
+    }
+
+    fn sampling_request(
+        server_id: Uuid,
+        reply: oneshot::Sender<JsonRpcResponse>,
+    ) -> BackendRequest {
+        server_request(server_id, "sampling/createMessage", reply)
+    }
+
+    async fn relay(proxy: &McpProxy) -> Arc<ClientRelay> {
+        // Never connects: the relay only touches the catalog's cache
+        let pool = PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let backends = Arc::new(McpProxy::new());
+        let catalog = Catalog::new(
+            Database::from_pool(pool),
+            backends.clone(),
+            DEFAULT_CATALOG_TTL,
+            NamingScheme::default(),
+            DEFAULT_PAGE_SIZE,
+        );
+        let subscriptions = Arc::new(ResourceSubscriptions::new(backends));
+        ClientRelay::new(
+            proxy,
+            catalog,
+            Arc::new(StreamManager::new()),
+            subscriptions,
+        )
+        .await
+    }
+
+    #[test]
+    fn test_namespace_notification() {
+        let scheme = NamingScheme::default();
+
+        let log = JsonRpcNotification::new(
+            "notifications/message",
+            Some(json!({"level": "info", "logger": "db", "data": "hi"})),
+        );
+        let log = namespace_notification(&scheme, "github", log);
+        assert_eq!(log.params.unwrap()["logger"], "github_db");
+
+        let updated = JsonRpcNotification::new(
+            "notifications/resources/updated",
+            Some(json!({"uri": "file:///a.txt"})),
+        );
+        let updated = namespace_notification(&scheme, "files", updated);
+        assert_eq!(updated.params.unwrap()["uri"], "files:file:///a.txt");
+    }
+
+    #[tokio::test]
+    async fn test_server_request_round_trip() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let session_id = Uuid::new_v4();
+        let mut client = relay.connect(session_id, sampling()).await;
+        let server_id = Uuid::new_v4();
+        let _call = relay.track_call(server_id, session_id).await;
+
+        let (tx, rx) = oneshot::channel();
+        relay.forward_request(sampling_request(server_id, tx)).await;
+
+        let message = tokio::time::timeout(Duration::from_secs(1), client.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["method"], "sampling/createMessage");
+        let id: RequestId = serde_json::from_value(message["id"].clone()).unwrap();
+        assert_ne!(id, RequestId::Number(5));
+
+        let answer = JsonRpcResponse::success(id, json!({"role": "assistant"}));
+        assert!(!relay.complete(Uuid::new_v4(), answer.clone()).await);
+        assert!(relay.complete(session_id, answer).await);
+        assert_eq!(rx.await.unwrap().result.unwrap()["role"], "assistant");
+    }
+
+    #[tokio::test]
+    async fn test_sampling_goes_to_a_client_that_supports_it() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut capable = relay.connect(first, sampling()).await;
+        let mut newest = relay.connect(second, Default::default()).await;
+        let server_id = Uuid::new_v4();
+        let _first_call = relay.track_call(server_id, first).await;
+        let _second_call = relay.track_call(server_id, second).await;
+
+        let (tx, _rx) = oneshot::channel();
+        relay.forward_request(sampling_request(server_id, tx)).await;
+
+        let message = tokio::time::timeout(Duration::from_secs(1), capable.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["method"], "sampling/createMessage");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), newest.recv())
+                .await
+                .is_err()
+        );
+
+        drop(capable);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let (tx, rx) = oneshot::channel();
+        relay.forward_request(sampling_request(server_id, tx)).await;
+        assert_eq!(rx.await.unwrap().error.unwrap().code, -32601);
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_stay_with_sessions_calling_the_server() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let sessions = ClientSessions::new(Duration::from_secs(60));
+        let capabilities = ClientCapabilities {
+            roots: Some(crate::mcp::protocol::RootsCapability { list_changed: true }),
+            ..sampling()
+        };
+        let (key_a, key_b) = (Uuid::new_v4(), Uuid::new_v4());
+        let a = sessions
+            .create(key_a, "2025-06-18", capabilities.clone(), None)
+            .await
+            .unwrap();
+        let b = sessions
+            .create(key_b, "2025-06-18", capabilities, None)
+            .await
+            .unwrap();
+        let mut tenant_a = relay.connect(a.id, a.capabilities.clone()).await;
+        let mut tenant_b = relay.connect(b.id, b.capabilities.clone()).await;
+
+        // Only the session calling a server hears from it, however recent the other
+        let (called, idle) = (Uuid::new_v4(), Uuid::new_v4());
+        let _call = relay.track_call(called, a.id).await;
+        let (tx, _rx) = oneshot::channel();
+        relay.forward_request(sampling_request(called, tx)).await;
+        let message = tokio::time::timeout(Duration::from_secs(1), tenant_a.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["method"], "sampling/createMessage");
+
+        // A server nobody is calling gets neither prompts answered nor roots
+        for method in ["sampling/createMessage", "roots/list"] {
+            let (tx, rx) = oneshot::channel();
+            relay
+                .forward_request(server_request(idle, method, tx))
+                .await;
+            assert_eq!(rx.await.unwrap().error.unwrap().code, -32601);
+        }
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), tenant_b.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_elicitation_goes_to_the_calling_session() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let elicitation = ClientCapabilities {
+            elicitation: Some(crate::mcp::protocol::ElicitationCapability {}),
+            ..Default::default()
+        };
+        let caller = Uuid::new_v4();
+        let mut calling = relay.connect(caller, elicitation.clone()).await;
+        let mut newest = relay.connect(Uuid::new_v4(), elicitation).await;
+
+        let server_id = Uuid::new_v4();
+        let _call = relay.track_call(server_id, caller).await;
+        let elicit = |schema: Value, reply| BackendRequest {
+            server_id,
+            server_name: "deploy".to_string(),
+            namespace: "deploy".to_string(),
+            request: crate::mcp::protocol::JsonRpcRequest::new(
+                9i64,
+                "elicitation/create",
+                Some(json!({"message": "Proceed?", "requestedSchema": schema})),
+            ),
+            reply,
+        };
+
+        let (tx, rx) = oneshot::channel();
+        let nested = json!({"type": "object", "properties": {"a": {"type": "object"}}});
+        relay.forward_request(elicit(nested, tx)).await;
+        assert_eq!(rx.await.unwrap().error.unwrap().code, -32602);
+
+        let (tx, rx) = oneshot::channel();
+        let flat = json!({"type": "object", "properties": {"ok": {"type": "boolean"}}});
+        relay.forward_request(elicit(flat, tx)).await;
+        let message = tokio::time::timeout(Duration::from_secs(1), calling.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["method"], "elicitation/create");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), newest.recv())
+                .await
+                .is_err()
+        );
+
+        let id: RequestId = serde_json::from_value(message["id"].clone()).unwrap();
+        let answer = JsonRpcResponse::success(id, json!({"action": "maybe"}));
+        assert!(relay.complete(caller, answer).await);
+        assert!(rx.await.unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_elicitation_without_a_single_caller_is_refused() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let elicitation = ClientCapabilities {
+            elicitation: Some(crate::mcp::protocol::ElicitationCapability {}),
+            ..Default::default()
+        };
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut bystander = relay.connect(first, elicitation.clone()).await;
+        let mut caller = relay.connect(second, elicitation).await;
+
+        let server_id = Uuid::new_v4();
+        let elicit = |meta: Value, reply| BackendRequest {
+            server_id,
+            server_name: "deploy".to_string(),
+            namespace: "deploy".to_string(),
+            request: crate::mcp::protocol::JsonRpcRequest::new(
+                9i64,
+                "elicitation/create",
+                Some(json!({
+                    "message": "Proceed?",
+                    "requestedSchema": {"type": "object", "properties": {}},
+                    "_meta": meta
+                })),
+            ),
+            reply,
+        };
+
+        // Nobody is calling the server, so nobody is asked
+        let (tx, rx) = oneshot::channel();
+        relay.forward_request(elicit(json!({}), tx)).await;
+        assert_eq!(rx.await.unwrap().error.unwrap().code, -32601);
+
+        // Two sessions are, and either could be the cause
+        let _first_call = relay.track_call(server_id, first).await;
+        let _second_call = relay.track_call(server_id, second).await;
+        let (tx, rx) = oneshot::channel();
+        relay.forward_request(elicit(json!({}), tx)).await;
+        assert_eq!(rx.await.unwrap().error.unwrap().code, -32601);
+
+        // A progress token tells them apart
+        let registration = relay.track_progress(second, ProgressToken::Number(1)).await;
+        let (tx, _rx) = oneshot::channel();
+        let meta = json!({"progressToken": registration.token()});
+        relay.forward_request(elicit(meta, tx)).await;
+        let message = tokio::time::timeout(Duration::from_secs(1), caller.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["method"], "elicitation/create");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), bystander.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_routed_to_its_session() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let session_id = Uuid::new_v4();
+        let mut client = relay.connect(session_id, Default::default()).await;
+        let mut other = relay.connect(Uuid::new_v4(), Default::default()).await;
+
+        let registration = relay
+            .track_progress(session_id, ProgressToken::Number(7))
+            .await;
+        assert_ne!(registration.token(), "7");
+
+        let progress = |token: &str| BackendNotification {
+            server_id: Uuid::new_v4(),
+            server_name: "build".to_string(),
+            namespace: "build".to_string(),
+            notification: JsonRpcNotification::new(
+                "notifications/progress",
+                Some(json!({"progressToken": token, "progress": 50, "total": 100})),
+            ),
+        };
+        relay.forward_notification(progress("unknown")).await;
+        relay
+            .forward_notification(progress(registration.token()))
+            .await;
+
+        let message = tokio::time::timeout(Duration::from_secs(1), client.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["params"]["progressToken"], 7);
+        assert_eq!(message["params"]["progress"], 50);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), other.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notifications_only_reach_gateway_sessions() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+        let mut client = relay.connect(Uuid::new_v4(), Default::default()).await;
+        let (_, mut dashboard) = relay.streams.register_client(EventFilters::default()).await;
+
+        relay
+            .forward_notification(BackendNotification {
+                server_id: Uuid::new_v4(),
+                server_name: "build".to_string(),
+                namespace: "build".to_string(),
+                notification: JsonRpcNotification::new(
+                    "notifications/message",
+                    Some(json!({"level": "info", "data": "done"})),
+                ),
+            })
+            .await;
+
+        let message = tokio::time::timeout(Duration::from_secs(1), client.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message["params"]["logger"], "build");
+        assert!(dashboard.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_request_without_client_is_refused() {
+        let proxy = McpProxy::new();
+        let relay = relay(&proxy).await;
+
+        let (tx, rx) = oneshot::channel();
+        relay
+            .forward_request(BackendRequest {
+                server_id: Uuid::new_v4(),
+                server_name: "llm".to_string(),
+                namespace: "llm".to_string(),
+                request: crate::mcp::protocol::JsonRpcRequest::new(5i64, "roots/list", None),
+                reply: tx,
+            })
+            .await;
+
+        assert_eq!(rx.await.unwrap().error.unwrap().code, -32601);
+    }
+}
@@ -0,0 +1,366 @@
+//! Per-backend timeouts, retries and circuit breaking
+//!
+//! Each `mcp_servers` row carries its own resilience settings. Requests that
+//! fail because the backend could not be reached or did not answer are
+//! retried with jittered exponential backoff, but only for methods that are
+//! safe to repeat. Consecutive failures open the server's circuit breaker:
+//! while it is open, requests fail at once instead of waiting on a backend
+//! that is known to be down, and aggregations skip the server. After the
+//! cooldown the circuit is half-open: a single probe request goes through,
+//! the others are still rejected, and the probe's result decides whether the
+//! circuit closes again or stays open for another cooldown.
+
+use crate::db::models::McpServer;
+use crate::utils::AppError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+use utoipa::ToSchema;
+
+/// Default time allowed to establish a connection to a backend
+pub const DEFAULT_CONNECT_TIMEOUT_MS: i32 = 10_000;
+
+/// Default time a backend gets to answer a request
+pub const DEFAULT_REQUEST_TIMEOUT_MS: i32 = 30_000;
+
+/// Default number of retries of a failed idempotent request
+pub const DEFAULT_MAX_RETRIES: i32 = 2;
+
+/// Default delay before the first retry
+pub const DEFAULT_RETRY_BACKOFF_MS: i32 = 200;
+
+/// Default consecutive failures that open a circuit
+pub const DEFAULT_BREAKER_THRESHOLD: i32 = 5;
+
+/// Default time an open circuit rejects requests
+pub const DEFAULT_BREAKER_COOLDOWN_SECS: i32 = 30;
+
+/// Longest delay between two attempts, however many retries came before
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Resilience settings of a server, as configured on its row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResiliencePolicy {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+    /// Consecutive failures that open the circuit; 0 never opens it
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl From<&McpServer> for ResiliencePolicy {
+    fn from(server: &McpServer) -> Self {
+        // Timeouts of zero would fail every request, so they are at least 1ms
+        let millis = |ms: i32| Duration::from_millis(u64::try_from(ms).unwrap_or(0).max(1));
+        let count = |n: i32| u32::try_from(n).unwrap_or(0);
+        Self {
+            connect_timeout: millis(server.connect_timeout_ms),
+            request_timeout: millis(server.request_timeout_ms),
+            max_retries: count(server.max_retries),
+            retry_backoff: Duration::from_millis(count(server.retry_backoff_ms).into()),
+            breaker_threshold: count(server.breaker_threshold),
+            breaker_cooldown: Duration::from_secs(count(server.breaker_cooldown_secs).into()),
+        }
+    }
+}
+
+impl ResiliencePolicy {
+    /// Retries allowed for a request to `method`
+    pub fn retries_for(&self, method: &str) -> u32 {
+        if is_idempotent(method) {
+            self.max_retries
+        } else {
+            0
+        }
+    }
+
+    /// Delay before retry number `attempt` (counting from 0)
+    ///
+    /// The backoff doubles with each attempt up to [`MAX_BACKOFF`]; the
+    /// actual delay is drawn between half of it and all of it, so callers
+    /// that failed together do not retry together.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        let ceiling = self.retry_backoff.saturating_mul(factor).min(MAX_BACKOFF);
+        ceiling.mul_f64(0.5 + fastrand::f64() / 2.0)
+    }
+}
+
+/// Whether repeating a request to `method` cannot change anything on the backend
+pub fn is_idempotent(method: &str) -> bool {
+    method.ends_with("/list") || matches!(method, "resources/read" | "ping")
+}
+
+/// Whether an error means the backend failed, rather than answered or was abandoned
+///
+/// Only these errors are retried and count against the circuit breaker. A
+/// JSON-RPC error is an answer, so it shows the backend is up.
+pub fn is_backend_failure(error: &AppError) -> bool {
+    matches!(
+        error,
+        AppError::Upstream(_) | AppError::UpstreamTimeout(_) | AppError::Process(_)
+    )
+}
+
+/// Position of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests go through
+    Closed,
+    /// Requests are rejected until the cooldown ends
+    Open,
+    /// The cooldown ended; one probe request closes or reopens the circuit
+    HalfOpen,
+}
+
+/// Circuit breaker state reported by the REST API
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    /// Failures since the backend last answered
+    pub consecutive_failures: u32,
+    /// When the circuit last opened, while it is not closed
+    pub opened_at: Option<DateTime<Utc>>,
+    /// When an open circuit lets a request through again
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+/// Circuit breaker guarding one backend
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    /// When the circuit opened, both for measuring and for reporting
+    opened: Option<(Instant, DateTime<Utc>)>,
+    /// Whether the one request a half-open circuit lets through is running
+    probe_in_flight: bool,
+}
+
+impl BreakerInner {
+    fn state(&self, cooldown: Duration) -> BreakerState {
+        match self.opened {
+            None => BreakerState::Closed,
+            Some((at, _)) if at.elapsed() < cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// A closed breaker opening after `threshold` consecutive failures
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            inner: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Current position of the breaker
+    pub fn state(&self) -> BreakerState {
+        self.lock().state(self.cooldown)
+    }
+
+    /// Whether requests are currently rejected
+    pub fn is_open(&self) -> bool {
+        self.state() == BreakerState::Open
+    }
+
+    /// Permission to send a request, unless the circuit rejects it
+    ///
+    /// A closed circuit admits every request and an open one none. A
+    /// half-open circuit admits a single probe until its permit is dropped,
+    /// so a backend that just came back is not hit by every waiting caller.
+    pub fn try_acquire(&self) -> Option<BreakerPermit<'_>> {
+        let mut inner = self.lock();
+        match inner.state(self.cooldown) {
+            BreakerState::Closed => Some(BreakerPermit {
+                breaker: self,
+                probe: false,
+            }),
+            BreakerState::HalfOpen if !inner.probe_in_flight => {
+                inner.probe_in_flight = true;
+                Some(BreakerPermit {
+                    breaker: self,
+                    probe: true,
+                })
+            }
+            BreakerState::HalfOpen | BreakerState::Open => None,
+        }
+    }
+
+    /// Note that the backend answered, closing the circuit
+    ///
+    /// Returns `true` if the circuit was open or half-open before.
+    pub fn record_success(&self) -> bool {
+        let mut inner = self.lock();
+        inner.consecutive_failures = 0;
+        inner.opened.take().is_some()
+    }
+
+    /// Note that the backend failed
+    ///
+    /// Returns `true` if this failure opened (or reopened) the circuit.
+    pub fn record_failure(&self) -> bool {
+        let mut inner = self.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let trips = match inner.state(self.cooldown) {
+            BreakerState::Closed => {
+                self.threshold > 0 && inner.consecutive_failures >= self.threshold
+            }
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+        if trips {
+            inner.opened = Some((Instant::now(), Utc::now()));
+        }
+        trips
+    }
+
+    /// Snapshot of the breaker for reporting
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.lock();
+        let state = inner.state(self.cooldown);
+        let opened_at = inner.opened.map(|(_, at)| at);
+        let retry_at = match state {
+            BreakerState::Open => opened_at.and_then(|at| {
+                chrono::Duration::from_std(self.cooldown)
+                    .ok()
+                    .map(|cooldown| at + cooldown)
+            }),
+            _ => None,
+        };
+        BreakerStatus {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            opened_at,
+            retry_at,
+        }
+    }
+}
+
+/// A request admitted by a [`CircuitBreaker`]
+///
+/// Dropping the permit of a half-open circuit's probe lets the next probe
+/// through, whether or not the probe's outcome was recorded.
+#[derive(Debug)]
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.lock().probe_in_flight = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_idempotent_methods_are_retried() {
+        let policy = ResiliencePolicy {
+            connect_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_secs(1),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(100),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
+        };
+        assert_eq!(policy.retries_for("tools/list"), 3);
+        assert_eq!(policy.retries_for("resources/templates/list"), 3);
+        assert_eq!(policy.retries_for("resources/read"), 3);
+        assert_eq!(policy.retries_for("ping"), 3);
+        assert_eq!(policy.retries_for("tools/call"), 0);
+        assert_eq!(policy.retries_for("resources/subscribe"), 0);
+
+        for attempt in 0..3 {
+            let ceiling = Duration::from_millis(100 << attempt);
+            let delay = policy.backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?}", delay);
+        }
+        assert!(policy.backoff(40) <= MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_breaker_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_success());
+        assert!(!breaker.record_failure());
+        assert!(!breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        assert!(breaker.record_failure());
+        assert!(breaker.is_open());
+        let status = breaker.status();
+        assert_eq!(status.consecutive_failures, 3);
+        assert!(status.retry_at.unwrap() > status.opened_at.unwrap());
+
+        // Calls already in flight when it opened do not extend the cooldown
+        assert!(!breaker.record_failure());
+
+        let disabled = CircuitBreaker::new(0, Duration::from_secs(60));
+        for _ in 0..10 {
+            assert!(!disabled.record_failure());
+        }
+        assert_eq!(disabled.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_breaker_closes_or_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        // Only one probe goes through at a time
+        let probe = breaker.try_acquire().unwrap();
+        assert!(breaker.try_acquire().is_none());
+        drop(probe);
+        let _probe = breaker.try_acquire().unwrap();
+
+        // A failed trial reopens the circuit
+        assert!(breaker.record_failure());
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        assert!(breaker.record_success());
+        let status = breaker.status();
+        assert_eq!(status.state, BreakerState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+        assert!(status.opened_at.is_none());
+    }
+
+    #[test]
+    fn test_breaker_permits() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60));
+        let first = breaker.try_acquire().unwrap();
+        let second = breaker.try_acquire().unwrap();
+        assert!(breaker.record_failure());
+        assert!(breaker.try_acquire().is_none());
+
+        // Closed-circuit permits never held a probe
+        drop(first);
+        drop(second);
+        assert!(breaker.try_acquire().is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Roots that backends see
+//!
+//! A backend asking `roots/list` gets either the roots configured for its
+//! server or, relayed like sampling, the roots of a client. A server's
+//! `roots_scope` narrows client roots down to one directory: a root
+//! containing the scope is replaced by the scope, roots inside the scope
+//! pass unchanged and everything else is hidden from the backend.
+
+use crate::db::models::McpServer;
+use crate::mcp::protocol::Root;
+use crate::utils::AppError;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+/// Check that a configured root or scope is a plain `file://` URI
+pub fn validate_root_uri(uri: &str) -> Result<(), AppError> {
+    if !uri.starts_with("file://") || Directory::parse(uri).is_none() {
+        return Err(AppError::Validation(format!(
+            "Root {} must be a file:// URI",
+            uri
+        )));
+    }
+    if escapes(uri) {
+        return Err(AppError::Validation(format!(
+            "Root {} must not contain '..' segments",
+            uri
+        )));
+    }
+    Ok(())
+}
+
+/// The roots configured for a server, if it does not use its clients' roots
+pub fn configured_roots(server: &McpServer) -> Option<Vec<Root>> {
+    let roots = server.roots.as_ref()?;
+    match serde_json::from_value(roots.clone()) {
+        Ok(roots) => Some(roots),
+        Err(e) => {
+            tracing::warn!(server_name = %server.name, "Ignoring invalid roots: {}", e);
+            None
+        }
+    }
+}
+
+/// Narrow client roots to what a backend limited to `scope` may see
+///
+/// Roots are compared by the directory they resolve to, so roots that
+/// climb out of the scope with `..` (percent-encoded or not) are hidden,
+/// and the roots passed on are in that resolved form.
+pub fn scope_roots(roots: Vec<Root>, scope: &str) -> Vec<Root> {
+    let Some(scope) = Directory::parse(scope) else {
+        return Vec::new();
+    };
+    let mut scoped: Vec<Root> = Vec::new();
+    for root in roots {
+        let Some(directory) = Directory::parse(&root.uri) else {
+            continue;
+        };
+        let root = if directory.contains(&scope) {
+            Root {
+                uri: scope.uri(),
+                name: scope.segments.last().cloned(),
+            }
+        } else if scope.contains(&directory) {
+            Root {
+                uri: directory.uri(),
+                name: root.name,
+            }
+        } else {
+            continue;
+        };
+        if !scoped.iter().any(|r| r.uri == root.uri) {
+            scoped.push(root);
+        }
+    }
+    scoped
+}
+
+/// The directory a `file://` URI names
+#[derive(Debug, PartialEq)]
+struct Directory {
+    host: String,
+    /// Percent-decoded path segments, with `.` and `..` resolved
+    segments: Vec<String>,
+}
+
+impl Directory {
+    /// Resolve a `file://` URI the way a client reading it would
+    ///
+    /// `None` for other URIs and for segments hiding a separator behind
+    /// percent-encoding, which would split differently once decoded.
+    fn parse(uri: &str) -> Option<Self> {
+        let url = Url::parse(uri).ok()?;
+        if url.scheme() != "file" {
+            return None;
+        }
+        let mut segments = Vec::new();
+        for segment in url.path_segments()? {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+            if segment.contains(['/', '\\', '\0']) {
+                return None;
+            }
+            if !segment.is_empty() {
+                segments.push(segment.into_owned());
+            }
+        }
+        Some(Self {
+            host: url.host_str().unwrap_or_default().to_string(),
+            segments,
+        })
+    }
+
+    /// Whether `inner` is this directory or lies below it
+    fn contains(&self, inner: &Directory) -> bool {
+        self.host == inner.host && inner.segments.starts_with(&self.segments)
+    }
+
+    /// The directory as a `file://` URI without trailing slash
+    fn uri(&self) -> String {
+        let mut url = Url::parse("file:///").expect("valid base URI");
+        if !self.host.is_empty() {
+            // The host came out of a parsed URI, so it is valid
+            let _ = url.set_host(Some(&self.host));
+        }
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.clear().extend(&self.segments);
+        }
+        url.to_string()
+    }
+}
+
+/// Whether a URI climbs out of its directory with `..`, percent-encoded or not
+fn escapes(uri: &str) -> bool {
+    uri.split(['/', '\\'])
+        .any(|segment| percent_decode_str(segment).decode_utf8_lossy() == "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(uri: &str) -> Root {
+        Root {
+            uri: uri.to_string(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_scope_roots() {
+        let roots = vec![
+            root("file:///home/me/code"),
+            root("file:///home/me/code/app/src"),
+            root("file:///home/me/code-old"),
+            root("file:///tmp"),
+            root("file:///home/me/code/app/../../secrets"),
+            root("file:///home/me/code/app/%2e%2e/%2e%2e/secrets"),
+            root("file:///home/me/code/app/%2E%2E%2Fsecrets"),
+            root("file:///home/me/code/app/src/%2e%2e/%2e%2e/app-old"),
+            root("file:///home/me/code/app/lib/../src"),
+        ];
+
+        let scoped = scope_roots(roots, "file:///home/me/code/app/");
+        let uris: Vec<&str> = scoped.iter().map(|r| r.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            ["file:///home/me/code/app", "file:///home/me/code/app/src"]
+        );
+        assert_eq!(scoped[0].name.as_deref(), Some("app"));
+    }
+
+    #[test]
+    fn test_validate_root_uri() {
+        assert!(validate_root_uri("file:///srv/data").is_ok());
+        assert!(validate_root_uri("https://example.com").is_err());
+        assert!(validate_root_uri("file:///srv/data/../etc").is_err());
+        assert!(validate_root_uri("file:///srv/data/%2e%2e/etc").is_err());
+        assert!(validate_root_uri("file:///srv/data/%2E./etc").is_err());
+    }
+}
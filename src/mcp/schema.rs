@@ -0,0 +1,515 @@
+//! JSON Schema checks of tool arguments and structured results
+//!
+//! Arguments of `tools/call` are checked against the tool's cached
+//! `inputSchema` before they reach a backend, so a malformed call is
+//! answered right away with every violation instead of whatever the backend
+//! makes of it. Results carrying `structuredContent` are checked against the
+//! tool's `outputSchema` as the server's [`OutputValidation`] mode asks.
+//!
+//! The usual validation keywords are supported: `type`, `enum`, `const`,
+//! object, array, string and number constraints, `allOf`/`anyOf`/`oneOf`/
+//! `not` and local `$ref`s. Other keywords are ignored, as JSON Schema does
+//! with keywords it does not know, so an unusual schema never blocks a call.
+
+use crate::db::models::{McpServer, OutputValidation};
+use crate::mcp::protocol::{Tool, ToolCallResult};
+use crate::utils::patterns;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// How deep `$ref`s and nested schemas are followed
+const MAX_DEPTH: usize = 64;
+
+/// One way a value fails its schema
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    /// JSON Pointer to the offending value (empty for the value itself)
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Everything wrong with `instance` according to `schema`
+pub fn validate(schema: &Value, instance: &Value) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let validator = Validator { root: schema };
+    validator.check(schema, instance, "", 0, &mut violations);
+    violations
+}
+
+/// Check the arguments of a call against the tool's `inputSchema`
+pub fn check_arguments(tool: &Tool, arguments: &Value) -> Vec<Violation> {
+    validate(&tool.input_schema, arguments)
+}
+
+/// Check a tool result against the tool's `outputSchema` in `server`'s mode
+///
+/// Violations are only returned in `enforce` mode; `warn` logs them and lets
+/// the result through. Error results and tools without an output schema
+/// are not checked.
+pub fn check_output(
+    server: &McpServer,
+    tool: &Tool,
+    result: &ToolCallResult,
+) -> Result<(), Vec<Violation>> {
+    let Some(output_schema) = &tool.output_schema else {
+        return Ok(());
+    };
+    if server.output_validation == OutputValidation::Off || result.is_error {
+        return Ok(());
+    }
+
+    let violations = match &result.structured_content {
+        Some(content) => validate(output_schema, content),
+        None => vec![Violation {
+            path: String::new(),
+            message: "structuredContent is missing although the tool has an outputSchema"
+                .to_string(),
+        }],
+    };
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    match server.output_validation {
+        OutputValidation::Enforce => Err(violations),
+        _ => {
+            tracing::warn!(
+                server_name = %server.name,
+                "Result of tool {} does not match its outputSchema: {}",
+                tool.name,
+                join(&violations)
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Violations as one line, e.g. for an error message
+pub fn join(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(Violation::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+struct Validator<'a> {
+    /// The schema `$ref`s are resolved against
+    root: &'a Value,
+}
+
+impl Validator<'_> {
+    fn check(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        path: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                return out.push(Violation {
+                    path: path.to_string(),
+                    message: "no value is allowed here".to_string(),
+                })
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+        if depth > MAX_DEPTH {
+            return;
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            if let Some(target) = self.resolve(reference) {
+                self.check(target, instance, path, depth + 1, out);
+            }
+        }
+        self.check_keywords(schema, instance, path, depth, out)
+    }
+
+    fn check_keywords(
+        &self,
+        schema: &Map<String, Value>,
+        instance: &Value,
+        path: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let violation = |message: String| Violation {
+            path: path.to_string(),
+            message,
+        };
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() && !types.iter().any(|t| has_type(instance, t)) {
+                out.push(violation(format!(
+                    "expected {}, got {}",
+                    types.join(" or "),
+                    type_name(instance)
+                )));
+                // Constraints for other types would only add noise
+                return;
+            }
+        }
+
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            if !values.contains(instance) {
+                out.push(violation(format!(
+                    "must be one of {}",
+                    Value::Array(values.clone())
+                )));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != instance {
+                out.push(violation(format!("must be {}", expected)));
+            }
+        }
+
+        match instance {
+            Value::Object(object) => self.check_object(schema, object, path, depth, out),
+            Value::Array(items) => self.check_array(schema, items, path, depth, out),
+            Value::String(s) => check_string(schema, s, path, out),
+            Value::Number(n) => {
+                if let Some(n) = n.as_f64() {
+                    check_number(schema, n, path, out)
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for sub in all {
+                self.check(sub, instance, path, depth + 1, out);
+            }
+        }
+        if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+            if !any.iter().any(|sub| self.matches(sub, instance, depth)) {
+                out.push(violation(
+                    "must match at least one schema in anyOf".to_string(),
+                ));
+            }
+        }
+        if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+            let matched = one
+                .iter()
+                .filter(|sub| self.matches(sub, instance, depth))
+                .count();
+            if matched != 1 {
+                out.push(violation(format!(
+                    "must match exactly one schema in oneOf, matched {}",
+                    matched
+                )));
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.matches(not, instance, depth) {
+                out.push(violation("must not match the schema in not".to_string()));
+            }
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    out.push(Violation {
+                        path: path.to_string(),
+                        message: format!("missing required property \"{}\"", name),
+                    });
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+        for (name, value) in object {
+            let child = format!("{}/{}", path, escape(name));
+            match properties.and_then(|p| p.get(name)) {
+                Some(property) => self.check(property, value, &child, depth + 1, out),
+                None => match additional {
+                    Some(Value::Bool(false)) => out.push(Violation {
+                        path: child,
+                        message: "property is not allowed".to_string(),
+                    }),
+                    Some(additional) => self.check(additional, value, &child, depth + 1, out),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &self,
+        schema: &Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let mut fail = |message: String| {
+            out.push(Violation {
+                path: path.to_string(),
+                message,
+            })
+        };
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                fail(format!("must have at least {} items", min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+            if items.len() as u64 > max {
+                fail(format!("must have at most {} items", max));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let duplicate = items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].contains(item));
+            if duplicate {
+                fail("items must be unique".to_string());
+            }
+        }
+
+        if let Some(item_schema) = schema.get("items").filter(|s| !s.is_array()) {
+            for (i, item) in items.iter().enumerate() {
+                let child = format!("{}/{}", path, i);
+                self.check(item_schema, item, &child, depth + 1, out);
+            }
+        }
+    }
+
+    /// Whether `instance` satisfies `schema` entirely
+    fn matches(&self, schema: &Value, instance: &Value, depth: usize) -> bool {
+        let mut violations = Vec::new();
+        self.check(schema, instance, "", depth + 1, &mut violations);
+        violations.is_empty()
+    }
+
+    /// The schema a local `$ref` (`#/$defs/...`) points at
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+}
+
+fn check_string(schema: &Map<String, Value>, s: &str, path: &str, out: &mut Vec<Violation>) {
+    let mut fail = |message: String| {
+        out.push(Violation {
+            path: path.to_string(),
+            message,
+        })
+    };
+    let length = s.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            fail(format!("length must be at least {}", min));
+        }
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            fail(format!("length must be at most {}", max));
+        }
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        // A pattern we cannot compile is the backend's problem, not the caller's
+        if let Some(regex) = patterns::compiled(pattern) {
+            if !regex.is_match(s) {
+                fail(format!("must match pattern {}", pattern));
+            }
+        }
+    }
+}
+
+fn check_number(schema: &Map<String, Value>, n: f64, path: &str, out: &mut Vec<Violation>) {
+    let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+    let mut fail = |message: String| {
+        out.push(Violation {
+            path: path.to_string(),
+            message,
+        })
+    };
+    if let Some(min) = bound("minimum") {
+        if n < min {
+            fail(format!("must be at least {}", min));
+        }
+    }
+    if let Some(max) = bound("maximum") {
+        if n > max {
+            fail(format!("must be at most {}", max));
+        }
+    }
+    if let Some(min) = bound("exclusiveMinimum") {
+        if n <= min {
+            fail(format!("must be greater than {}", min));
+        }
+    }
+    if let Some(max) = bound("exclusiveMaximum") {
+        if n >= max {
+            fail(format!("must be less than {}", max));
+        }
+    }
+    if let Some(factor) = bound("multipleOf").filter(|f| *f > 0.0) {
+        let quotient = n / factor;
+        if (quotient - quotient.round()).abs() > 1e-9 {
+            fail(format!("must be a multiple of {}", factor));
+        }
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "number" => instance.is_number(),
+        "integer" => instance
+            .as_f64()
+            .is_some_and(|n| n.fract() == 0.0 && n.is_finite()),
+        // Unknown type names are not ours to enforce
+        _ => true,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Escape a property name for use in a JSON Pointer
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(schema: &Value, instance: &Value) -> Vec<String> {
+        let mut messages: Vec<String> = validate(schema, instance)
+            .iter()
+            .map(Violation::to_string)
+            .collect();
+        messages.sort();
+        messages
+    }
+
+    #[test]
+    fn test_object_violations_are_listed() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "minLength": 1},
+                "mode": {"enum": ["fast", "exact"]},
+                "limit": {"type": "integer", "minimum": 1, "maximum": 100},
+                "tags": {"type": "array", "items": {"enum": ["a", "b"]}, "uniqueItems": true}
+            },
+            "required": ["query", "mode"],
+            "additionalProperties": false
+        });
+
+        let instance = json!({"query": "", "limit": 2.5, "tags": ["a", "c", "a"], "extra": 1});
+        assert_eq!(
+            messages(&schema, &instance),
+            [
+                "/extra: property is not allowed",
+                "/limit: expected integer, got number",
+                "/query: length must be at least 1",
+                "/tags/1: must be one of [\"a\",\"b\"]",
+                "/tags: items must be unique",
+                "missing required property \"mode\"",
+            ]
+        );
+
+        let instance = json!({"query": "rust", "mode": "fast", "limit": 10});
+        assert!(validate(&schema, &instance).is_empty());
+    }
+
+    #[test]
+    fn test_combinators_and_refs() {
+        let schema = json!({
+            "$defs": {"id": {"type": "string", "pattern": "^[0-9]+$"}},
+            "type": "object",
+            "properties": {
+                "id": {"$ref": "#/$defs/id"},
+                "target": {"oneOf": [{"type": "string"}, {"type": "integer"}]},
+                "flag": {"not": {"const": false}}
+            }
+        });
+
+        let instance = json!({"id": "12a", "target": [], "flag": false});
+        assert_eq!(
+            messages(&schema, &instance),
+            [
+                "/flag: must not match the schema in not",
+                "/id: must match pattern ^[0-9]+$",
+                "/target: must match exactly one schema in oneOf, matched 0",
+            ]
+        );
+        assert!(validate(&schema, &json!({"id": "12", "target": 3})).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_keywords_are_ignored() {
+        let schema = json!({"type": "string", "format": "hostname", "x-custom": true});
+        assert!(validate(&schema, &json!("not a host name")).is_empty());
+        assert!(validate(&json!(true), &json!(null)).is_empty());
+        assert_eq!(
+            messages(&json!(false), &json!(1)),
+            ["no value is allowed here"]
+        );
+    }
+
+    #[test]
+    fn test_string_patterns() {
+        let schema = json!({"type": "string", "pattern": "^cached-[a-z]+$"});
+        assert!(validate(&schema, &json!("cached-abc")).is_empty());
+        assert_eq!(
+            messages(&schema, &json!("cached-1")),
+            ["must match pattern ^cached-[a-z]+$"]
+        );
+
+        // A pattern that does not compile is the backend's problem
+        let broken = json!({"type": "string", "pattern": "(unclosed"});
+        assert!(validate(&broken, &json!("anything")).is_empty());
+        assert!(validate(&broken, &json!("anything")).is_empty());
+    }
+}
@@ -1,11 +1,12 @@
 //! MCP Server process management
 
+use crate::db::models::McpServer;
 use crate::mcp::protocol::JsonRpcNotification;
 use crate::utils::AppError;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
+use tokio::process::{Child, ChildStdout, Command};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -28,6 +29,43 @@ pub struct McpServerConfig {
     pub working_dir: Option<String>,
 }
 
+impl TryFrom<&McpServer> for McpServerConfig {
+    type Error = AppError;
+
+    /// Build a spawn configuration from a stdio `mcp_servers` row
+    fn try_from(server: &McpServer) -> Result<Self, Self::Error> {
+        let command = server
+            .command
+            .clone()
+            .filter(|c| !c.trim().is_empty())
+            .ok_or_else(|| {
+                AppError::McpProtocol(format!("stdio server '{}' has no command", server.name))
+            })?;
+
+        let args = match &server.args {
+            Some(value) => serde_json::from_value(value.clone()).map_err(|e| {
+                AppError::McpProtocol(format!("Invalid args for server '{}': {}", server.name, e))
+            })?,
+            None => Vec::new(),
+        };
+
+        let env = match &server.env {
+            Some(value) => serde_json::from_value(value.clone()).map_err(|e| {
+                AppError::McpProtocol(format!("Invalid env for server '{}': {}", server.name, e))
+            })?,
+            None => HashMap::new(),
+        };
+
+        Ok(Self {
+            name: server.name.clone(),
+            command,
+            args,
+            env,
+            working_dir: None,
+        })
+    }
+}
+
 /// Handle to a running MCP server process
 pub struct McpServerHandle {
     pub id: String,
@@ -210,6 +248,17 @@ impl McpServerManager {
         })
     }
 
+    /// Take ownership of a server's stdout so the caller can read its messages
+    ///
+    /// Returns `None` if the server is unknown or stdout was already taken.
+    pub async fn take_stdout(&self, server_id: &str) -> Option<ChildStdout> {
+        let mut servers = self.servers.write().await;
+        servers
+            .get_mut(server_id)
+            .and_then(|handle| handle.child.as_mut())
+            .and_then(|child| child.stdout.take())
+    }
+
     /// Send a message to a server's stdin
     pub async fn send_message(&self, server_id: &str, message: &str) -> Result<(), AppError> {
         let mut servers = self.servers.write().await;
@@ -0,0 +1,225 @@
+//! Initialized sessions with backend MCP servers
+//!
+//! A [`BackendSession`] wraps a transport that has completed the
+//! `initialize` handshake and remembers what the backend negotiated, so the
+//! gateway can avoid calling methods a backend never advertised and adapt
+//! requests to the protocol revision it speaks. It also remembers the
+//! client capabilities the gateway offered, since a backend only learns of
+//! a change to them by initializing again.
+
+use crate::mcp::protocol::version;
+use crate::mcp::protocol::{
+    ClientCapabilities, ClientInfo, InitializeParams, InitializeResult, JsonRpcNotification,
+    JsonRpcRequest, JsonRpcResponse, ProtocolVersion, ServerCapabilities, MCP_PROTOCOL_VERSION,
+};
+use crate::mcp::transport::{CancelSignal, McpTransport};
+use crate::utils::AppError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// A backend connection that completed the initialize handshake
+pub struct BackendSession {
+    transport: Arc<dyn McpTransport>,
+    initialize: InitializeResult,
+    /// Client capabilities offered to the backend in `initialize`
+    offered: ClientCapabilities,
+    /// `updated_at` of the server row the session was built from
+    config_revision: DateTime<Utc>,
+    established_at: DateTime<Utc>,
+}
+
+impl BackendSession {
+    /// Run the handshake over `transport`, offering `offered`, and record the result
+    pub async fn establish(
+        transport: Arc<dyn McpTransport>,
+        config_revision: DateTime<Utc>,
+        offered: ClientCapabilities,
+    ) -> Result<Self, AppError> {
+        let initialize = match handshake(transport.as_ref(), offered.clone()).await {
+            Ok(result) => result,
+            Err(e) => {
+                transport.close().await;
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            transport,
+            initialize,
+            offered,
+            config_revision,
+            established_at: Utc::now(),
+        })
+    }
+
+    /// Send a request to the backend
+    pub async fn request(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse, AppError> {
+        self.transport.send_request(self.adapt(request)).await
+    }
+
+    /// Send a request to the backend that the caller may abandon
+    pub async fn request_cancellable(
+        &self,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        self.transport
+            .send_cancellable(self.adapt(request), cancel)
+            .await
+    }
+
+    /// Adapt a request to the backend's protocol revision
+    fn adapt(&self, mut request: JsonRpcRequest) -> JsonRpcRequest {
+        if let Some(params) = request.params.as_mut() {
+            version::adapt_params(&request.method, params, self.protocol_version());
+        }
+        request
+    }
+
+    /// Send a notification to the backend
+    pub async fn notify(&self, notification: JsonRpcNotification) -> Result<(), AppError> {
+        self.transport.send_notification(notification).await
+    }
+
+    /// Whether the session can be reused for a server row at `revision`
+    /// by a gateway now offering `offered`
+    pub fn is_usable(&self, revision: DateTime<Utc>, offered: &ClientCapabilities) -> bool {
+        self.transport.is_alive() && self.config_revision == revision && self.offered == *offered
+    }
+
+    /// Close the underlying connection
+    pub async fn close(&self) {
+        self.transport.close().await;
+    }
+
+    /// The backend's full initialize result
+    pub fn initialize_result(&self) -> &InitializeResult {
+        &self.initialize
+    }
+
+    /// Protocol revision the backend agreed to, checked during the handshake
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::parse(&self.initialize.protocol_version).unwrap_or(ProtocolVersion::LATEST)
+    }
+
+    /// Client capabilities the gateway offered the backend
+    pub fn offered_capabilities(&self) -> &ClientCapabilities {
+        &self.offered
+    }
+
+    /// Capabilities the backend advertised
+    pub fn capabilities(&self) -> &ServerCapabilities {
+        &self.initialize.capabilities
+    }
+
+    /// Whether the backend advertised the `tools` capability
+    pub fn supports_tools(&self) -> bool {
+        self.initialize.capabilities.tools.is_some()
+    }
+
+    /// Whether the backend advertised the `resources` capability
+    pub fn supports_resources(&self) -> bool {
+        self.initialize.capabilities.resources.is_some()
+    }
+
+    /// Whether the backend lets clients subscribe to resource updates
+    pub fn supports_resource_subscriptions(&self) -> bool {
+        self.initialize
+            .capabilities
+            .resources
+            .as_ref()
+            .is_some_and(|r| r.subscribe)
+    }
+
+    /// Whether the backend advertised the `prompts` capability
+    pub fn supports_prompts(&self) -> bool {
+        self.initialize.capabilities.prompts.is_some()
+    }
+
+    /// Whether the backend advertised the `completions` capability
+    pub fn supports_completions(&self) -> bool {
+        self.initialize.capabilities.completions.is_some()
+    }
+
+    /// Summary of the negotiated session for API responses
+    pub fn info(&self) -> NegotiatedSessionInfo {
+        NegotiatedSessionInfo {
+            protocol_version: self.initialize.protocol_version.clone(),
+            server_name: self.initialize.server_info.name.clone(),
+            server_version: self.initialize.server_info.version.clone(),
+            capabilities: serde_json::to_value(&self.initialize.capabilities).unwrap_or_default(),
+            established_at: self.established_at,
+        }
+    }
+}
+
+/// What a backend negotiated during initialize
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NegotiatedSessionInfo {
+    /// Protocol version the backend agreed to
+    #[schema(example = "2025-03-26")]
+    pub protocol_version: String,
+    /// Name the backend reported in `serverInfo`
+    pub server_name: String,
+    /// Version the backend reported in `serverInfo`
+    pub server_version: String,
+    /// Capabilities the backend advertised
+    #[schema(value_type = Object)]
+    pub capabilities: serde_json::Value,
+    /// When the session was established
+    pub established_at: DateTime<Utc>,
+}
+
+/// Run the MCP `initialize` / `notifications/initialized` handshake
+///
+/// The latest revision is requested; a backend answering with a revision
+/// the gateway does not speak is refused.
+pub async fn handshake(
+    transport: &dyn McpTransport,
+    capabilities: ClientCapabilities,
+) -> Result<InitializeResult, AppError> {
+    let params = InitializeParams {
+        protocol_version: MCP_PROTOCOL_VERSION.to_string(),
+        capabilities,
+        client_info: ClientInfo {
+            name: "metamcp".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    };
+
+    let request = JsonRpcRequest::new(
+        0i64,
+        "initialize",
+        Some(serde_json::to_value(params).map_err(|e| AppError::Internal(e.to_string()))?),
+    );
+    let response = transport.send_request(request).await?;
+
+    if let Some(error) = response.error {
+        return Err(AppError::McpProtocol(format!(
+            "Initialize failed: {} (code: {})",
+            error.message, error.code
+        )));
+    }
+
+    let result: InitializeResult = response
+        .result
+        .ok_or_else(|| AppError::McpProtocol("Empty initialize response".to_string()))
+        .and_then(|r| {
+            serde_json::from_value(r)
+                .map_err(|e| AppError::McpProtocol(format!("Invalid initialize response: {}", e)))
+        })?;
+    if ProtocolVersion::parse(&result.protocol_version).is_none() {
+        return Err(AppError::McpProtocol(format!(
+            "Unsupported protocol version: {}",
+            result.protocol_version
+        )));
+    }
+
+    transport
+        .send_notification(JsonRpcNotification::new("notifications/initialized", None))
+        .await?;
+
+    Ok(result)
+}
@@ -0,0 +1,384 @@
+//! Resource subscriptions of gateway clients
+//!
+//! Clients subscribe to prefixed resource URIs; the gateway subscribes to the
+//! resource on its backend once, however many client sessions are
+//! interested, and unsubscribes when the last of them unsubscribes or ends
+//! its session. The proxy subscribes again whenever it opens a new session
+//! with the backend, so subscriptions outlive reconnects. The relay uses the
+//! subscribers recorded here to decide which sessions get a backend's
+//! `notifications/resources/updated`.
+
+use crate::db::models::McpServer;
+use crate::mcp::proxy::SharedMcpProxy;
+use crate::utils::AppError;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// A backend resource, by server id and the URI the backend uses
+type Key = (Uuid, String);
+
+/// Whether the backend is subscribed; locked across the backend calls of
+/// one resource so they never interleave
+type BackendState = Arc<tokio::sync::Mutex<bool>>;
+
+struct Subscription {
+    server: McpServer,
+    sessions: HashSet<Uuid>,
+    backend: BackendState,
+}
+
+/// Reference-counted resource subscriptions
+///
+/// The registry lock is never held across a backend call: sessions are
+/// added and removed under it, and the backend is then brought in line
+/// under the resource's own lock, checking again who is subscribed.
+pub struct ResourceSubscriptions {
+    proxy: SharedMcpProxy,
+    subscriptions: Mutex<HashMap<Key, Subscription>>,
+}
+
+impl ResourceSubscriptions {
+    /// Create an empty registry forwarding to `proxy`'s backends
+    pub fn new(proxy: SharedMcpProxy) -> Self {
+        Self {
+            proxy,
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Key, Subscription>> {
+        self.subscriptions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Subscribe a session to a backend resource
+    ///
+    /// Only the first subscriber reaches the backend; if the backend refuses,
+    /// the session is not subscribed.
+    pub async fn subscribe(
+        &self,
+        session_id: Uuid,
+        server: &McpServer,
+        uri: &str,
+    ) -> Result<(), AppError> {
+        let key = (server.id, uri.to_string());
+        let backend = {
+            let mut subscriptions = self.lock();
+            let subscription = subscriptions
+                .entry(key.clone())
+                .or_insert_with(|| Subscription {
+                    server: server.clone(),
+                    sessions: HashSet::new(),
+                    backend: Arc::default(),
+                });
+            subscription.sessions.insert(session_id);
+            subscription.backend.clone()
+        };
+
+        let mut subscribed = backend.lock().await;
+        if *subscribed {
+            return Ok(());
+        }
+        if let Err(e) = self.proxy.subscribe_resource(server, uri).await {
+            let mut subscriptions = self.lock();
+            if let Some(subscription) = subscriptions.get_mut(&key) {
+                subscription.sessions.remove(&session_id);
+                if subscription.sessions.is_empty() {
+                    subscriptions.remove(&key);
+                }
+            }
+            return Err(e);
+        }
+        *subscribed = true;
+        tracing::debug!(server_name = %server.name, uri = %uri, "Subscribed to resource");
+        Ok(())
+    }
+
+    /// Unsubscribe a session from a backend resource
+    ///
+    /// The backend is only told once no session is subscribed any more.
+    pub async fn unsubscribe(
+        &self,
+        session_id: Uuid,
+        server: &McpServer,
+        uri: &str,
+    ) -> Result<(), AppError> {
+        let key = (server.id, uri.to_string());
+        let backend = {
+            let mut subscriptions = self.lock();
+            let Some(subscription) = subscriptions.get_mut(&key) else {
+                return Ok(());
+            };
+            subscription.sessions.remove(&session_id);
+            if !subscription.sessions.is_empty() {
+                return Ok(());
+            }
+            subscription.backend.clone()
+        };
+
+        self.release(&key, server, &backend).await
+    }
+
+    /// Drop every subscription of a session that ended
+    pub async fn end_session(&self, session_id: Uuid) {
+        let abandoned: Vec<(Key, McpServer, BackendState)> = self
+            .lock()
+            .iter_mut()
+            .filter_map(|(key, subscription)| {
+                subscription.sessions.remove(&session_id);
+                subscription.sessions.is_empty().then(|| {
+                    let server = subscription.server.clone();
+                    (key.clone(), server, subscription.backend.clone())
+                })
+            })
+            .collect();
+
+        let released = abandoned.iter().map(|(key, server, backend)| async move {
+            if let Err(e) = self.release(key, server, backend).await {
+                tracing::warn!(
+                    "Failed to unsubscribe from {} on {}: {}",
+                    key.1,
+                    server.name,
+                    e
+                );
+            }
+        });
+        futures::future::join_all(released).await;
+    }
+
+    /// Unsubscribe the backend from a resource nobody seemed to want
+    ///
+    /// A session may have subscribed again while this waited for the
+    /// resource's lock, in which case the backend stays subscribed. The
+    /// resource is only forgotten after the backend call, so a session
+    /// subscribing meanwhile waits for it and then subscribes again.
+    async fn release(
+        &self,
+        key: &Key,
+        server: &McpServer,
+        backend: &BackendState,
+    ) -> Result<(), AppError> {
+        let mut subscribed = backend.lock().await;
+        if !is_abandoned(&self.lock(), key, backend) {
+            return Ok(());
+        }
+
+        let result = if std::mem::take(&mut *subscribed) {
+            self.proxy.unsubscribe_resource(server, &key.1).await
+        } else {
+            Ok(())
+        };
+        {
+            let mut subscriptions = self.lock();
+            if is_abandoned(&subscriptions, key, backend) {
+                subscriptions.remove(key);
+            }
+        }
+        result?;
+        tracing::debug!(server_name = %server.name, uri = %key.1, "Unsubscribed from resource");
+        Ok(())
+    }
+
+    /// Sessions subscribed to a backend resource
+    pub async fn subscribers(&self, server_id: Uuid, uri: &str) -> Vec<Uuid> {
+        self.lock()
+            .get(&(server_id, uri.to_string()))
+            .map(|s| s.sessions.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Whether `backend`'s resource is still registered but has no subscriber left
+fn is_abandoned(
+    subscriptions: &HashMap<Key, Subscription>,
+    key: &Key,
+    backend: &BackendState,
+) -> bool {
+    subscriptions.get(key).is_some_and(|subscription| {
+        Arc::ptr_eq(&subscription.backend, backend) && subscription.sessions.is_empty()
+    })
+}
+
+/// Shared subscription registry
+pub type SharedResourceSubscriptions = Arc<ResourceSubscriptions>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::proxy::McpProxy;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Answers with `result`, echoing the request's id
+    struct Reply(Value);
+
+    impl Respond for Reply {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": body["id"],
+                "result": self.0
+            }))
+        }
+    }
+
+    async fn mount(mock: &MockServer, method_name: &str, result: Value, calls: u64) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "method": method_name })))
+            .respond_with(Reply(result))
+            .expect(calls)
+            .mount(mock)
+            .await;
+    }
+
+    fn server_at(url: &str) -> McpServer {
+        McpServer::test_fixture("logs", url)
+    }
+
+    async fn mount_handshake(mock: &MockServer, sessions: u64) {
+        let initialize = json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"resources": {"subscribe": true}},
+            "serverInfo": {"name": "mock", "version": "1"}
+        });
+        mount(mock, "initialize", initialize, sessions).await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({"method": "notifications/initialized"}),
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(mock)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_are_reference_counted() {
+        let mock = MockServer::start().await;
+        mount_handshake(&mock, 1).await;
+        mount(&mock, "resources/subscribe", json!({}), 1).await;
+        mount(&mock, "resources/unsubscribe", json!({}), 1).await;
+
+        let server = server_at(&mock.uri());
+        let subscriptions = ResourceSubscriptions::new(Arc::new(McpProxy::new()));
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+
+        subscriptions
+            .subscribe(a, &server, "log://app")
+            .await
+            .unwrap();
+        subscriptions
+            .subscribe(b, &server, "log://app")
+            .await
+            .unwrap();
+        assert_eq!(
+            subscriptions
+                .subscribers(server.id, "log://app")
+                .await
+                .len(),
+            2
+        );
+
+        subscriptions
+            .unsubscribe(a, &server, "log://app")
+            .await
+            .unwrap();
+        assert_eq!(
+            subscriptions.subscribers(server.id, "log://app").await,
+            vec![b]
+        );
+
+        subscriptions.end_session(b).await;
+        assert!(subscriptions
+            .subscribers(server.id, "log://app")
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backend_calls_do_not_block_other_resources() {
+        let mock = MockServer::start().await;
+        mount_handshake(&mock, 1).await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({"method": "resources/subscribe", "params": {"uri": "log://slow"}}),
+            ))
+            .respond_with(|request: &Request| {
+                Reply(json!({}))
+                    .respond(request)
+                    .set_delay(Duration::from_millis(500))
+            })
+            .mount(&mock)
+            .await;
+
+        let server = server_at(&mock.uri());
+        let subscriptions = Arc::new(ResourceSubscriptions::new(Arc::new(McpProxy::new())));
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let slow = tokio::spawn({
+            let subscriptions = subscriptions.clone();
+            let server = server.clone();
+            async move { subscriptions.subscribe(a, &server, "log://slow").await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Neither lookups nor ending another session wait for the slow backend
+        tokio::time::timeout(Duration::from_millis(100), async {
+            assert!(subscriptions
+                .subscribers(server.id, "log://other")
+                .await
+                .is_empty());
+            subscriptions.end_session(b).await;
+        })
+        .await
+        .unwrap();
+
+        slow.await.unwrap().unwrap();
+        assert_eq!(
+            subscriptions.subscribers(server.id, "log://slow").await,
+            vec![a]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refused_subscription_is_forgotten() {
+        let mock = MockServer::start().await;
+        mount_handshake(&mock, 1).await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"method": "resources/subscribe"})))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock)
+            .await;
+
+        let server = server_at(&mock.uri());
+        let subscriptions = ResourceSubscriptions::new(Arc::new(McpProxy::new()));
+        assert!(subscriptions
+            .subscribe(Uuid::new_v4(), &server, "log://app")
+            .await
+            .is_err());
+        assert!(subscriptions
+            .subscribers(server.id, "log://app")
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_new_backend_session_subscribes_again() {
+        let mock = MockServer::start().await;
+        mount_handshake(&mock, 2).await;
+        mount(&mock, "resources/subscribe", json!({}), 2).await;
+
+        let server = server_at(&mock.uri());
+        let proxy = Arc::new(McpProxy::new());
+        let subscriptions = ResourceSubscriptions::new(proxy.clone());
+        subscriptions
+            .subscribe(Uuid::new_v4(), &server, "log://app")
+            .await
+            .unwrap();
+
+        // As after the server was updated or its process restarted
+        proxy.disconnect(server.id).await;
+        proxy.session(&server).await.unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! Incremental decoder for `text/event-stream` bodies
+
+/// A single server-sent event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type (`message` when the stream did not name one)
+    pub event: String,
+    /// Event payload, with multiple `data:` lines joined by `\n`
+    pub data: String,
+    /// Last event id, if the stream set one
+    pub id: Option<String>,
+}
+
+/// Decoder that turns arbitrary byte chunks into complete events
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+}
+
+impl SseDecoder {
+    /// Create an empty decoder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return every event it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Flush an event left unterminated when the stream ended
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            if let Some(event) = self.process_line(line.trim_end_matches('\r')) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "id" => self.id = Some(value.to_string()),
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        Some(SseEvent {
+            event: event.unwrap_or_else(|| "message".to_string()),
+            data: std::mem::take(&mut self.data).join("\n"),
+            id: self.id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_split_chunks() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"event: endpoint\r\nda").is_empty());
+
+        let events =
+            decoder.push(b"ta: /messages?sessionId=1\r\n\r\n: ping\n\ndata: {\"a\":1}\n\n");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].event, "endpoint");
+        assert_eq!(events[0].data, "/messages?sessionId=1");
+        assert_eq!(events[1].event, "message");
+        assert_eq!(events[1].data, "{\"a\":1}");
+    }
+
+    #[test]
+    fn test_decode_multiline_data() {
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"id: 7\ndata: line1\ndata: line2\n\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "line1\nline2");
+        assert_eq!(events[0].id.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_finish_flushes_unterminated_event() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: tail").is_empty());
+        assert_eq!(decoder.finish().unwrap().data, "tail");
+    }
+}
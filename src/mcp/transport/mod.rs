@@ -0,0 +1,529 @@
+//! Backend transports for talking to MCP servers
+//!
+//! Each transport owns a live connection to one backend and multiplexes
+//! concurrent callers over it, rewriting request ids so responses can be
+//! routed back to the caller that sent them.
+
+pub mod event_stream;
+pub mod sse;
+pub mod stdio;
+pub mod streamable_http;
+
+use crate::db::models::McpServer;
+use crate::mcp::protocol::{
+    JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, RequestId, Root, RootsListResult,
+};
+use crate::mcp::roots;
+use crate::utils::AppError;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use uuid::Uuid;
+
+pub use sse::SseTransport;
+pub use stdio::StdioTransport;
+pub use streamable_http::StreamableHttpTransport;
+
+/// Default time to wait for a backend to answer a request
+///
+/// Servers set their own with `request_timeout_ms`.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time a client gets to answer a request relayed from a backend
+///
+/// Longer than [`DEFAULT_REQUEST_TIMEOUT`] since requests like sampling may
+/// wait for a human.
+pub const SERVER_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A live connection to a backend MCP server
+#[async_trait]
+pub trait McpTransport: Send + Sync {
+    /// Send a request and wait for the matching response
+    ///
+    /// The response carries the same id as `request`, regardless of the id
+    /// used on the wire.
+    async fn send_request(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse, AppError> {
+        self.send_cancellable(request, CancelSignal::never()).await
+    }
+
+    /// Send a request the caller may abandon
+    ///
+    /// When `cancel` fires, the backend is sent `notifications/cancelled`
+    /// for the wire id and the call ends with [`AppError::Cancelled`].
+    async fn send_cancellable(
+        &self,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError>;
+
+    /// Send a notification (no response expected)
+    async fn send_notification(&self, notification: JsonRpcNotification) -> Result<(), AppError>;
+
+    /// Whether the connection can still carry messages
+    fn is_alive(&self) -> bool;
+
+    /// Tear down the connection
+    async fn close(&self);
+}
+
+/// Fires when the caller of a request gives up on it
+#[derive(Debug, Default)]
+pub struct CancelSignal(Option<oneshot::Receiver<Option<String>>>);
+
+impl CancelSignal {
+    /// A signal and the sender that fires it with an optional reason
+    pub fn new() -> (oneshot::Sender<Option<String>>, Self) {
+        let (tx, rx) = oneshot::channel();
+        (tx, Self(Some(rx)))
+    }
+
+    /// A signal that never fires
+    pub fn never() -> Self {
+        Self(None)
+    }
+
+    /// Wait until the request is cancelled, returning the reason
+    ///
+    /// Never resolves if the sender is dropped without firing.
+    pub async fn cancelled(self) -> Option<String> {
+        match self.0 {
+            Some(rx) => match rx.await {
+                Ok(reason) => reason,
+                Err(_) => std::future::pending().await,
+            },
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// `notifications/cancelled` for a request we sent with `wire_id`
+pub(crate) fn cancel_notification(
+    wire_id: &RequestId,
+    reason: Option<String>,
+) -> JsonRpcNotification {
+    let mut params = serde_json::json!({ "requestId": wire_id });
+    if let Some(reason) = reason {
+        params["reason"] = serde_json::json!(reason);
+    }
+    JsonRpcNotification::new("notifications/cancelled", Some(params))
+}
+
+/// A notification a backend sent on its own initiative
+#[derive(Debug, Clone)]
+pub struct BackendNotification {
+    pub server_id: Uuid,
+    pub server_name: String,
+    pub namespace: String,
+    pub notification: JsonRpcNotification,
+}
+
+/// A request a backend sent to the client side (e.g. `sampling/createMessage`)
+#[derive(Debug)]
+pub struct BackendRequest {
+    pub server_id: Uuid,
+    pub server_name: String,
+    pub namespace: String,
+    pub request: JsonRpcRequest,
+    /// Where the client's answer must be sent; dropping it fails the request
+    pub reply: oneshot::Sender<JsonRpcResponse>,
+}
+
+/// Where a transport publishes messages received from its backend
+#[derive(Clone)]
+pub struct BackendEvents {
+    server_id: Uuid,
+    server_name: String,
+    namespace: String,
+    notifications: broadcast::Sender<BackendNotification>,
+    requests: Option<mpsc::Sender<BackendRequest>>,
+    request_timeout: Duration,
+    /// Whether the server's policy lets it ask clients for sampling
+    allow_sampling: bool,
+    /// Whether the server's policy lets it ask users for input
+    allow_elicitation: bool,
+    /// Roots answered to `roots/list` without asking a client
+    roots: Option<Vec<Root>>,
+    /// Directory client roots are narrowed to
+    roots_scope: Option<String>,
+    /// Resources to subscribe to again when the backend forgets our session
+    subscribed: Arc<SubscribedResources>,
+}
+
+impl BackendEvents {
+    /// Publish notifications from `server` on `notifications`
+    pub fn new(server: &McpServer, notifications: broadcast::Sender<BackendNotification>) -> Self {
+        Self {
+            server_id: server.id,
+            server_name: server.name.clone(),
+            namespace: server.namespace.clone(),
+            notifications,
+            requests: None,
+            request_timeout: SERVER_REQUEST_TIMEOUT,
+            allow_sampling: server.allow_sampling,
+            allow_elicitation: server.allow_elicitation,
+            roots: roots::configured_roots(server),
+            roots_scope: server.roots_scope.clone(),
+            subscribed: Arc::default(),
+        }
+    }
+
+    /// Hand server requests to `requests` instead of refusing them
+    pub fn with_requests(mut self, requests: mpsc::Sender<BackendRequest>) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+
+    /// Set how long a client gets to answer a handed-over request
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Share the server's resource subscriptions with the transport
+    pub fn with_subscribed(mut self, subscribed: Arc<SubscribedResources>) -> Self {
+        self.subscribed = subscribed;
+        self
+    }
+
+    /// Resources the gateway is subscribed to on this server
+    pub fn subscribed(&self) -> &SubscribedResources {
+        &self.subscribed
+    }
+
+    /// Events for a transport nobody listens to (e.g. one-off connections)
+    pub fn detached(server: &McpServer) -> Self {
+        Self::new(server, broadcast::channel(1).0)
+    }
+
+    /// Publish a notification; dropped silently if nobody is listening
+    pub fn notify(&self, notification: JsonRpcNotification) {
+        tracing::debug!(
+            server_name = %self.server_name,
+            method = %notification.method,
+            "Notification from MCP server"
+        );
+        let _ = self.notifications.send(BackendNotification {
+            server_id: self.server_id,
+            server_name: self.server_name.clone(),
+            namespace: self.namespace.clone(),
+            notification,
+        });
+    }
+
+    /// Answer a request the backend sent us
+    ///
+    /// `ping` is answered directly, as are requests the server's policy
+    /// forbids and `roots/list` for a server with configured roots; anything
+    /// else is handed to whoever takes server requests and the answer is
+    /// awaited, so callers should run this off their read loop.
+    pub async fn request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let id = request.id.clone().unwrap_or(0i64.into());
+        let lists_roots = request.method == "roots/list";
+        if let (true, Some(roots)) = (lists_roots, &self.roots) {
+            let result = RootsListResult {
+                roots: roots.clone(),
+            };
+            return JsonRpcResponse::success(id, serde_json::json!(result));
+        }
+        let requests = match &self.requests {
+            Some(requests) if request.method != "ping" => requests,
+            _ => return reply_to_server_request(&request),
+        };
+        if !self.permits(&request.method) {
+            tracing::debug!(
+                server_name = %self.server_name,
+                method = %request.method,
+                "Refusing request from MCP server by policy"
+            );
+            return JsonRpcResponse::error(
+                id,
+                -32601,
+                &format!("{} is not allowed for {}", request.method, self.server_name),
+                None,
+            );
+        }
+
+        tracing::debug!(
+            server_name = %self.server_name,
+            method = %request.method,
+            "Request from MCP server"
+        );
+
+        let (tx, rx) = oneshot::channel();
+        let relayed = BackendRequest {
+            server_id: self.server_id,
+            server_name: self.server_name.clone(),
+            namespace: self.namespace.clone(),
+            request,
+            reply: tx,
+        };
+        if let Err(mpsc::error::SendError(relayed)) = requests.send(relayed).await {
+            return reply_to_server_request(&relayed.request);
+        }
+
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(mut response)) => {
+                response.id = id;
+                match (lists_roots, &self.roots_scope) {
+                    (true, Some(scope)) => scope_roots_response(response, scope),
+                    _ => response,
+                }
+            }
+            Ok(Err(_)) => {
+                JsonRpcResponse::error(id, -32603, "Client went away before answering", None)
+            }
+            Err(_) => JsonRpcResponse::error(
+                id,
+                -32603,
+                &format!(
+                    "Client did not answer within {}s",
+                    self.request_timeout.as_secs()
+                ),
+                None,
+            ),
+        }
+    }
+
+    /// Whether the server's policy lets its `method` requests reach clients
+    fn permits(&self, method: &str) -> bool {
+        match method {
+            "sampling/createMessage" => self.allow_sampling,
+            "elicitation/create" => self.allow_elicitation,
+            _ => true,
+        }
+    }
+}
+
+/// Resources the gateway is subscribed to on one backend
+///
+/// Subscriptions belong to a backend session, so whoever opens a new one
+/// (the proxy replacing a session, or a transport re-initializing after the
+/// backend forgot ours) subscribes to these again.
+#[derive(Debug, Default)]
+pub struct SubscribedResources(std::sync::Mutex<HashSet<String>>);
+
+impl SubscribedResources {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Note that the gateway wants updates of `uri`
+    pub fn insert(&self, uri: &str) {
+        self.lock().insert(uri.to_string());
+    }
+
+    /// Note that the gateway no longer wants updates of `uri`
+    pub fn remove(&self, uri: &str) {
+        self.lock().remove(uri);
+    }
+
+    /// `resources/subscribe` requests restoring every subscription
+    pub fn requests(&self) -> Vec<JsonRpcRequest> {
+        self.lock()
+            .iter()
+            .map(|uri| {
+                let params = serde_json::json!({ "uri": uri });
+                JsonRpcRequest::new(0i64, "resources/subscribe", Some(params))
+            })
+            .collect()
+    }
+}
+
+/// Requests awaiting a response from a backend, keyed by wire id
+#[derive(Default)]
+pub struct PendingRequests {
+    next_id: AtomicI64,
+    waiters: Mutex<HashMap<RequestId, oneshot::Sender<JsonRpcResponse>>>,
+}
+
+impl PendingRequests {
+    /// Create an empty pending request table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate a fresh wire id and register a waiter for it
+    pub async fn register(&self) -> (RequestId, oneshot::Receiver<JsonRpcResponse>) {
+        let id = RequestId::Number(self.next_id.fetch_add(1, Ordering::Relaxed) + 1);
+        let (tx, rx) = oneshot::channel();
+        self.waiters.lock().await.insert(id.clone(), tx);
+        (id, rx)
+    }
+
+    /// Deliver a response to its waiter
+    ///
+    /// Returns `false` if nobody was waiting for this id.
+    pub async fn complete(&self, response: JsonRpcResponse) -> bool {
+        match self.waiters.lock().await.remove(&response.id) {
+            Some(tx) => tx.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Forget a waiter (e.g. after a timeout)
+    pub async fn remove(&self, id: &RequestId) {
+        self.waiters.lock().await.remove(id);
+    }
+
+    /// Drop every waiter so callers observe a closed connection
+    pub async fn fail_all(&self) {
+        self.waiters.lock().await.clear();
+    }
+
+    /// Wait for a registered response, restoring the caller's original id
+    pub async fn wait(
+        &self,
+        wire_id: RequestId,
+        rx: oneshot::Receiver<JsonRpcResponse>,
+        original_id: RequestId,
+        timeout: Duration,
+    ) -> Result<JsonRpcResponse, AppError> {
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(mut response)) => {
+                response.id = original_id;
+                Ok(response)
+            }
+            Ok(Err(_)) => Err(AppError::Upstream(
+                "Connection to MCP server closed before it responded".to_string(),
+            )),
+            Err(_) => {
+                self.remove(&wire_id).await;
+                Err(AppError::UpstreamTimeout(format!(
+                    "MCP server did not respond within {}ms",
+                    timeout.as_millis()
+                )))
+            }
+        }
+    }
+}
+
+/// Narrow the roots in a client's `roots/list` answer to `scope`
+fn scope_roots_response(mut response: JsonRpcResponse, scope: &str) -> JsonRpcResponse {
+    let Some(result) = response.result.take() else {
+        return response;
+    };
+    match serde_json::from_value::<RootsListResult>(result) {
+        Ok(mut list) => {
+            list.roots = roots::scope_roots(list.roots, scope);
+            response.result = Some(serde_json::json!(list));
+            response
+        }
+        Err(e) => JsonRpcResponse::error(
+            response.id,
+            -32603,
+            &format!("Client sent an invalid roots list: {}", e),
+            None,
+        ),
+    }
+}
+
+/// Answer a server-initiated request that the gateway does not relay
+fn reply_to_server_request(request: &JsonRpcRequest) -> JsonRpcResponse {
+    let id = request.id.clone().unwrap_or(0i64.into());
+    match request.method.as_str() {
+        "ping" => JsonRpcResponse::success(id, serde_json::json!({})),
+        method => {
+            JsonRpcResponse::error(id, -32601, &format!("Method not found: {}", method), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_pending_requests_restore_original_id() {
+        let pending = PendingRequests::new();
+        let (wire_id, rx) = pending.register().await;
+
+        let response = JsonRpcResponse::success(wire_id.clone(), json!({"ok": true}));
+        assert!(pending.complete(response).await);
+
+        let original = RequestId::String("client-7".to_string());
+        let response = pending
+            .wait(wire_id, rx, original.clone(), DEFAULT_REQUEST_TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(response.id, original);
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_unique_ids() {
+        let pending = PendingRequests::new();
+        let (a, _rx_a) = pending.register().await;
+        let (b, _rx_b) = pending.register().await;
+        assert_ne!(a, b);
+    }
+
+    fn server() -> McpServer {
+        McpServer::test_fixture("files", "http://localhost")
+    }
+
+    #[tokio::test]
+    async fn test_policy_refuses_sampling() {
+        let server = McpServer {
+            allow_sampling: false,
+            ..server()
+        };
+        let (tx, mut rx) = mpsc::channel(1);
+        let events = BackendEvents::detached(&server).with_requests(tx);
+
+        let request = JsonRpcRequest::new(3i64, "sampling/createMessage", Some(json!({})));
+        let response = events.request(request).await;
+        assert_eq!(response.id, RequestId::Number(3));
+        assert_eq!(response.error.unwrap().code, -32601);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_configured_roots_are_answered_directly() {
+        let server = McpServer {
+            roots: Some(json!([{"uri": "file:///srv/data", "name": "data"}])),
+            ..server()
+        };
+        let (tx, mut rx) = mpsc::channel(1);
+        let events = BackendEvents::detached(&server).with_requests(tx);
+
+        let response = events
+            .request(JsonRpcRequest::new(4i64, "roots/list", None))
+            .await;
+        assert_eq!(
+            response.result.unwrap()["roots"][0]["uri"],
+            "file:///srv/data"
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_client_roots_are_scoped() {
+        let response = JsonRpcResponse::success(
+            RequestId::Number(1),
+            json!({"roots": [
+                {"uri": "file:///work", "name": "work"},
+                {"uri": "file:///home"}
+            ]}),
+        );
+        let response = scope_roots_response(response, "file:///work/api");
+        assert_eq!(
+            response.result.unwrap()["roots"],
+            json!([{"uri": "file:///work/api", "name": "api"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_closed_connection() {
+        let pending = PendingRequests::new();
+        let (wire_id, rx) = pending.register().await;
+        pending.fail_all().await;
+
+        let result = pending
+            .wait(wire_id, rx, RequestId::Number(1), DEFAULT_REQUEST_TIMEOUT)
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,420 @@
+//! Legacy HTTP+SSE transport (MCP 2024-11-05)
+//!
+//! The client opens a long-lived `GET` event stream; the server announces a
+//! POST URL in an `endpoint` event, and every JSON-RPC response comes back
+//! as a `message` event on that stream. When the stream drops we reconnect
+//! and replay the initialize handshake and resource subscriptions, since
+//! each stream is a new session.
+
+use super::event_stream::{SseDecoder, SseEvent};
+use super::{cancel_notification, BackendEvents, CancelSignal, McpTransport, PendingRequests};
+use crate::db::models::McpServer;
+use crate::mcp::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use crate::mcp::resilience::ResiliencePolicy;
+use crate::utils::AppError;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::{header, Client};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Reconnect attempts before the transport gives up and reports itself dead
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Delay before the first reconnect attempt (doubled on each failure)
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// State shared between the transport handle and its stream task
+struct SseShared {
+    client: Client,
+    stream_url: Url,
+    server_name: String,
+    /// POST endpoint for the current stream, `None` while (re)connecting
+    endpoint: watch::Sender<Option<Url>>,
+    pending: PendingRequests,
+    alive: AtomicBool,
+    /// Handshake seen on the first connection, replayed after a reconnect
+    handshake: Mutex<Option<(JsonRpcRequest, JsonRpcNotification)>>,
+    events: BackendEvents,
+    request_timeout: Duration,
+}
+
+/// Connection to a legacy HTTP+SSE MCP server
+pub struct SseTransport {
+    shared: Arc<SseShared>,
+    task: JoinHandle<()>,
+}
+
+impl SseTransport {
+    /// Open the event stream and wait for the server's `endpoint` event
+    pub async fn connect(
+        client: Client,
+        server: &McpServer,
+        events: BackendEvents,
+    ) -> Result<Self, AppError> {
+        let stream_url = Url::parse(&server.url).map_err(|e| {
+            AppError::McpProtocol(format!("Invalid SSE URL '{}': {}", server.url, e))
+        })?;
+
+        let policy = ResiliencePolicy::from(server);
+        let (endpoint, mut endpoint_rx) = watch::channel(None);
+        let shared = Arc::new(SseShared {
+            client,
+            stream_url,
+            server_name: server.name.clone(),
+            endpoint,
+            pending: PendingRequests::new(),
+            alive: AtomicBool::new(true),
+            handshake: Mutex::new(None),
+            events,
+            request_timeout: policy.request_timeout,
+        });
+
+        let task = tokio::spawn(stream_loop(shared.clone()));
+        let transport = Self { shared, task };
+
+        let ready = tokio::time::timeout(
+            policy.connect_timeout,
+            endpoint_rx.wait_for(|endpoint| endpoint.is_some()),
+        )
+        .await
+        .is_ok_and(|r| r.is_ok());
+        if !ready {
+            transport.close().await;
+            return Err(AppError::Upstream(format!(
+                "SSE MCP server '{}' did not announce an endpoint",
+                server.name
+            )));
+        }
+
+        Ok(transport)
+    }
+}
+
+impl SseShared {
+    /// Wait until a POST endpoint is available
+    async fn current_endpoint(&self) -> Result<Url, AppError> {
+        let mut rx = self.endpoint.subscribe();
+        let endpoint = match tokio::time::timeout(
+            self.request_timeout,
+            rx.wait_for(|endpoint| endpoint.is_some() || !self.alive.load(Ordering::Acquire)),
+        )
+        .await
+        {
+            Ok(Ok(endpoint)) => endpoint.clone(),
+            _ => None,
+        };
+
+        endpoint.ok_or_else(|| self.not_connected())
+    }
+
+    fn not_connected(&self) -> AppError {
+        AppError::Upstream(format!(
+            "SSE MCP server '{}' is not connected",
+            self.server_name
+        ))
+    }
+
+    /// POST a message to an endpoint; the answer arrives on the stream
+    async fn post<T: Serialize>(&self, endpoint: Url, message: &T) -> Result<(), AppError> {
+        let response = self
+            .client
+            .post(endpoint)
+            .timeout(self.request_timeout)
+            .json(message)
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Failed to connect to MCP server: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::Upstream(format!(
+                "MCP server returned error status: {}",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Send a request to a specific endpoint and wait for its response
+    async fn request_at(
+        &self,
+        endpoint: Url,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        let (wire_id, rx) = self.pending.register().await;
+        let original_id = request.id.clone().unwrap_or(wire_id.clone());
+
+        let wire_request = JsonRpcRequest {
+            id: Some(wire_id.clone()),
+            ..request
+        };
+
+        if let Err(e) = self.post(endpoint.clone(), &wire_request).await {
+            self.pending.remove(&wire_id).await;
+            return Err(e);
+        }
+
+        let wait = self
+            .pending
+            .wait(wire_id.clone(), rx, original_id, self.request_timeout);
+        tokio::select! {
+            response = wait => response,
+            reason = cancel.cancelled() => {
+                self.pending.remove(&wire_id).await;
+                self.post(endpoint, &cancel_notification(&wire_id, reason)).await?;
+                Err(AppError::Cancelled("Request cancelled by the client".to_string()))
+            }
+        }
+    }
+
+    /// Resolve the `endpoint` event payload against the stream URL
+    ///
+    /// # Security
+    /// OWASP API7:2023 - the endpoint must stay on the stream's origin so a
+    /// backend cannot redirect our POSTs to an arbitrary host.
+    fn resolve_endpoint(&self, data: &str) -> Option<Url> {
+        let endpoint = self.stream_url.join(data.trim()).ok()?;
+        if endpoint.origin() != self.stream_url.origin() {
+            tracing::warn!(
+                server_name = %self.server_name,
+                "OWASP API7:2023 - Ignoring cross-origin SSE endpoint: {}",
+                endpoint
+            );
+            return None;
+        }
+        Some(endpoint)
+    }
+
+    /// Handle one `message` event from the stream
+    async fn handle_message(self: &Arc<Self>, data: &str) {
+        let value: Value = match serde_json::from_str(data) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!(server_name = %self.server_name, "Invalid JSON on SSE stream: {}", e);
+                return;
+            }
+        };
+
+        let messages = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        for message in messages {
+            match JsonRpcMessage::from_value(message) {
+                Ok(JsonRpcMessage::Response(response)) => {
+                    let id = response.id.clone();
+                    if !self.pending.complete(response).await {
+                        tracing::debug!(server_name = %self.server_name, "Dropping response for unknown id {}", id);
+                    }
+                }
+                Ok(JsonRpcMessage::Notification(notification)) => self.events.notify(notification),
+                Ok(JsonRpcMessage::Request(request)) => {
+                    let shared = self.clone();
+                    tokio::spawn(async move {
+                        let reply = shared.events.request(request).await;
+                        let result = match shared.current_endpoint().await {
+                            Ok(endpoint) => shared.post(endpoint, &reply).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = result {
+                            tracing::warn!(server_name = %shared.server_name, "Failed to reply to server request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(server_name = %self.server_name, "Invalid JSON-RPC message: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Replay the handshake and subscriptions on a fresh stream, then publish its endpoint
+    async fn reinitialize(self: Arc<Self>, endpoint: Url) {
+        let handshake = self.handshake.lock().await.clone();
+
+        if let Some((initialize, initialized)) = handshake {
+            let replay = self.request_at(endpoint.clone(), initialize, CancelSignal::never());
+            let result = match replay.await {
+                Ok(response) if response.error.is_none() => {
+                    self.post(endpoint.clone(), &initialized).await
+                }
+                Ok(response) => Err(AppError::McpProtocol(format!(
+                    "Initialize failed: {}",
+                    response.error.map(|e| e.message).unwrap_or_default()
+                ))),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = result {
+                tracing::warn!(server_name = %self.server_name, "Failed to re-initialize after reconnect: {}", e);
+                // Let the proxy replace this connection on the next request
+                self.alive.store(false, Ordering::Release);
+                self.endpoint.send_replace(None);
+                return;
+            }
+
+            for request in self.events.subscribed().requests() {
+                let replay = self.request_at(endpoint.clone(), request, CancelSignal::never());
+                let error = match replay.await {
+                    Ok(response) => response.error.map(|e| e.message),
+                    Err(e) => Some(e.to_string()),
+                };
+                if let Some(error) = error {
+                    tracing::warn!(server_name = %self.server_name, "Failed to resubscribe: {}", error);
+                }
+            }
+        }
+
+        tracing::info!(server_name = %self.server_name, "Reconnected to SSE MCP server");
+        self.endpoint.send_replace(Some(endpoint));
+    }
+}
+
+/// Keep the event stream open, reconnecting with backoff when it drops
+async fn stream_loop(shared: Arc<SseShared>) {
+    let mut attempts = 0;
+    let mut connected_before = false;
+
+    loop {
+        match open_stream(&shared).await {
+            Ok(response) => {
+                attempts = 0;
+                read_stream(&shared, response, connected_before).await;
+                connected_before = true;
+            }
+            Err(e) => {
+                tracing::warn!(server_name = %shared.server_name, "Failed to open SSE stream: {}", e);
+            }
+        }
+
+        // Responses for requests sent on the old stream will never arrive
+        shared.endpoint.send_replace(None);
+        shared.pending.fail_all().await;
+
+        attempts += 1;
+        if attempts > MAX_RECONNECT_ATTEMPTS {
+            break;
+        }
+
+        let delay = INITIAL_RECONNECT_DELAY * 2u32.pow(attempts - 1);
+        tracing::info!(
+            server_name = %shared.server_name,
+            "SSE stream closed, reconnecting in {}ms",
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+    }
+
+    tracing::error!(server_name = %shared.server_name, "Giving up on SSE MCP server after {} attempts", MAX_RECONNECT_ATTEMPTS);
+    shared.alive.store(false, Ordering::Release);
+    // Wake anyone waiting for an endpoint
+    shared.endpoint.send_replace(None);
+}
+
+async fn open_stream(shared: &SseShared) -> Result<reqwest::Response, AppError> {
+    let response = shared
+        .client
+        .get(shared.stream_url.clone())
+        .header(header::ACCEPT, "text/event-stream")
+        .send()
+        .await
+        .map_err(|e| AppError::Upstream(format!("Failed to connect to MCP server: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::Upstream(format!(
+            "MCP server returned error status: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response)
+}
+
+/// Dispatch events until the stream ends
+async fn read_stream(shared: &Arc<SseShared>, response: reqwest::Response, reconnect: bool) {
+    let mut decoder = SseDecoder::new();
+    let mut body = response.bytes_stream();
+
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::warn!(server_name = %shared.server_name, "SSE stream error: {}", e);
+                break;
+            }
+        };
+
+        for event in decoder.push(&chunk) {
+            dispatch_event(shared, event, reconnect).await;
+        }
+    }
+
+    if let Some(event) = decoder.finish() {
+        dispatch_event(shared, event, reconnect).await;
+    }
+}
+
+async fn dispatch_event(shared: &Arc<SseShared>, event: SseEvent, reconnect: bool) {
+    match event.event.as_str() {
+        "endpoint" => {
+            let Some(endpoint) = shared.resolve_endpoint(&event.data) else {
+                return;
+            };
+            if reconnect {
+                tokio::spawn(shared.clone().reinitialize(endpoint));
+            } else {
+                shared.endpoint.send_replace(Some(endpoint));
+            }
+        }
+        "message" => shared.handle_message(&event.data).await,
+        other => {
+            tracing::debug!(server_name = %shared.server_name, "Ignoring SSE event '{}'", other);
+        }
+    }
+}
+
+#[async_trait]
+impl McpTransport for SseTransport {
+    async fn send_cancellable(
+        &self,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        let endpoint = self.shared.current_endpoint().await?;
+
+        if request.method == "initialize" {
+            let mut handshake = self.shared.handshake.lock().await;
+            *handshake = Some((
+                request.clone(),
+                JsonRpcNotification::new("notifications/initialized", None),
+            ));
+        }
+
+        self.shared.request_at(endpoint, request, cancel).await
+    }
+
+    async fn send_notification(&self, notification: JsonRpcNotification) -> Result<(), AppError> {
+        let endpoint = self.shared.current_endpoint().await?;
+        self.shared.post(endpoint, &notification).await
+    }
+
+    fn is_alive(&self) -> bool {
+        self.shared.alive.load(Ordering::Acquire)
+    }
+
+    async fn close(&self) {
+        self.task.abort();
+        self.shared.alive.store(false, Ordering::Release);
+        self.shared.endpoint.send_replace(None);
+        self.shared.pending.fail_all().await;
+    }
+}
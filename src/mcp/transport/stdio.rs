@@ -0,0 +1,199 @@
+//! stdio transport: newline-delimited JSON-RPC over a child process
+//!
+//! The process is launched and owned by [`McpServerManager`]; this transport
+//! writes to its stdin through the manager and reads its stdout in a
+//! background task, routing responses to waiting callers by id.
+
+use super::{cancel_notification, BackendEvents, CancelSignal, McpTransport, PendingRequests};
+use crate::db::models::McpServer;
+use crate::mcp::protocol::{JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+use crate::mcp::resilience::ResiliencePolicy;
+use crate::mcp::server_manager::{McpServerConfig, McpServerManager};
+use crate::utils::AppError;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::ChildStdout;
+
+/// Connection to a stdio MCP server process
+pub struct StdioTransport {
+    manager: Arc<McpServerManager>,
+    process_id: String,
+    server_name: String,
+    pending: Arc<PendingRequests>,
+    alive: Arc<AtomicBool>,
+    request_timeout: Duration,
+}
+
+impl StdioTransport {
+    /// Spawn the process described by a stdio `mcp_servers` row
+    pub async fn spawn(
+        manager: Arc<McpServerManager>,
+        server: &McpServer,
+        events: BackendEvents,
+    ) -> Result<Self, AppError> {
+        let config = McpServerConfig::try_from(server)?;
+        let process_id = manager.spawn_server(config).await?;
+
+        let stdout = match manager.take_stdout(&process_id).await {
+            Some(stdout) => stdout,
+            None => {
+                let _ = manager.stop_server(&process_id).await;
+                return Err(AppError::Process(format!(
+                    "stdout not available for MCP server '{}'",
+                    server.name
+                )));
+            }
+        };
+
+        let transport = Self {
+            manager,
+            process_id,
+            server_name: server.name.clone(),
+            pending: Arc::new(PendingRequests::new()),
+            alive: Arc::new(AtomicBool::new(true)),
+            request_timeout: ResiliencePolicy::from(server).request_timeout,
+        };
+
+        tokio::spawn(read_loop(
+            stdout,
+            transport.manager.clone(),
+            transport.process_id.clone(),
+            transport.server_name.clone(),
+            transport.pending.clone(),
+            transport.alive.clone(),
+            events,
+        ));
+
+        Ok(transport)
+    }
+
+    /// Write one message as a single line on the process's stdin
+    async fn write_message<T: Serialize>(&self, message: &T) -> Result<(), AppError> {
+        if !self.is_alive() {
+            return Err(AppError::Upstream(format!(
+                "stdio MCP server '{}' is not running",
+                self.server_name
+            )));
+        }
+
+        let line = serde_json::to_string(message).map_err(|e| AppError::Internal(e.to_string()))?;
+        self.manager.send_message(&self.process_id, &line).await
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn send_cancellable(
+        &self,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        let (wire_id, rx) = self.pending.register().await;
+        let original_id = request.id.clone().unwrap_or(wire_id.clone());
+
+        let wire_request = JsonRpcRequest {
+            id: Some(wire_id.clone()),
+            ..request
+        };
+
+        if let Err(e) = self.write_message(&wire_request).await {
+            self.pending.remove(&wire_id).await;
+            return Err(e);
+        }
+
+        let wait = self
+            .pending
+            .wait(wire_id.clone(), rx, original_id, self.request_timeout);
+        tokio::select! {
+            response = wait => response,
+            reason = cancel.cancelled() => {
+                self.pending.remove(&wire_id).await;
+                self.write_message(&cancel_notification(&wire_id, reason)).await?;
+                Err(AppError::Cancelled("Request cancelled by the client".to_string()))
+            }
+        }
+    }
+
+    async fn send_notification(&self, notification: JsonRpcNotification) -> Result<(), AppError> {
+        self.write_message(&notification).await
+    }
+
+    fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+
+    async fn close(&self) {
+        self.alive.store(false, Ordering::Release);
+        self.pending.fail_all().await;
+        let _ = self.manager.stop_server(&self.process_id).await;
+    }
+}
+
+/// Read messages from the process's stdout until it exits
+async fn read_loop(
+    stdout: ChildStdout,
+    manager: Arc<McpServerManager>,
+    process_id: String,
+    server_name: String,
+    pending: Arc<PendingRequests>,
+    alive: Arc<AtomicBool>,
+    events: BackendEvents,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let value: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(_) => {
+                tracing::debug!(server_name = %server_name, "Ignoring non-JSON stdout line: {}", line);
+                continue;
+            }
+        };
+
+        // A backend may answer with a JSON-RPC batch
+        let messages = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        for message in messages {
+            match JsonRpcMessage::from_value(message) {
+                Ok(JsonRpcMessage::Response(response)) => {
+                    let id = response.id.clone();
+                    if !pending.complete(response).await {
+                        tracing::debug!(server_name = %server_name, "Dropping response for unknown id {}", id);
+                    }
+                }
+                Ok(JsonRpcMessage::Notification(notification)) => events.notify(notification),
+                Ok(JsonRpcMessage::Request(request)) => {
+                    let (manager, process_id) = (manager.clone(), process_id.clone());
+                    let (server_name, events) = (server_name.clone(), events.clone());
+                    tokio::spawn(async move {
+                        let reply = events.request(request).await;
+                        let line = serde_json::to_string(&reply).unwrap_or_default();
+                        if let Err(e) = manager.send_message(&process_id, &line).await {
+                            tracing::warn!(server_name = %server_name, "Failed to reply to server request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(server_name = %server_name, "Invalid JSON-RPC message: {}", e);
+                }
+            }
+        }
+    }
+
+    alive.store(false, Ordering::Release);
+    pending.fail_all().await;
+    tracing::warn!(server_name = %server_name, process_id = %process_id, "stdio MCP server closed stdout");
+}
@@ -0,0 +1,569 @@
+//! Streamable HTTP transport (MCP 2025-03-26)
+//!
+//! Every message is POSTed to a single endpoint. The server answers either
+//! with a JSON body or with a `text/event-stream` that may carry
+//! notifications and server requests before the final response. The
+//! session id issued on `initialize` is echoed on every later request; a
+//! 404 means the session expired and we transparently re-initialize and
+//! subscribe to the resources the old session was subscribed to.
+
+use super::event_stream::SseDecoder;
+use super::{cancel_notification, BackendEvents, CancelSignal, McpTransport};
+use crate::db::models::McpServer;
+use crate::mcp::protocol::{
+    JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, RequestId,
+};
+use crate::mcp::resilience::ResiliencePolicy;
+use crate::utils::AppError;
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::{header, Client, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+
+/// Header carrying the backend-issued session id
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the negotiated protocol version
+pub const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Outcome of a single POST to the backend
+enum PostOutcome {
+    /// The backend answered the request
+    Response(JsonRpcResponse),
+    /// The backend accepted a notification or response (202)
+    Accepted,
+    /// The backend no longer recognizes our session (404)
+    SessionExpired,
+}
+
+/// State shared with tasks that reply to server requests
+struct HttpShared {
+    client: Client,
+    url: String,
+    server_name: String,
+    session_id: RwLock<Option<String>>,
+    protocol_version: RwLock<Option<String>>,
+    /// The initialize request, kept so an expired session can be re-established
+    handshake: Mutex<Option<JsonRpcRequest>>,
+    next_id: AtomicI64,
+    alive: AtomicBool,
+    events: BackendEvents,
+    request_timeout: Duration,
+}
+
+/// Connection to a Streamable HTTP MCP server
+pub struct StreamableHttpTransport {
+    shared: Arc<HttpShared>,
+}
+
+impl StreamableHttpTransport {
+    /// Create a transport for an `http` `mcp_servers` row
+    ///
+    /// No request is made until the first message is sent.
+    pub fn new(client: Client, server: &McpServer, events: BackendEvents) -> Self {
+        Self {
+            shared: Arc::new(HttpShared {
+                client,
+                url: server.url.clone(),
+                server_name: server.name.clone(),
+                session_id: RwLock::new(None),
+                protocol_version: RwLock::new(None),
+                handshake: Mutex::new(None),
+                next_id: AtomicI64::new(0),
+                alive: AtomicBool::new(true),
+                events,
+                request_timeout: ResiliencePolicy::from(server).request_timeout,
+            }),
+        }
+    }
+
+    /// The session id issued by the backend, if any
+    pub async fn session_id(&self) -> Option<String> {
+        self.shared.session_id.read().await.clone()
+    }
+}
+
+impl HttpShared {
+    /// POST one message with the session headers
+    ///
+    /// Returns `None` when the backend no longer recognizes our session.
+    async fn send<T: Serialize>(&self, message: &T) -> Result<Option<reqwest::Response>, AppError> {
+        let session_id = self.session_id.read().await.clone();
+
+        let mut builder = self
+            .client
+            .post(&self.url)
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        if let Some(ref id) = session_id {
+            builder = builder.header(SESSION_ID_HEADER, id);
+        }
+        if let Some(version) = self.protocol_version.read().await.as_ref() {
+            builder = builder.header(PROTOCOL_VERSION_HEADER, version);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| AppError::Upstream(format!("Failed to connect to MCP server: {}", e)))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND && session_id.is_some() {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(AppError::Upstream(format!(
+                "MCP server returned error status: {}",
+                status
+            )));
+        }
+
+        if let Some(new_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+        {
+            if session_id.as_deref() != Some(new_id) {
+                *self.session_id.write().await = Some(new_id.to_string());
+            }
+        }
+
+        Ok(Some(response))
+    }
+
+    /// POST a notification or a response to a server request
+    async fn post_message<T: Serialize>(&self, message: &T) -> Result<PostOutcome, AppError> {
+        match self.send(message).await? {
+            Some(_) => Ok(PostOutcome::Accepted),
+            None => Ok(PostOutcome::SessionExpired),
+        }
+    }
+
+    /// POST a request and read its answer from a JSON or SSE body
+    async fn post_request(
+        self: &Arc<Self>,
+        request: &JsonRpcRequest,
+        wire_id: &RequestId,
+    ) -> Result<PostOutcome, AppError> {
+        let Some(response) = self.send(request).await? else {
+            return Ok(PostOutcome::SessionExpired);
+        };
+        if response.status() == StatusCode::ACCEPTED {
+            return Ok(PostOutcome::Accepted);
+        }
+
+        let is_event_stream = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("text/event-stream"));
+
+        let found = if is_event_stream {
+            self.read_event_stream(response, wire_id).await?
+        } else {
+            let body: Value = response.json().await.map_err(|e| {
+                AppError::McpProtocol(format!("Failed to parse MCP server response: {}", e))
+            })?;
+            self.handle_messages(body, wire_id)
+        };
+
+        found.map(PostOutcome::Response).ok_or_else(|| {
+            AppError::McpProtocol("MCP server response did not answer the request".to_string())
+        })
+    }
+
+    /// Read an SSE response body until the answer to `wire_id` arrives
+    async fn read_event_stream(
+        self: &Arc<Self>,
+        response: reqwest::Response,
+        wire_id: &RequestId,
+    ) -> Result<Option<JsonRpcResponse>, AppError> {
+        let mut decoder = SseDecoder::new();
+        let mut body = response.bytes_stream();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk
+                .map_err(|e| AppError::McpProtocol(format!("MCP server stream error: {}", e)))?;
+
+            for event in decoder.push(&chunk) {
+                if event.event != "message" {
+                    continue;
+                }
+                match serde_json::from_str::<Value>(&event.data) {
+                    Ok(value) => {
+                        if let Some(response) = self.handle_messages(value, wire_id) {
+                            return Ok(Some(response));
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!(server_name = %self.server_name, "Invalid JSON on response stream: {}", e);
+                    }
+                }
+            }
+        }
+
+        match decoder.finish() {
+            Some(event) if event.event == "message" => match serde_json::from_str(&event.data) {
+                Ok(value) => Ok(self.handle_messages(value, wire_id)),
+                Err(_) => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Process messages from a response body, returning the answer to `wire_id`
+    fn handle_messages(
+        self: &Arc<Self>,
+        value: Value,
+        wire_id: &RequestId,
+    ) -> Option<JsonRpcResponse> {
+        let messages = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        let mut answer = None;
+        for message in messages {
+            match JsonRpcMessage::from_value(message) {
+                Ok(JsonRpcMessage::Response(response)) if &response.id == wire_id => {
+                    answer = Some(response);
+                }
+                Ok(JsonRpcMessage::Response(response)) => {
+                    tracing::debug!(server_name = %self.server_name, "Dropping response for unknown id {}", response.id);
+                }
+                Ok(JsonRpcMessage::Notification(notification)) => self.events.notify(notification),
+                Ok(JsonRpcMessage::Request(request)) => {
+                    let transport = self.clone();
+                    tokio::spawn(async move {
+                        let reply = transport.events.request(request).await;
+                        if let Err(e) = transport.post_message(&reply).await {
+                            tracing::warn!(server_name = %transport.server_name, "Failed to reply to server request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(server_name = %self.server_name, "Invalid JSON-RPC message: {}", e);
+                }
+            }
+        }
+
+        answer
+    }
+
+    /// A fresh id for a request on the wire
+    fn next_wire_id(&self) -> RequestId {
+        RequestId::Number(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    /// Send a request once, returning `None` if the session expired
+    async fn try_request(
+        self: &Arc<Self>,
+        request: &JsonRpcRequest,
+        wire_id: &RequestId,
+    ) -> Result<Option<JsonRpcResponse>, AppError> {
+        let wire_request = JsonRpcRequest {
+            id: Some(wire_id.clone()),
+            ..request.clone()
+        };
+
+        let outcome = tokio::time::timeout(
+            self.request_timeout,
+            self.post_request(&wire_request, wire_id),
+        )
+        .await
+        .map_err(|_| {
+            AppError::UpstreamTimeout(format!(
+                "MCP server did not respond within {}ms",
+                self.request_timeout.as_millis()
+            ))
+        })??;
+
+        match outcome {
+            PostOutcome::Response(mut response) => {
+                response.id = request.id.clone().unwrap_or_else(|| wire_id.clone());
+                Ok(Some(response))
+            }
+            PostOutcome::SessionExpired => Ok(None),
+            PostOutcome::Accepted => Err(AppError::McpProtocol(
+                "MCP server accepted a request without answering it".to_string(),
+            )),
+        }
+    }
+
+    /// Establish a new session after the backend dropped ours
+    async fn reinitialize(self: &Arc<Self>) -> Result<(), AppError> {
+        let handshake = self.handshake.lock().await.clone().ok_or_else(|| {
+            AppError::McpProtocol("MCP session expired before it was initialized".to_string())
+        })?;
+
+        tracing::info!(server_name = %self.server_name, "MCP session expired, re-initializing");
+        *self.session_id.write().await = None;
+
+        let wire_id = self.next_wire_id();
+        let response = self.try_request(&handshake, &wire_id).await?.ok_or_else(|| {
+            AppError::McpProtocol("MCP server rejected re-initialize".to_string())
+        })?;
+        if let Some(error) = response.error {
+            return Err(AppError::McpProtocol(format!(
+                "Initialize failed: {} (code: {})",
+                error.message, error.code
+            )));
+        }
+
+        let initialized = JsonRpcNotification::new("notifications/initialized", None);
+        self.post_message(&initialized).await?;
+
+        for request in self.events.subscribed().requests() {
+            let wire_id = self.next_wire_id();
+            let error = match self.try_request(&request, &wire_id).await {
+                Ok(Some(response)) => response.error.map(|e| e.message),
+                Ok(None) => Some("session expired".to_string()),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(error) = error {
+                tracing::warn!(server_name = %self.server_name, "Failed to resubscribe: {}", error);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl McpTransport for StreamableHttpTransport {
+    async fn send_cancellable(
+        &self,
+        request: JsonRpcRequest,
+        cancel: CancelSignal,
+    ) -> Result<JsonRpcResponse, AppError> {
+        let shared = &self.shared;
+        if request.method == "initialize" {
+            *shared.session_id.write().await = None;
+            *shared.handshake.lock().await = Some(request.clone());
+        }
+
+        let wire_id = shared.next_wire_id();
+        let exchange = async {
+            match shared.try_request(&request, &wire_id).await? {
+                Some(response) => Ok(response),
+                None => {
+                    shared.reinitialize().await?;
+                    shared
+                        .try_request(&request, &wire_id)
+                        .await?
+                        .ok_or_else(|| {
+                            AppError::McpProtocol(
+                                "MCP session expired again after re-initialize".to_string(),
+                            )
+                        })
+                }
+            }
+        };
+
+        // Dropping the exchange abandons the POST and any response stream
+        let response = tokio::select! {
+            response = exchange => response?,
+            reason = cancel.cancelled() => {
+                shared.post_message(&cancel_notification(&wire_id, reason)).await?;
+                return Err(AppError::Cancelled("Request cancelled by the client".to_string()));
+            }
+        };
+
+        if request.method == "initialize" {
+            let version = response
+                .result
+                .as_ref()
+                .and_then(|r| r.get("protocolVersion"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            *shared.protocol_version.write().await = version;
+        }
+
+        Ok(response)
+    }
+
+    async fn send_notification(&self, notification: JsonRpcNotification) -> Result<(), AppError> {
+        let shared = &self.shared;
+        match shared.post_message(&notification).await? {
+            PostOutcome::SessionExpired => {
+                shared.reinitialize().await?;
+                shared.post_message(&notification).await.map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.shared.alive.load(Ordering::Acquire)
+    }
+
+    async fn close(&self) {
+        let shared = &self.shared;
+        shared.alive.store(false, Ordering::Release);
+
+        let Some(session_id) = shared.session_id.write().await.take() else {
+            return;
+        };
+
+        // Servers may answer 405 if they don't allow clients to end sessions
+        let result = shared
+            .client
+            .delete(&shared.url)
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await;
+        if let Err(e) = result {
+            tracing::debug!(server_name = %shared.server_name, "Failed to end MCP session: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::transport::SubscribedResources;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header as header_eq, method};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Answers with `result`, echoing the request's id like a real server
+    struct Reply {
+        result: Value,
+        session: Option<&'static str>,
+    }
+
+    impl Respond for Reply {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let template = ResponseTemplate::new(200).set_body_json(json!({
+                "jsonrpc": "2.0",
+                "id": body["id"],
+                "result": self.result
+            }));
+            match self.session {
+                Some(session) => template.insert_header(SESSION_ID_HEADER, session),
+                None => template,
+            }
+        }
+    }
+
+    fn server_at(url: &str) -> McpServer {
+        McpServer::test_fixture("test", url)
+    }
+
+    async fn mount_initialize(mock: &MockServer, session: &'static str) {
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({"method": "initialize"})))
+            .respond_with(Reply {
+                result: json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {},
+                    "serverInfo": {"name": "mock", "version": "1"}
+                }),
+                session: Some(session),
+            })
+            .mount(mock)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({"method": "notifications/initialized"}),
+            ))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(mock)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_session_id_resent_and_sse_response_parsed() {
+        let mock = MockServer::start().await;
+        mount_initialize(&mock, "session-1").await;
+
+        let sse_body = concat!(
+            "event: message\n",
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\",\"params\":{\"progress\":1}}\n\n",
+            "event: message\n",
+            "data: {\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"tools\":[]}}\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(header_eq(SESSION_ID_HEADER, "session-1"))
+            .and(body_partial_json(json!({"method": "tools/list"})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse_body, "text/event-stream"))
+            .mount(&mock)
+            .await;
+
+        let server = server_at(&mock.uri());
+        let (tx, mut notifications) = tokio::sync::broadcast::channel(8);
+        let transport =
+            StreamableHttpTransport::new(Client::new(), &server, BackendEvents::new(&server, tx));
+        crate::mcp::session::handshake(&transport, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(transport.session_id().await.as_deref(), Some("session-1"));
+
+        let response = transport
+            .send_request(JsonRpcRequest::new(42i64, "tools/list", None))
+            .await
+            .unwrap();
+        assert_eq!(response.id, RequestId::Number(42));
+        assert_eq!(response.result, Some(json!({"tools": []})));
+
+        let published = notifications.try_recv().unwrap();
+        assert_eq!(published.server_id, server.id);
+        assert_eq!(published.notification.method, "notifications/progress");
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_reinitializes() {
+        let mock = MockServer::start().await;
+        mount_initialize(&mock, "session-2").await;
+
+        Mock::given(method("POST"))
+            .and(header_eq(SESSION_ID_HEADER, "stale"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock)
+            .await;
+        Mock::given(method("POST"))
+            .and(header_eq(SESSION_ID_HEADER, "session-2"))
+            .and(body_partial_json(json!({"method": "ping"})))
+            .respond_with(Reply {
+                result: json!({}),
+                session: None,
+            })
+            .mount(&mock)
+            .await;
+        // The new session is subscribed to what the expired one was
+        Mock::given(method("POST"))
+            .and(header_eq(SESSION_ID_HEADER, "session-2"))
+            .and(body_partial_json(
+                json!({"method": "resources/subscribe", "params": {"uri": "log://app"}}),
+            ))
+            .respond_with(Reply {
+                result: json!({}),
+                session: None,
+            })
+            .expect(1)
+            .mount(&mock)
+            .await;
+
+        let server = server_at(&mock.uri());
+        let subscribed = Arc::new(SubscribedResources::default());
+        subscribed.insert("log://app");
+        let events = BackendEvents::detached(&server).with_subscribed(subscribed);
+        let transport = StreamableHttpTransport::new(Client::new(), &server, events);
+        crate::mcp::session::handshake(&transport, Default::default())
+            .await
+            .unwrap();
+        *transport.shared.session_id.write().await = Some("stale".to_string());
+
+        let response = transport
+            .send_request(JsonRpcRequest::new(7i64, "ping", None))
+            .await
+            .unwrap();
+        assert_eq!(response.id, RequestId::Number(7));
+        assert_eq!(transport.session_id().await.as_deref(), Some("session-2"));
+    }
+}
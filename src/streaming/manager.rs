@@ -99,6 +99,15 @@ struct ClientConnection {
     filters: EventFilters,
 }
 
+impl ClientConnection {
+    /// Queue an event the client's filters let through, without waiting
+    ///
+    /// Returns `false` if the client is gone or its queue is full.
+    fn offer(&self, event: &StreamEvent) -> bool {
+        !self.filters.should_send(event) || self.tx.try_send(event.clone()).is_ok()
+    }
+}
+
 /// Stream manager for broadcasting events to connected clients
 pub struct StreamManager {
     /// Broadcast channel for system-wide events
@@ -126,20 +135,43 @@ impl StreamManager {
         let _ = self.broadcast_tx.send(event.clone());
 
         // Also send to individual clients based on their filters
-        let clients = self.client_channels.read().await;
-        for (_, client) in clients.iter() {
-            if client.filters.should_send(&event) {
-                let _ = client.tx.send(event.clone()).await;
-            }
-        }
+        let stalled: Vec<String> = {
+            let clients = self.client_channels.read().await;
+            clients
+                .iter()
+                .filter(|(_, client)| !client.offer(&event))
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+        self.drop_stalled(stalled).await;
     }
 
     /// Send an event to a specific client
     pub async fn send_to_client(&self, client_id: &str, event: StreamEvent) {
-        let clients = self.client_channels.read().await;
-        if let Some(client) = clients.get(client_id) {
-            if client.filters.should_send(&event) {
-                let _ = client.tx.send(event).await;
+        let stalled = {
+            let clients = self.client_channels.read().await;
+            match clients.get(client_id) {
+                Some(client) => !client.offer(&event),
+                None => false,
+            }
+        };
+        if stalled {
+            self.drop_stalled(vec![client_id.to_string()]).await;
+        }
+    }
+
+    /// Disconnect clients whose channel is closed or full
+    ///
+    /// Their receivers end once drained, which closes the client's stream so
+    /// it can reconnect, instead of every sender waiting on the slowest client.
+    async fn drop_stalled(&self, client_ids: Vec<String>) {
+        if client_ids.is_empty() {
+            return;
+        }
+        let mut clients = self.client_channels.write().await;
+        for client_id in client_ids {
+            if clients.remove(&client_id).is_some() {
+                tracing::warn!(client_id = %client_id, "Disconnecting client that fell behind its stream");
             }
         }
     }
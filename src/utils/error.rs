@@ -13,6 +13,7 @@ use thiserror::Error;
 use utoipa::ToSchema;
 
 use super::security::UrlValidationError;
+use crate::mcp::protocol::{error_codes, JsonRpcError};
 
 /// Application-level errors
 #[derive(Error, Debug)]
@@ -47,12 +48,35 @@ pub enum AppError {
     #[error("Configuration error: {0}")]
     Config(String),
 
+    /// A backend MCP server broke the protocol or cannot be spoken to as configured
+    ///
+    /// Never the client's fault: malformed client messages are `BadRequest`.
     #[error("MCP protocol error: {0}")]
     McpProtocol(String),
 
     #[error("Process error: {0}")]
     Process(String),
 
+    /// A backend MCP server answered with a JSON-RPC error
+    #[error("MCP server error: {} (code: {})", .0.message, .0.code)]
+    McpServer(JsonRpcError),
+
+    /// A backend MCP server could not be reached or gave an unusable answer
+    #[error("MCP server unavailable: {0}")]
+    Upstream(String),
+
+    /// A backend MCP server did not answer in time
+    #[error("MCP server timed out: {0}")]
+    UpstreamTimeout(String),
+
+    /// A backend MCP server is skipped while its circuit breaker is open
+    #[error("MCP server circuit open: {0}")]
+    CircuitOpen(String),
+
+    /// The client gave up on a request before the backend answered
+    #[error("Request cancelled: {0}")]
+    Cancelled(String),
+
     /// OWASP API7:2023 - Server Side Request Forgery (SSRF)
     /// Security violation errors for blocked URLs and other security issues
     #[error("Security violation: {0}")]
@@ -78,9 +102,62 @@ pub struct ErrorResponse {
     pub details: Option<String>,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message, details) = match &self {
+impl AppError {
+    /// JSON-RPC error code this error is reported with on the MCP gateway
+    pub fn json_rpc_code(&self) -> i32 {
+        match self {
+            AppError::BadRequest(_) => error_codes::INVALID_REQUEST,
+            AppError::Validation(_) | AppError::SecurityViolation(_) => {
+                error_codes::INVALID_PARAMS
+            }
+            AppError::Unauthorized(_) | AppError::Forbidden(_) | AppError::Jwt(_) => {
+                error_codes::UNAUTHORIZED
+            }
+            AppError::NotFound(_) => error_codes::NOT_FOUND,
+            AppError::Conflict(_) => error_codes::CONFLICT,
+            AppError::McpServer(err) => err.code,
+            AppError::Upstream(_) | AppError::McpProtocol(_) => error_codes::UPSTREAM_ERROR,
+            AppError::UpstreamTimeout(_) => error_codes::UPSTREAM_TIMEOUT,
+            AppError::CircuitOpen(_) => error_codes::CIRCUIT_OPEN,
+            AppError::Cancelled(_) => error_codes::REQUEST_CANCELLED,
+            AppError::Internal(_)
+            | AppError::Database(_)
+            | AppError::Config(_)
+            | AppError::Process(_) => error_codes::SERVER_ERROR,
+        }
+    }
+
+    /// This error as a JSON-RPC error object
+    ///
+    /// `data` holds the same fields as the REST [`ErrorResponse`]. Errors a
+    /// backend sent are passed through as they were.
+    pub fn to_json_rpc_error(&self) -> JsonRpcError {
+        if let AppError::McpServer(err) = self {
+            return err.clone();
+        }
+
+        let (status, error_message, details) = self.classify();
+        // Internal errors keep their details out of the response
+        let message = match details {
+            Some(_) => self.to_string(),
+            None => error_message.to_string(),
+        };
+        let data = ErrorResponse {
+            error: error_message.to_string(),
+            status: status.as_u16(),
+            details,
+        };
+
+        JsonRpcError {
+            code: self.json_rpc_code(),
+            message,
+            data: serde_json::to_value(data).ok(),
+        }
+    }
+
+    /// Status, label and client-facing details of this error
+    fn classify(&self) -> (StatusCode, &'static str, Option<String>) {
+        match self {
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "Unauthorized", Some(msg.clone())),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "Forbidden", Some(msg.clone())),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "Not Found", Some(msg.clone())),
@@ -103,17 +180,45 @@ impl IntoResponse for AppError {
                 tracing::error!("Configuration error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Configuration Error", None)
             }
-            AppError::McpProtocol(msg) => (StatusCode::BAD_REQUEST, "MCP Protocol Error", Some(msg.clone())),
+            AppError::McpProtocol(msg) => (StatusCode::BAD_GATEWAY, "MCP Protocol Error", Some(msg.clone())),
             AppError::Process(msg) => {
                 tracing::error!("Process error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Process Error", None)
             }
+            AppError::McpServer(err) => {
+                let status = match err.code {
+                    // Invalid params, which includes unknown tools and bad arguments
+                    -32602 => StatusCode::BAD_REQUEST,
+                    -32601 => StatusCode::NOT_IMPLEMENTED,
+                    _ => StatusCode::BAD_GATEWAY,
+                };
+                (status, "MCP Server Error", Some(format!("{} (code: {})", err.message, err.code)))
+            }
+            AppError::Upstream(msg) => (StatusCode::BAD_GATEWAY, "Bad Gateway", Some(msg.clone())),
+            AppError::UpstreamTimeout(msg) => {
+                (StatusCode::GATEWAY_TIMEOUT, "Gateway Timeout", Some(msg.clone()))
+            }
+            AppError::CircuitOpen(msg) => {
+                (StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable", Some(msg.clone()))
+            }
+            // 499 Client Closed Request, as used by nginx
+            AppError::Cancelled(msg) => (
+                StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+                "Request Cancelled",
+                Some(msg.clone()),
+            ),
             // OWASP API7:2023 - Security violations return 422 Unprocessable Entity
             // to indicate the request was understood but cannot be processed for security reasons
             AppError::SecurityViolation(msg) => {
                 (StatusCode::UNPROCESSABLE_ENTITY, "Security Violation", Some(msg.clone()))
             }
-        };
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_message, details) = self.classify();
 
         let body = ErrorResponse {
             error: error_message.to_string(),
@@ -127,3 +232,37 @@ impl IntoResponse for AppError {
 
 /// Result type alias for application operations
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_rpc_error_mapping() {
+        let error = AppError::UpstreamTimeout("github".to_string()).to_json_rpc_error();
+        assert_eq!(error.code, error_codes::UPSTREAM_TIMEOUT);
+        let data = error.data.unwrap();
+        assert_eq!(data["status"], 504);
+        assert_eq!(data["details"], "github");
+
+        // Internal details stay out of the message
+        let error = AppError::Internal("secret".to_string()).to_json_rpc_error();
+        assert_eq!(error.code, error_codes::SERVER_ERROR);
+        assert!(!error.message.contains("secret"));
+
+        let backend = JsonRpcError {
+            code: -32602,
+            message: "Unknown tool".to_string(),
+            data: None,
+        };
+        let error = AppError::McpServer(backend).to_json_rpc_error();
+        assert_eq!((error.code, error.message.as_str()), (-32602, "Unknown tool"));
+
+        // A backend breaking the protocol is not the client's invalid request
+        let error = AppError::McpProtocol("repeated cursor".to_string()).to_json_rpc_error();
+        assert_eq!(error.code, error_codes::UPSTREAM_ERROR);
+        assert_eq!(error.data.unwrap()["status"], 502);
+        let error = AppError::BadRequest("no method".to_string()).to_json_rpc_error();
+        assert_eq!(error.code, error_codes::INVALID_REQUEST);
+    }
+}
@@ -1,6 +1,7 @@
 //! Utility modules
 
 pub mod error;
+pub mod patterns;
 pub mod security;
 
 pub use error::{AppError, AppResult, ErrorResponse};
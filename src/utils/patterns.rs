@@ -0,0 +1,50 @@
+//! Compiled regular expressions shared across requests
+//!
+//! Tool schemas and resource templates are matched on every call, so the
+//! patterns they give are compiled once and kept for later matches.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// Compiled patterns kept before the cache starts over
+const MAX_PATTERNS: usize = 1024;
+
+/// Patterns that do not compile are kept as `None`, so they are not retried
+static PATTERNS: LazyLock<Mutex<HashMap<String, Option<Regex>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `pattern` compiled, from the cache when it was compiled before
+///
+/// Returns `None` if `pattern` is not a valid regular expression.
+pub fn compiled(pattern: &str) -> Option<Regex> {
+    let mut patterns = PATTERNS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(regex) = patterns.get(pattern) {
+        return regex.clone();
+    }
+    // Backends that generate their schemas must not grow the cache forever
+    if patterns.len() >= MAX_PATTERNS {
+        patterns.clear();
+    }
+    let regex = Regex::new(pattern).ok();
+    patterns.insert(pattern.to_string(), regex.clone());
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_are_compiled_once() {
+        let regex = compiled("^cached-[a-z]+$").unwrap();
+        assert!(regex.is_match("cached-abc"));
+        assert!(PATTERNS.lock().unwrap().contains_key("^cached-[a-z]+$"));
+        assert!(!compiled("^cached-[a-z]+$").unwrap().is_match("cached-1"));
+
+        // A pattern that does not compile is remembered and still refused
+        assert!(compiled("(unclosed").is_none());
+        assert!(PATTERNS.lock().unwrap()["(unclosed"].is_none());
+        assert!(compiled("(unclosed").is_none());
+    }
+}
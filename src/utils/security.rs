@@ -126,13 +126,11 @@ fn is_localhost(host: &str) -> bool {
 fn is_cloud_metadata_endpoint(host: &str) -> bool {
     let host_lower = host.to_lowercase();
 
-    // AWS EC2 metadata
+    // AWS EC2 and Azure metadata
     host_lower == "169.254.169.254"
         // GCP metadata
         || host_lower == "metadata.google.internal"
         || host_lower == "metadata.goog"
-        // Azure metadata
-        || host_lower == "169.254.169.254"
         // Kubernetes
         || host_lower == "kubernetes.default"
         || host_lower == "kubernetes.default.svc"
@@ -334,7 +332,7 @@ mod tests {
     fn test_cloud_metadata_blocked() {
         assert!(matches!(
             validate_url_for_ssrf("http://169.254.169.254/latest/meta-data/"),
-            Err(UrlValidationError::LinkLocalBlocked)
+            Err(UrlValidationError::MetadataEndpointBlocked)
         ));
         assert!(matches!(
             validate_url_for_ssrf("http://metadata.google.internal"),
@@ -0,0 +1,4 @@
+//! Integration test entry point
+
+#[path = "integration/mod.rs"]
+mod integration;
@@ -3,7 +3,7 @@
 //! These tests require a running database. They test the complete
 //! authentication flow from API key creation to JWT token validation.
 
-use metamcp::auth::{AuthService, JwtService, ApiKeyEncryption};
+use metamcp::auth::AuthService;
 use metamcp::db::Database;
 use std::sync::Arc;
 
@@ -3,13 +3,50 @@
 //! These tests verify the MCP proxy can correctly forward requests
 //! to backend MCP servers.
 
-use metamcp::mcp::{McpProxy, McpServerManager, McpServerConfig};
+use metamcp::mcp::{
+    BackendSession, BreakerState, CancelSignal, McpProxy, McpServerManager, McpServerConfig,
+};
+use metamcp::utils::AppError;
 use metamcp::db::models::McpServer;
-use serde_json::json;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use uuid::Uuid;
-use wiremock::{MockServer, Mock, ResponseTemplate};
-use wiremock::matchers::{method, path, body_json};
+use wiremock::{MockServer, Mock, Request, Respond, ResponseTemplate};
+use wiremock::matchers::{method, body_partial_json};
+
+/// Answers with `result`, echoing the request's id
+struct Reply(Value);
+
+impl Respond for Reply {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": body["id"],
+            "result": self.0
+        }))
+    }
+}
+
+/// Answer the initialize handshake the proxy runs before its first request
+async fn mount_handshake(mock_server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "initialize"})))
+        .respond_with(Reply(json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"tools": {}, "resources": {}, "prompts": {}},
+            "serverInfo": {"name": "mock", "version": "1"}
+        })))
+        .with_priority(1)
+        .mount(mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "notifications/initialized"})))
+        .respond_with(ResponseTemplate::new(202))
+        .with_priority(1)
+        .mount(mock_server)
+        .await;
+}
 
 /// Create a mock MCP server for testing
 fn create_mock_mcp_server(url: &str) -> McpServer {
@@ -24,39 +61,84 @@ fn create_mock_mcp_server(url: &str) -> McpServer {
         is_active: true,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
+        namespace: "mock".to_string(),
+        tool_renames: None,
+        tool_aliases: None,
+        allow_sampling: true,
+        allow_elicitation: true,
+        roots: None,
+        roots_scope: None,
+        output_validation: Default::default(),
+        connect_timeout_ms: 10_000,
+        request_timeout_ms: 30_000,
+        max_retries: 2,
+        retry_backoff_ms: 200,
+        breaker_threshold: 5,
+        breaker_cooldown_secs: 30,
     }
 }
 
+#[tokio::test]
+async fn test_mcp_proxy_only_open_sessions_count_for_capabilities() {
+    let mock_server = MockServer::start().await;
+    mount_handshake(&mock_server).await;
+    let proxy = McpProxy::new();
+    let server = create_mock_mcp_server(&mock_server.uri());
+
+    // Nothing is connected just to answer the question
+    assert!(!proxy.any_session(BackendSession::supports_completions).await);
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+
+    // A fleet without a completions-capable backend does not offer completions
+    proxy.session(&server).await.unwrap();
+    assert!(proxy.any_session(BackendSession::supports_tools).await);
+    assert!(!proxy.any_session(BackendSession::supports_completions).await);
+
+    let completing = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "initialize"})))
+        .respond_with(Reply(json!({
+            "protocolVersion": "2025-03-26",
+            "capabilities": {"completions": {}},
+            "serverInfo": {"name": "mock", "version": "1"}
+        })))
+        .mount(&completing)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "notifications/initialized"})))
+        .respond_with(ResponseTemplate::new(202))
+        .mount(&completing)
+        .await;
+    proxy
+        .session(&create_mock_mcp_server(&completing.uri()))
+        .await
+        .unwrap();
+    assert!(proxy.any_session(BackendSession::supports_completions).await);
+}
+
 #[tokio::test]
 async fn test_mcp_proxy_list_tools() {
     // Start mock server
     let mock_server = MockServer::start().await;
 
+    mount_handshake(&mock_server).await;
+
     // Set up mock response for tools/list
     Mock::given(method("POST"))
-        .and(body_json(json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "method": "tools/list",
-            "params": null
-        })))
-        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "result": {
-                "tools": [
-                    {
-                        "name": "echo",
-                        "description": "Echoes input",
-                        "inputSchema": {"type": "object"}
-                    },
-                    {
-                        "name": "add",
-                        "description": "Adds numbers",
-                        "inputSchema": {"type": "object"}
-                    }
-                ]
-            }
+        .and(body_partial_json(json!({"method": "tools/list"})))
+        .respond_with(Reply(json!({
+            "tools": [
+                {
+                    "name": "echo",
+                    "description": "Echoes input",
+                    "inputSchema": {"type": "object"}
+                },
+                {
+                    "name": "add",
+                    "description": "Adds numbers",
+                    "inputSchema": {"type": "object"}
+                }
+            ]
         })))
         .mount(&mock_server)
         .await;
@@ -73,21 +155,50 @@ async fn test_mcp_proxy_list_tools() {
     assert!(tools.iter().any(|t| t.get("name").and_then(|n| n.as_str()) == Some("add")));
 }
 
+#[tokio::test]
+async fn test_mcp_proxy_list_tools_follows_cursors() {
+    let mock_server = MockServer::start().await;
+    mount_handshake(&mock_server).await;
+
+    // Second page, asked for with the first page's cursor
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "tools/list", "params": {"cursor": "page-2"}})))
+        .respond_with(Reply(json!({
+            "tools": [{"name": "add", "inputSchema": {"type": "object"}}]
+        })))
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"method": "tools/list"})))
+        .respond_with(Reply(json!({
+            "tools": [{"name": "echo", "inputSchema": {"type": "object"}}],
+            "nextCursor": "page-2"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let proxy = McpProxy::new();
+    let server = create_mock_mcp_server(&mock_server.uri());
+
+    let tools = proxy.list_tools(&server).await.expect("Failed to list tools");
+
+    let names: Vec<_> = tools.iter().filter_map(|t| t["name"].as_str()).collect();
+    assert_eq!(names, ["echo", "add"]);
+}
+
 #[tokio::test]
 async fn test_mcp_proxy_call_tool() {
     let mock_server = MockServer::start().await;
+    mount_handshake(&mock_server).await;
 
     // Set up mock response for tools/call
     Mock::given(method("POST"))
-        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "result": {
-                "content": [{
-                    "type": "text",
-                    "text": "Hello, World!"
-                }]
-            }
+        .respond_with(Reply(json!({
+            "content": [{
+                "type": "text",
+                "text": "Hello, World!"
+            }]
         })))
         .mount(&mock_server)
         .await;
@@ -96,30 +207,33 @@ async fn test_mcp_proxy_call_tool() {
     let server = create_mock_mcp_server(&mock_server.uri());
 
     let result = proxy
-        .call_tool(&server, "echo", json!({"message": "Hello, World!"}))
+        .call_tool(
+            &server,
+            "echo",
+            json!({"message": "Hello, World!"}),
+            None,
+            CancelSignal::never(),
+        )
         .await
         .expect("Failed to call tool");
 
-    assert!(result.get("content").is_some());
+    assert_eq!(result.content.len(), 1);
 }
 
 #[tokio::test]
 async fn test_mcp_proxy_list_resources() {
     let mock_server = MockServer::start().await;
+    mount_handshake(&mock_server).await;
 
     Mock::given(method("POST"))
-        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "result": {
-                "resources": [
-                    {
-                        "uri": "file:///test.txt",
-                        "name": "Test File",
-                        "mimeType": "text/plain"
-                    }
-                ]
-            }
+        .respond_with(Reply(json!({
+            "resources": [
+                {
+                    "uri": "file:///test.txt",
+                    "name": "Test File",
+                    "mimeType": "text/plain"
+                }
+            ]
         })))
         .mount(&mock_server)
         .await;
@@ -138,19 +252,16 @@ async fn test_mcp_proxy_list_resources() {
 #[tokio::test]
 async fn test_mcp_proxy_list_prompts() {
     let mock_server = MockServer::start().await;
+    mount_handshake(&mock_server).await;
 
     Mock::given(method("POST"))
-        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "jsonrpc": "2.0",
-            "id": 1,
-            "result": {
-                "prompts": [
-                    {
-                        "name": "code_review",
-                        "description": "Review code"
-                    }
-                ]
-            }
+        .respond_with(Reply(json!({
+            "prompts": [
+                {
+                    "name": "code_review",
+                    "description": "Review code"
+                }
+            ]
         })))
         .mount(&mock_server)
         .await;
@@ -200,6 +311,64 @@ async fn test_mcp_proxy_connection_error() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_mcp_proxy_circuit_opens_after_failures() {
+    let proxy = McpProxy::new();
+
+    let mut server = create_mock_mcp_server("http://localhost:59999");
+    server.max_retries = 0;
+    server.breaker_threshold = 2;
+
+    for _ in 0..2 {
+        let result = proxy.list_tools(&server).await;
+        assert!(matches!(result, Err(AppError::Upstream(_))));
+    }
+
+    // The backend is no longer tried
+    let result = proxy.list_tools(&server).await;
+    assert!(matches!(result, Err(AppError::CircuitOpen(_))));
+
+    let status = proxy.breaker_status(&server).await;
+    assert_eq!(status.state, BreakerState::Open);
+    assert_eq!(status.consecutive_failures, 2);
+
+    // Updating a server resets its breaker
+    proxy.disconnect(server.id).await;
+    assert_eq!(proxy.breaker_status(&server).await.state, BreakerState::Closed);
+}
+
+#[tokio::test]
+async fn test_mcp_proxy_retries_count_once_towards_the_breaker() {
+    let proxy = McpProxy::new();
+
+    let mut server = create_mock_mcp_server("http://localhost:59999");
+    server.max_retries = 2;
+    server.retry_backoff_ms = 1;
+    server.breaker_threshold = 2;
+    server.breaker_cooldown_secs = 0;
+
+    // Three attempts make one failed call
+    let result = proxy.list_tools(&server).await;
+    assert!(matches!(result, Err(AppError::Upstream(_))));
+    let status = proxy.breaker_status(&server).await;
+    assert_eq!(status.state, BreakerState::Closed);
+    assert_eq!(status.consecutive_failures, 1);
+
+    let result = proxy.list_tools(&server).await;
+    assert!(matches!(result, Err(AppError::Upstream(_))));
+    assert_eq!(proxy.breaker_status(&server).await.consecutive_failures, 2);
+
+    // Without a cooldown the circuit is half-open at once; a failed probe
+    // reports what the backend did rather than the circuit it reopened
+    assert_eq!(
+        proxy.breaker_status(&server).await.state,
+        BreakerState::HalfOpen
+    );
+    let result = proxy.list_tools(&server).await;
+    assert!(matches!(result, Err(AppError::Upstream(_))));
+    assert_eq!(proxy.breaker_status(&server).await.consecutive_failures, 3);
+}
+
 #[tokio::test]
 async fn test_mcp_proxy_unsupported_protocol() {
     let proxy = McpProxy::new();
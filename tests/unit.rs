@@ -0,0 +1,4 @@
+//! Unit test entry point
+
+#[path = "unit/mod.rs"]
+mod unit;
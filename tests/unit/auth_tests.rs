@@ -1,6 +1,6 @@
 //! Unit tests for authentication module
 
-use metamcp::auth::{Claims, JwtService};
+use metamcp::auth::JwtService;
 
 #[test]
 fn test_jwt_service_creation() {
@@ -1,13 +1,13 @@
 //! Unit tests for MCP protocol types
 
-use serde_json::json;
+use serde_json::{json, Map};
 
 // Test JSON-RPC structures
 #[cfg(test)]
 mod jsonrpc_tests {
     use super::*;
     use metamcp::mcp::protocol::{
-        JsonRpcRequest, JsonRpcResponse, JsonRpcError, RequestId,
+        JsonRpcRequest, JsonRpcResponse, RequestId,
         JSONRPC_VERSION, MCP_PROTOCOL_VERSION,
     };
 
@@ -18,7 +18,7 @@ mod jsonrpc_tests {
 
     #[test]
     fn test_mcp_protocol_version() {
-        assert_eq!(MCP_PROTOCOL_VERSION, "2024-11-05");
+        assert_eq!(MCP_PROTOCOL_VERSION, "2025-06-18");
     }
 
     #[test]
@@ -120,11 +120,10 @@ mod jsonrpc_tests {
 // Test MCP capability structures
 #[cfg(test)]
 mod capability_tests {
-    use super::*;
     use metamcp::mcp::protocol::{
         ClientCapabilities, ServerCapabilities,
         ToolsCapability, ResourcesCapability, PromptsCapability,
-        RootsCapability, SamplingCapability,
+        CompletionsCapability,
     };
 
     #[test]
@@ -140,6 +139,7 @@ mod capability_tests {
         assert!(caps.tools.is_none());
         assert!(caps.resources.is_none());
         assert!(caps.prompts.is_none());
+        assert!(caps.completions.is_none());
     }
 
     #[test]
@@ -166,6 +166,20 @@ mod capability_tests {
         let json = serde_json::to_string(&caps).expect("Failed to serialize");
         assert!(json.contains("\"listChanged\":true"));
     }
+
+    #[test]
+    fn test_completions_capability() {
+        let caps = ServerCapabilities {
+            completions: Some(CompletionsCapability {}),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&caps).expect("Failed to serialize");
+        assert_eq!(json["completions"], serde_json::json!({}));
+
+        let parsed: ServerCapabilities =
+            serde_json::from_value(serde_json::json!({"completions": {}})).unwrap();
+        assert!(parsed.completions.is_some());
+    }
 }
 
 // Test MCP tool structures
@@ -178,6 +192,7 @@ mod tool_tests {
     fn test_tool_definition() {
         let tool = Tool {
             name: "test_tool".to_string(),
+            title: None,
             description: Some("A test tool".to_string()),
             input_schema: json!({
                 "type": "object",
@@ -185,6 +200,10 @@ mod tool_tests {
                     "arg1": {"type": "string"}
                 }
             }),
+            output_schema: None,
+            annotations: None,
+            meta: None,
+            extra: Map::new(),
         };
 
         assert_eq!(tool.name, "test_tool");
@@ -195,8 +214,13 @@ mod tool_tests {
     fn test_tool_serialization() {
         let tool = Tool {
             name: "echo".to_string(),
+            title: None,
             description: Some("Echo tool".to_string()),
             input_schema: json!({"type": "object"}),
+            output_schema: None,
+            annotations: None,
+            meta: None,
+            extra: Map::new(),
         };
 
         let json = serde_json::to_string(&tool).expect("Failed to serialize");
@@ -210,6 +234,7 @@ mod tool_tests {
         let params = ToolCallParams {
             name: "test".to_string(),
             arguments: json!({"key": "value"}),
+            meta: None,
         };
 
         let json = serde_json::to_string(&params).expect("Failed to serialize");
@@ -219,9 +244,7 @@ mod tool_tests {
 
     #[test]
     fn test_content_text() {
-        let content = Content::Text {
-            text: "Hello, world!".to_string(),
-        };
+        let content = Content::text("Hello, world!");
 
         let json = serde_json::to_string(&content).expect("Failed to serialize");
         assert!(json.contains("\"type\":\"text\""));
@@ -231,10 +254,11 @@ mod tool_tests {
     #[test]
     fn test_tool_call_result() {
         let result = ToolCallResult {
-            content: vec![Content::Text {
-                text: "Result".to_string(),
-            }],
+            content: vec![Content::text("Result")],
+            structured_content: None,
             is_error: false,
+            meta: None,
+            extra: Map::new(),
         };
 
         assert!(!result.is_error);
@@ -256,8 +280,13 @@ mod resource_prompt_tests {
         let resource = Resource {
             uri: "file:///test.txt".to_string(),
             name: "Test File".to_string(),
+            title: None,
             description: Some("A test file".to_string()),
             mime_type: Some("text/plain".to_string()),
+            size: None,
+            annotations: None,
+            meta: None,
+            extra: Map::new(),
         };
 
         assert_eq!(resource.uri, "file:///test.txt");
@@ -271,6 +300,8 @@ mod resource_prompt_tests {
             mime_type: Some("text/plain".to_string()),
             text: Some("File content".to_string()),
             blob: None,
+            meta: None,
+            extra: Map::new(),
         };
 
         assert_eq!(content.text, Some("File content".to_string()));
@@ -284,16 +315,27 @@ mod resource_prompt_tests {
                 Resource {
                     uri: "file:///a.txt".to_string(),
                     name: "A".to_string(),
+                    title: None,
                     description: None,
                     mime_type: None,
+                    size: None,
+                    annotations: None,
+                    meta: None,
+                    extra: Map::new(),
                 },
                 Resource {
                     uri: "file:///b.txt".to_string(),
                     name: "B".to_string(),
+                    title: None,
                     description: None,
                     mime_type: None,
+                    size: None,
+                    annotations: None,
+                    meta: None,
+                    extra: Map::new(),
                 },
             ],
+            next_cursor: None,
         };
 
         assert_eq!(result.resources.len(), 2);
@@ -303,14 +345,18 @@ mod resource_prompt_tests {
     fn test_prompt_definition() {
         let prompt = Prompt {
             name: "test_prompt".to_string(),
+            title: None,
             description: Some("A test prompt".to_string()),
             arguments: vec![
                 PromptArgument {
                     name: "arg1".to_string(),
+                    title: None,
                     description: Some("First argument".to_string()),
                     required: true,
                 },
             ],
+            meta: None,
+            extra: Map::new(),
         };
 
         assert_eq!(prompt.name, "test_prompt");
@@ -324,10 +370,14 @@ mod resource_prompt_tests {
             prompts: vec![
                 Prompt {
                     name: "prompt1".to_string(),
+                    title: None,
                     description: None,
                     arguments: vec![],
+                    meta: None,
+                    extra: Map::new(),
                 },
             ],
+            next_cursor: None,
         };
 
         assert_eq!(result.prompts.len(), 1);
@@ -337,7 +387,6 @@ mod resource_prompt_tests {
 // Test initialize structures
 #[cfg(test)]
 mod initialize_tests {
-    use super::*;
     use metamcp::mcp::protocol::{
         InitializeParams, InitializeResult,
         ClientInfo, ServerInfo as McpServerInfo,
@@ -391,3 +440,61 @@ mod initialize_tests {
         assert!(json.contains("\"clientInfo\""));
     }
 }
+
+// Test resource templates
+#[cfg(test)]
+mod resource_template_tests {
+    use metamcp::mcp::protocol::ResourceTemplate;
+
+    fn template(uri_template: &str) -> ResourceTemplate {
+        ResourceTemplate {
+            uri_template: uri_template.to_string(),
+            name: "test".to_string(),
+            title: None,
+            description: None,
+            mime_type: None,
+            annotations: None,
+            meta: None,
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_simple_expression_matches_one_segment() {
+        let t = template("db://{table}/{id}");
+        assert!(t.matches("db://users/42"));
+        assert!(!t.matches("db://users/42/extra"));
+        assert!(!t.matches("file:///users/42"));
+    }
+
+    #[test]
+    fn test_reserved_expression_matches_paths() {
+        let t = template("file:///{+path}");
+        assert!(t.matches("file:///home/user/notes.txt"));
+        assert!(!template("file:///{path}").matches("file:///home/user/notes.txt"));
+    }
+
+    #[test]
+    fn test_long_uris_match_in_linear_time() {
+        let t = template("x://{+a}-{+b}-{+c}!");
+        let uri = format!("x://{}", "-".repeat(100_000));
+        assert!(!t.matches(&uri));
+        assert!(t.matches(&format!("{}!", uri)));
+        let dots = format!("db://{}/", ".".repeat(100_000));
+        assert!(!template("db://{table}.{id}").matches(&dots));
+    }
+
+    #[test]
+    fn test_unclosed_expression_never_matches() {
+        assert!(!template("db://{table").matches("db://{table"));
+        assert!(template("db://a.b/{id}").matches("db://a.b/42"));
+        assert!(!template("db://a.b/{id}").matches("db://axb/42"));
+    }
+
+    #[test]
+    fn test_resource_template_serialization() {
+        let json = serde_json::to_value(template("db://{table}")).unwrap();
+        assert_eq!(json["uriTemplate"], "db://{table}");
+        assert!(json.get("mimeType").is_none());
+    }
+}
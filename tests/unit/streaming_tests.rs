@@ -213,9 +213,8 @@ fn test_event_filters_combined() {
 
 #[test]
 fn test_stream_manager_new() {
-    let manager = StreamManager::new();
     // Just verify it can be created
-    assert!(true);
+    let _manager = StreamManager::new();
 }
 
 #[tokio::test]
@@ -256,9 +255,8 @@ async fn test_stream_manager_multiple_clients() {
 #[tokio::test]
 async fn test_stream_manager_subscribe() {
     let manager = StreamManager::new();
-    let _rx = manager.subscribe();
     // Verify subscription works without panic
-    assert!(true);
+    let _rx = manager.subscribe();
 }
 
 #[tokio::test]
@@ -270,9 +268,6 @@ async fn test_stream_manager_server_registration() {
 
     // Unregister one
     manager.unregister_server("srv-1").await;
-
-    // Verify no panic
-    assert!(true);
 }
 
 #[tokio::test]
@@ -345,7 +340,33 @@ async fn test_stream_manager_handle_mcp_event() {
         status: "success".to_string(),
     };
     manager.handle_mcp_event("srv-1".to_string(), event).await;
+}
+
+#[tokio::test]
+async fn test_stream_manager_disconnects_slow_client() {
+    let manager = StreamManager::new();
+    let (slow_id, mut slow_rx) = manager.register_client(EventFilters::default()).await;
+    let (_fast_id, mut fast_rx) = manager.register_client(EventFilters::default()).await;
+
+    let event = || StreamEvent::Error {
+        code: "E".to_string(),
+        message: "boom".to_string(),
+    };
+    // Fill both queues, then drain only one of them
+    for _ in 0..256 {
+        manager.broadcast(event()).await;
+    }
+    while fast_rx.try_recv().is_ok() {}
 
-    // Verify no panic
-    assert!(true);
+    // Neither queue being full makes this wait
+    tokio::time::timeout(std::time::Duration::from_secs(1), manager.broadcast(event()))
+        .await
+        .unwrap();
+    assert_eq!(manager.client_count().await, 1);
+    assert!(fast_rx.try_recv().is_ok());
+
+    // The slow client's stream ends once it caught up
+    while slow_rx.recv().await.is_some() {}
+    manager.send_to_client(&slow_id, event()).await;
+    assert_eq!(manager.client_count().await, 1);
 }